@@ -1,4 +1,4 @@
-use jsonwebtoken::{encode, EncodingKey, Header};
+use api::routes::token_keys::TokenKeys;
 use serde::Serialize;
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 
@@ -21,9 +21,15 @@ fn main() {
         provider: "manual".to_string(),
     };
 
-    let secret = std::env::var("JWT_SECRET").unwrap();
-    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap();
+    // same key directory (JWT_KEYS_DIR) the API signs with
+    let keys = TokenKeys::from_env().unwrap();
+
+    // `gen_jwt --pq`: ML-DSA signed, accepted by post-quantum-only routes
+    let token = if std::env::args().any(|arg| arg == "--pq") {
+        keys.sign_post_quantum(&claims).unwrap()
+    } else {
+        keys.sign(&claims).unwrap()
+    };
 
     println!("{}", token);
 }
-
@@ -0,0 +1,136 @@
+//! Manage service accounts and their API keys straight from the database.
+//!
+//!   service_account create <name> [description] [--org <org id>]
+//!   service_account list [--org <org id>]
+//!   service_account issue-key <name> <scope,scope,...> [--expires-days N]
+//!   service_account keys <name>
+//!   service_account revoke-key <prefix>
+//!   service_account disable <name>
+//!
+//! Changes go on the audit trail as `cli:$USER`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use api::routes::audit::{cli_actor, Audit, AuditEntry};
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::service_accounts::{generate_api_key, is_valid_account_name, validate_scopes};
+use db::models::ServiceAccount;
+use db::queries;
+
+const USAGE: &str = "usage: service_account <create|list|issue-key|keys|revoke-key|disable> ...";
+
+async fn account(pool: &PgPool, name: &str) -> Result<ServiceAccount> {
+    queries::get_service_account_by_name(pool, name)
+        .await?
+        .ok_or_else(|| anyhow!("No service account named {}", name))
+}
+
+/// Add what was done to the audit trail, as `cli:<user>`; the change is
+/// made either way, so a failed write is only reported
+async fn record(pool: &PgPool, entry: AuditEntry) {
+    if let Err(err) = Audit::new(pool.clone()).try_record(&cli_actor(), entry).await {
+        eprintln!("⚠️ Audit write failed: {}", err);
+    }
+}
+
+/// `--org <org id>`, else the default organization
+fn org(args: &[String]) -> Result<Uuid> {
+    match args.iter().position(|a| a == "--org") {
+        Some(i) => args
+            .get(i + 1)
+            .ok_or_else(|| anyhow!(USAGE))?
+            .parse()
+            .context("--org takes an organization id"),
+        None => Ok(DEFAULT_ORG_ID),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let arg = |i: usize| args.get(i).map(String::as_str).ok_or_else(|| anyhow!(USAGE));
+
+    let url = std::env::var("DATABASE_URL").context("DATABASE_URL must be set")?;
+    let pool = PgPool::connect(&url).await?;
+
+    match arg(0)? {
+        "create" => {
+            let name = arg(1)?;
+            if !is_valid_account_name(name) {
+                bail!("Service account names use a-z, 0-9, '-' and '_'");
+            }
+            let description = args.get(2).map(String::as_str).filter(|a| !a.starts_with("--")).unwrap_or_default();
+            let org_id = org(&args)?;
+            let account = queries::create_service_account(&pool, org_id, name, description, None).await?;
+            record(&pool, AuditEntry::new("service_account.create", "service_account", account.id).in_org(org_id).created(&account)).await;
+            println!("🤖 Created service account {} ({})", account.name, account.id);
+        }
+        "list" => {
+            for account in queries::get_service_accounts(&pool, org(&args)?).await? {
+                let state = if account.disabled_at.is_some() { "disabled" } else { "active" };
+                println!("{}  {:<24} {:<8} {}", account.id, account.name, state, account.description);
+            }
+        }
+        "issue-key" => {
+            let account = account(&pool, arg(1)?).await?;
+            let scopes: Vec<String> = arg(2)?.split(',').map(|s| s.trim().to_string()).collect();
+            validate_scopes(&scopes).map_err(|msg| anyhow!(msg))?;
+            let expires_at = match args.iter().position(|a| a == "--expires-days") {
+                Some(i) => {
+                    let days: i64 = arg(i + 1)?.parse().context("--expires-days takes a number")?;
+                    if days <= 0 {
+                        bail!("expires_in_days must be positive");
+                    }
+                    Some(chrono::Utc::now() + chrono::Duration::days(days))
+                }
+                None => None,
+            };
+            if account.disabled_at.is_some() {
+                bail!("Service account {} is disabled", account.name);
+            }
+
+            let generated = generate_api_key();
+            let api_key =
+                queries::create_api_key(&pool, account.id, &generated.prefix, &generated.hash, &scopes, expires_at).await?;
+            record(&pool, AuditEntry::new("api_key.create", "service_account", account.id).created(&api_key)).await;
+            eprintln!("🔑 Issued {} for {}; store it now, it can't be shown again", generated.prefix, account.name);
+            println!("{}", generated.key);
+        }
+        "keys" => {
+            let account = account(&pool, arg(1)?).await?;
+            for key in queries::get_api_keys(&pool, account.id).await? {
+                let state = match (key.revoked_at, key.expires_at) {
+                    (Some(_), _) => "revoked".to_string(),
+                    (None, Some(expires_at)) => format!("expires {}", expires_at.format("%Y-%m-%d")),
+                    (None, None) => "active".to_string(),
+                };
+                let last_used = key.last_used_at.map_or("never".to_string(), |at| at.to_rfc3339());
+                println!("{}  {:<18} last used {}  [{}]", key.prefix, state, last_used, key.scopes.join(","));
+            }
+        }
+        "revoke-key" => {
+            let key = queries::get_api_key_by_prefix(&pool, arg(1)?)
+                .await?
+                .ok_or_else(|| anyhow!("No API key with prefix {}", arg(1).unwrap_or_default()))?;
+            if queries::revoke_api_key(&pool, key.service_account_id, key.id).await? == 0 {
+                bail!("{} was already revoked", key.prefix);
+            }
+            record(&pool, AuditEntry::new("api_key.revoke", "service_account", key.service_account_id).detail(json!({ "key_id": key.id }))).await;
+            println!("🔒 Revoked {}", key.prefix);
+        }
+        "disable" => {
+            let before = account(&pool, arg(1)?).await?;
+            if queries::disable_service_account(&pool, before.id).await? {
+                let after = account(&pool, &before.name).await?;
+                record(&pool, AuditEntry::new("service_account.disable", "service_account", before.id).updated(&before, &after)).await;
+            }
+            println!("🔒 Disabled {} and revoked its keys", before.name);
+        }
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
@@ -1,15 +1,89 @@
 pub mod routes;
+#[cfg(any(test, feature = "mock-oauth"))]
+pub mod mock_oauth;
 
-use axum::{Router, Extension};
+use axum::{middleware, Router, Extension};
 use sqlx::PgPool;
+use std::sync::Arc;
 
-/// Shared app router — used by both main.rs and tests
-pub fn app_routes(pool: PgPool) -> Router {
+use routes::audit::{assign_request_id, audit_routes};
+use routes::auth::{auth_routes, AuthState};
+use routes::auth_middleware::{require_auth, require_post_quantum, require_recent_mfa};
+use routes::commsec::{commsec_key_routes, commsec_traffic_routes, CommsecState};
+use routes::mfa::mfa_routes;
+use routes::orgs::org_routes;
+use routes::rate_limit::{rate_limit_by_caller, rate_limit_by_ip, RateLimitPolicy, RateLimiter};
+use routes::retention::{retention_routes, RetentionState};
+use routes::sessions::session_routes;
+use routes::solana::solana_routes;
+use routes::token_keys::TokenKeys;
+
+/// Everything the full API serves from
+pub struct AppState {
+    pub pool: PgPool,
+    pub auth: AuthState,
+    pub commsec: CommsecState,
+    pub retention: RetentionState,
+    pub rate_limiter: RateLimiter,
+}
+
+/// The whole API as main.rs serves it
+pub fn app(state: AppState) -> Router {
+    let keys = state.auth.keys.clone();
+    let routes = data_routes()
+        .merge(auth_routes(state.auth.clone()))
+        .merge(session_routes(state.auth.clone()))
+        .merge(mfa_routes(state.auth.clone()))
+        .merge(solana_routes(state.auth.clone()))
+        .merge(org_routes(state.auth))
+        .merge(routes::identities::identity_routes())
+        .merge(routes::roles::role_routes())
+        .merge(routes::service_accounts::service_account_routes())
+        // 🔐 CommSec hands out key material: ML-DSA signed tokens only, plus recent MFA for the key pair
+        .merge(
+            commsec_key_routes(Arc::new(state.commsec.clone()))
+                .route_layer(middleware::from_fn(require_recent_mfa))
+                .route_layer(middleware::from_fn(require_post_quantum)),
+        )
+        .merge(commsec_traffic_routes(Arc::new(state.commsec)).route_layer(middleware::from_fn(require_post_quantum)))
+        .merge(retention_routes(state.retention))
+        .merge(audit_routes());
+    secured(routes, state.rate_limiter, keys, state.pool)
+}
+
+/// The data routes behind the same auth, rate-limit and request-id layers
+/// as `app` (default budgets) — for tests that don't need sign-in or CommSec
+pub fn app_routes(pool: PgPool, keys: TokenKeys) -> Router {
+    secured(data_routes(), RateLimiter::in_memory(RateLimitPolicy::default()), keys, pool)
+}
+
+fn data_routes() -> Router {
     Router::new()
+        .merge(routes::health::health_routes())
         .merge(routes::user::user_routes())
         .merge(routes::inventory::inventory_routes())
         .merge(routes::packages::package_routes())
+        .merge(routes::missions::mission_routes())
+}
+
+fn secured(routes: Router, rate_limiter: RateLimiter, keys: TokenKeys, pool: PgPool) -> Router {
+    routes
+        // 🚦 token buckets per API key or user, once the token is verified...
+        .route_layer(middleware::from_fn_with_state(rate_limiter.clone(), rate_limit_by_caller))
+        // 🔐 every route needs a valid token, except auth_middleware::PUBLIC_ROUTES
+        .route_layer(middleware::from_fn(require_auth))
+        // 🚦 ...and per IP, before it is, so rejected tokens cost too; failed sign-ins lock the caller out
+        .route_layer(middleware::from_fn_with_state(rate_limiter, rate_limit_by_ip))
+        .layer(Extension(keys))
         .layer(Extension(pool))
+        // 🧾 request ids tie audit events to logs
+        .layer(middleware::from_fn(assign_request_id))
+}
+
+/// Every CommSec route, without the auth layers `app` puts in front of them
+pub fn commsec_routes(state: CommsecState) -> Router {
+    let state = Arc::new(state);
+    commsec_key_routes(state.clone()).merge(commsec_traffic_routes(state))
 }
 
 pub async fn init_db_pool() -> PgPool {
@@ -18,4 +92,3 @@ pub async fn init_db_pool() -> PgPool {
         .expect("DATABASE_URL or DATABASE_TEST_URL must be set");
     PgPool::connect(&url).await.expect("Failed to connect to DB")
 }
-
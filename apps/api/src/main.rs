@@ -1,15 +1,16 @@
-use axum::{Router, Extension};
 use sqlx::PgPool;
 use std::net::SocketAddr;
-use oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
-use std::collections::HashMap;
 use dotenvy::dotenv;
 
-use crate::routes::commsec::{commsec_routes, init_commsec_state}; // ✅ added init_commsec_state
-use crate::routes::auth::{auth_routes, AuthState};
-use crate::routes::{user, inventory, packages};
-
-mod routes;
+use api::routes::auth::AuthState;
+use api::routes::commsec::init_commsec_state;
+use api::routes::missions;
+use api::routes::oidc::JwksCache;
+use api::routes::rate_limit::{RateLimitPolicy, RateLimiter};
+use api::routes::token_keys::TokenKeys;
+use api::routes::providers::{load_providers, ProvidersConfig};
+use api::routes::retention::{spawn_retention_sweeper, RetentionPolicy, RetentionState};
+use api::{app, AppState};
 
 /// Helper to load an env var with clear error messages
 fn get_env_var(key: &str) -> String {
@@ -19,78 +20,113 @@ fn get_env_var(key: &str) -> String {
     })
 }
 
+/// Serve the mock OAuth provider and register it as provider "mock"
+#[cfg(feature = "mock-oauth")]
+async fn add_mock_provider(config: &mut ProvidersConfig, addr: &str) {
+    use api::mock_oauth::{MockProvider, MOCK_CLIENT_ID, MOCK_CLIENT_SECRET};
+    use api::routes::providers::ProviderConfig;
+
+    let addr: SocketAddr = addr.parse().expect("OAUTH_MOCK_ADDR must be host:port");
+    let mock = MockProvider::spawn(addr).await.expect("Failed to start mock OAuth provider");
+    println!("🧪 Mock OAuth provider at {}", mock.issuer());
+    config.providers.insert("mock".to_string(), ProviderConfig {
+        issuer: Some(mock.issuer().to_string()),
+        client_id: Some(MOCK_CLIENT_ID.to_string()),
+        client_secret: Some(MOCK_CLIENT_SECRET.to_string()),
+        ..ProviderConfig::default()
+    });
+}
+
+/// Release builds have no mock provider to serve
+#[cfg(not(feature = "mock-oauth"))]
+async fn add_mock_provider(_config: &mut ProvidersConfig, _addr: &str) {
+    eprintln!("⚠️ OAUTH_MOCK_ADDR ignored: built without the mock-oauth feature");
+}
+
 #[tokio::main]
 async fn main() -> Result<(), sqlx::Error> {
     // ✅ Load .env
     dotenv().ok();
+    tracing_subscriber::fmt::init();
 
     // ✅ Connect to DB
     let database_url = get_env_var("DATABASE_URL");
     let pool = PgPool::connect(&database_url).await?;
 
-    // ✅ Setup OAuth clients
-    let mut clients = HashMap::new();
-
-    // Google
-    clients.insert("google".to_string(),
-        oauth2::basic::BasicClient::new(
-            ClientId::new(get_env_var("GOOGLE_CLIENT_ID")),
-            Some(ClientSecret::new(get_env_var("GOOGLE_CLIENT_SECRET"))),
-            AuthUrl::new("https://accounts.google.com/o/oauth2/auth".to_string()).unwrap(),
-            Some(TokenUrl::new("https://oauth2.googleapis.com/token".to_string()).unwrap()),
-        )
-        .set_redirect_uri(RedirectUrl::new(
-            "http://127.0.0.1:3000/auth/callback/google".to_string()
-        ).unwrap())
-    );
-
-    // GitHub
-    clients.insert("github".to_string(),
-        oauth2::basic::BasicClient::new(
-            ClientId::new(get_env_var("GITHUB_CLIENT_ID")),
-            Some(ClientSecret::new(get_env_var("GITHUB_CLIENT_SECRET"))),
-            AuthUrl::new("https://github.com/login/oauth/authorize".to_string()).unwrap(),
-            Some(TokenUrl::new("https://github.com/login/oauth/access_token".to_string()).unwrap()),
-        )
-        .set_redirect_uri(RedirectUrl::new(
-            "http://127.0.0.1:3000/auth/callback/github".to_string()
-        ).unwrap())
-    );
-
-    // Amazon
-    clients.insert("amazon".to_string(),
-        oauth2::basic::BasicClient::new(
-            ClientId::new(get_env_var("AMAZON_CLIENT_ID")),
-            Some(ClientSecret::new(get_env_var("AMAZON_CLIENT_SECRET"))),
-            AuthUrl::new("https://www.amazon.com/ap/oa".to_string()).unwrap(),
-            Some(TokenUrl::new("https://api.amazon.com/auth/o2/token".to_string()).unwrap()),
-        )
-        .set_redirect_uri(RedirectUrl::new(
-            "http://127.0.0.1:3000/auth/callback/amazon".to_string()
-        ).unwrap())
-    );
+    // ✅ Setup OAuth providers from config/oauth_providers.toml
+    let mut providers_config = ProvidersConfig::load().unwrap_or_else(|err| {
+        eprintln!("❌ {}", err);
+        std::process::exit(1);
+    });
+
+    // Local mock provider for offline development (OAUTH_MOCK_ADDR=127.0.0.1:3999)
+    if let Ok(addr) = std::env::var("OAUTH_MOCK_ADDR") {
+        add_mock_provider(&mut providers_config, &addr).await;
+    }
+
+    let providers = load_providers(&providers_config).await.unwrap_or_else(|err| {
+        eprintln!("❌ {}", err);
+        std::process::exit(1);
+    });
+
+    let retention_policy = RetentionPolicy::from_env();
+
+    // ✅ Per-caller request budgets (RATE_LIMIT_* env vars), kept in memory
+    let rate_limiter = RateLimiter::in_memory(RateLimitPolicy::from_env());
+
+    // ✅ JWT signing keys (keys/jwt, rotated per JWT_KEY_ROTATION_SECS)
+    let token_keys = TokenKeys::from_env().unwrap_or_else(|err| {
+        eprintln!("❌ {}", err);
+        std::process::exit(1);
+    });
+    println!("🔑 Signing JWTs with key {}", token_keys.active_kid());
+    token_keys.clone().spawn_rotation(token_keys.rotation_check_interval());
 
     let auth_state = AuthState {
-        clients,
-        jwt_secret: get_env_var("JWT_SECRET"),
+        providers,
+        keys: token_keys,
+        token_ttl_secs: retention_policy.issued_token_ttl.as_secs() as i64,
+        refresh_ttl_secs: retention_policy.refresh_token_ttl.as_secs() as i64,
+        jwks: JwksCache::default(),
+        // comma-separated emails made admins on their next login
+        admin_emails: std::env::var("ADMIN_EMAILS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|email| email.trim().to_string())
+            .filter(|email| !email.is_empty())
+            .collect(),
     };
 
     // ✅ Initialize CommSec state
     let commsec_state = init_commsec_state();
 
+    // ✅ Expire keys, ciphertexts and token records per retention policy
+    let retention_state = RetentionState {
+        policy: retention_policy,
+        commsec: commsec_state.clone(),
+    };
+    spawn_retention_sweeper(pool.clone(), retention_state.clone());
+
+    // ✅ Watch for missed beacon check-ins
+    missions::spawn_beacon_watch(pool.clone(), std::time::Duration::from_secs(60));
+
     // ✅ Register routes
-    let app = Router::new()
-        .merge(user::user_routes())
-        .merge(inventory::inventory_routes())
-        .merge(packages::package_routes())
-        .merge(auth_routes(auth_state))
-        .merge(commsec_routes(commsec_state)) // ✅ pass CommsecState here
-        .layer(Extension(pool));
+    let app = app(AppState {
+        pool,
+        auth: auth_state,
+        commsec: commsec_state,
+        retention: retention_state,
+        rate_limiter,
+    });
 
     // ✅ Start server
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     println!("🚀 API running at http://{}", addr);
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, app).await?;
+    axum::serve(
+        tokio::net::TcpListener::bind(addr).await?,
+        app.into_make_service_with_connect_info::<SocketAddr>(), // peer IP for CommSec monitoring
+    )
+    .await?;
 
     Ok(())
 }
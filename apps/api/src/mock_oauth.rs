@@ -0,0 +1,376 @@
+//! In-process OAuth 2.0 / OpenID Connect provider for offline testing.
+//!
+//! Implements just enough of an authorization server for `auth_routes` to
+//! run a real authorization-code + PKCE flow against it: authorize, token,
+//! userinfo, JWKS and discovery endpoints. ID tokens are EdDSA-signed with a
+//! key generated at startup (see [`MockProvider::rotate_key`]). Failure modes
+//! (denied consent, broken userinfo, bad ID tokens) are switched on at runtime
+//! through [`MockBehavior`].
+
+use axum::{
+    extract::{Form, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use oauth2::url::Url;
+use ring::{rand::SystemRandom, signature::{Ed25519KeyPair, KeyPair}};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex};
+use uuid::Uuid;
+
+pub const MOCK_CLIENT_ID: &str = "mock-client";
+pub const MOCK_CLIENT_SECRET: &str = "mock-secret";
+
+/// The account the provider "logs in" on every authorize request
+#[derive(Debug, Clone, Serialize)]
+pub struct MockAccount {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+impl Default for MockAccount {
+    fn default() -> Self {
+        Self {
+            sub: "mock-user-1".to_string(),
+            email: Some("mock-user-1@mock.tidasone".to_string()),
+            email_verified: true,
+            name: Some("Mock User".to_string()),
+        }
+    }
+}
+
+/// Runtime switches for the provider's responses
+#[derive(Debug, Clone, Default)]
+pub struct MockBehavior {
+    pub account: MockAccount,
+    pub deny_consent: bool,  // authorize redirects back with `error=access_denied`
+    pub fail_userinfo: bool, // userinfo answers 500
+    pub id_token: IdTokenFaults,
+}
+
+/// Ways to make the issued ID token invalid
+#[derive(Debug, Clone, Default)]
+pub struct IdTokenFaults {
+    pub omit: bool,
+    pub audience: Option<String>,
+    pub issuer: Option<String>,
+    pub nonce: Option<String>,
+    pub expired: bool,
+    pub foreign_key: bool, // signed with a key not in the JWKS (same kid)
+}
+
+struct SigningKey {
+    kid: String,
+    encoding: EncodingKey,
+    public_key: Vec<u8>,
+}
+
+impl SigningKey {
+    fn generate() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).expect("Ed25519 keygen failed");
+        let keypair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("invalid Ed25519 key");
+        Self {
+            kid: Uuid::new_v4().to_string(),
+            encoding: EncodingKey::from_ed_der(pkcs8.as_ref()),
+            public_key: keypair.public_key().as_ref().to_vec(),
+        }
+    }
+}
+
+struct PendingCode {
+    redirect_uri: String,
+    code_challenge: Option<String>,
+    nonce: Option<String>,
+    account: MockAccount,
+    faults: IdTokenFaults,
+}
+
+struct MockState {
+    issuer: String,
+    key: Mutex<SigningKey>,
+    jwks_requests: AtomicUsize,
+    behavior: Mutex<MockBehavior>,
+    codes: Mutex<HashMap<String, PendingCode>>,
+    access_tokens: Mutex<HashMap<String, MockAccount>>,
+}
+
+/// Handle to a mock provider serving at `issuer`
+#[derive(Clone)]
+pub struct MockProvider {
+    state: Arc<MockState>,
+}
+
+impl MockProvider {
+    /// Provider whose endpoints live under `issuer` (serve [`Self::router`] there)
+    pub fn new(issuer: &str) -> Self {
+        Self {
+            state: Arc::new(MockState {
+                issuer: issuer.trim_end_matches('/').to_string(),
+                key: Mutex::new(SigningKey::generate()),
+                jwks_requests: AtomicUsize::new(0),
+                behavior: Mutex::new(MockBehavior::default()),
+                codes: Mutex::new(HashMap::new()),
+                access_tokens: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Bind `addr` (port 0 for an ephemeral port) and serve in the background
+    pub async fn spawn(addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let provider = Self::new(&format!("http://{}", listener.local_addr()?));
+        let router = provider.router();
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                eprintln!("Mock OAuth provider stopped: {:?}", err);
+            }
+        });
+        Ok(provider)
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .route("/userinfo", get(userinfo))
+            .route("/jwks", get(jwks))
+            .with_state(self.state.clone())
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.state.issuer
+    }
+
+    pub fn userinfo_url(&self) -> String {
+        format!("{}/userinfo", self.state.issuer)
+    }
+
+    /// Change how the provider responds from now on
+    pub fn configure(&self, update: impl FnOnce(&mut MockBehavior)) {
+        update(&mut self.state.behavior.lock().unwrap());
+    }
+
+    /// Replace the signing key; the JWKS only publishes the new one
+    pub fn rotate_key(&self) {
+        *self.state.key.lock().unwrap() = SigningKey::generate();
+    }
+
+    /// How many times the JWKS endpoint has been fetched
+    pub fn jwks_requests(&self) -> usize {
+        self.state.jwks_requests.load(Ordering::SeqCst)
+    }
+}
+
+fn oauth_error(status: StatusCode, error: &str) -> Response {
+    (status, Json(json!({ "error": error }))).into_response()
+}
+
+async fn discovery(State(state): State<Arc<MockState>>) -> Json<Value> {
+    let issuer = &state.issuer;
+    Json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "userinfo_endpoint": format!("{}/userinfo", issuer),
+        "jwks_uri": format!("{}/jwks", issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["EdDSA"],
+        "code_challenge_methods_supported": ["S256"],
+    }))
+}
+
+#[derive(Deserialize)]
+struct AuthorizeParams {
+    client_id: String,
+    redirect_uri: String,
+    state: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    nonce: Option<String>,
+}
+
+async fn authorize(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
+    if params.client_id != MOCK_CLIENT_ID {
+        return oauth_error(StatusCode::BAD_REQUEST, "unauthorized_client");
+    }
+    if params.code_challenge.is_some() && params.code_challenge_method.as_deref() != Some("S256") {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_request");
+    }
+    let Ok(mut redirect) = Url::parse(&params.redirect_uri) else {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_request");
+    };
+
+    let behavior = state.behavior.lock().unwrap().clone();
+    {
+        let mut query = redirect.query_pairs_mut();
+        if behavior.deny_consent {
+            query.append_pair("error", "access_denied");
+        } else {
+            let code = Uuid::new_v4().to_string();
+            state.codes.lock().unwrap().insert(
+                code.clone(),
+                PendingCode {
+                    redirect_uri: params.redirect_uri.clone(),
+                    code_challenge: params.code_challenge,
+                    nonce: params.nonce,
+                    account: behavior.account,
+                    faults: behavior.id_token,
+                },
+            );
+            query.append_pair("code", &code);
+        }
+        if let Some(csrf) = &params.state {
+            query.append_pair("state", csrf);
+        }
+    }
+
+    Redirect::to(redirect.as_str()).into_response()
+}
+
+/// Client credentials from HTTP Basic auth, falling back to the form body
+fn client_credentials(headers: &HeaderMap, form: &HashMap<String, String>) -> (String, String) {
+    let basic = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|pair| pair.split_once(':').map(|(id, secret)| (id.to_string(), secret.to_string())));
+
+    basic.unwrap_or_else(|| {
+        (
+            form.get("client_id").cloned().unwrap_or_default(),
+            form.get("client_secret").cloned().unwrap_or_default(),
+        )
+    })
+}
+
+#[derive(Serialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    exp: usize,
+    iat: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    email_verified: bool,
+}
+
+async fn token(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    if client_credentials(&headers, &form) != (MOCK_CLIENT_ID.to_string(), MOCK_CLIENT_SECRET.to_string()) {
+        return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client");
+    }
+    if form.get("grant_type").map(String::as_str) != Some("authorization_code") {
+        return oauth_error(StatusCode::BAD_REQUEST, "unsupported_grant_type");
+    }
+
+    // codes are single use, even when the exchange fails
+    let pending = form.get("code").and_then(|code| state.codes.lock().unwrap().remove(code));
+    let Some(pending) = pending else {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant");
+    };
+    if form.get("redirect_uri") != Some(&pending.redirect_uri) {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant");
+    }
+    if let Some(challenge) = &pending.code_challenge {
+        let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+        if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != *challenge {
+            return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant");
+        }
+    }
+
+    let faults = &pending.faults;
+    let now = chrono::Utc::now().timestamp() as usize;
+    let claims = IdTokenClaims {
+        iss: faults.issuer.clone().unwrap_or_else(|| state.issuer.clone()),
+        sub: pending.account.sub.clone(),
+        aud: faults.audience.clone().unwrap_or_else(|| MOCK_CLIENT_ID.to_string()),
+        exp: if faults.expired { now - 3600 } else { now + 3600 },
+        iat: now,
+        nonce: faults.nonce.clone().or(pending.nonce),
+        email: pending.account.email.clone(),
+        email_verified: pending.account.email_verified,
+    };
+    let id_token = {
+        let key = state.key.lock().unwrap();
+        let mut id_header = Header::new(Algorithm::EdDSA);
+        id_header.kid = Some(key.kid.clone());
+        let result = if faults.foreign_key {
+            encode(&id_header, &claims, &SigningKey::generate().encoding)
+        } else {
+            encode(&id_header, &claims, &key.encoding)
+        };
+        match result {
+            Ok(token) => token,
+            Err(_) => return oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error"),
+        }
+    };
+
+    let access_token = Uuid::new_v4().to_string();
+    state.access_tokens.lock().unwrap().insert(access_token.clone(), pending.account);
+
+    let mut body = json!({
+        "access_token": access_token,
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "scope": "openid email profile",
+    });
+    if !faults.omit {
+        body["id_token"] = json!(id_token);
+    }
+    Json(body).into_response()
+}
+
+async fn userinfo(State(state): State<Arc<MockState>>, headers: HeaderMap) -> Response {
+    if state.behavior.lock().unwrap().fail_userinfo {
+        return oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error");
+    }
+
+    let account = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("bearer ")))
+        .and_then(|token| state.access_tokens.lock().unwrap().get(token).cloned());
+
+    match account {
+        Some(account) => Json(account).into_response(),
+        None => oauth_error(StatusCode::UNAUTHORIZED, "invalid_token"),
+    }
+}
+
+async fn jwks(State(state): State<Arc<MockState>>) -> Json<Value> {
+    state.jwks_requests.fetch_add(1, Ordering::SeqCst);
+    let key = state.key.lock().unwrap();
+    Json(json!({
+        "keys": [{
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "alg": "EdDSA",
+            "use": "sig",
+            "kid": key.kid,
+            "x": URL_SAFE_NO_PAD.encode(&key.public_key),
+        }]
+    }))
+}
@@ -0,0 +1,263 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Query, Request},
+    http::{request::Parts, HeaderValue},
+    middleware::Next,
+    response::Response,
+    routing::get,
+    Router, Json, Extension,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+use db::models::{AuditEvent, AuditFilter, NewAuditEvent, GENESIS_HASH};
+use db::queries;
+
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::permissions::{AuditRead, RequirePermission};
+
+/// Header carrying the request id, taken from the client or made up here
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Actor of events no account can be pinned on yet, like failed logins
+pub const ANONYMOUS: &str = "anonymous";
+
+/// Actor of events done with an admin CLI, by the OS user running it
+pub fn cli_actor() -> String {
+    format!("cli:{}", std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()))
+}
+
+const DEFAULT_PAGE: i64 = 100;
+const MAX_PAGE: i64 = 1000;
+
+/// Id that ties a request's audit events to its logs
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Middleware: give every request an id (a sane client-supplied one is kept)
+/// and echo it in the response
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut response = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// One thing that happened, before it's stamped with who and where
+pub struct AuditEntry {
+    action: &'static str,
+    target_type: &'static str,
+    target_id: Option<String>,
+    org_id: Option<Uuid>,
+    changes: Value,
+}
+
+impl AuditEntry {
+    pub fn new(action: &'static str, target_type: &'static str, target_id: impl Display) -> Self {
+        AuditEntry {
+            action,
+            target_type,
+            target_id: Some(target_id.to_string()),
+            org_id: None,
+            changes: Value::Object(Map::new()),
+        }
+    }
+
+    pub fn in_org(mut self, org_id: Uuid) -> Self {
+        self.org_id = Some(org_id);
+        self
+    }
+
+    pub fn created(mut self, after: &impl Serialize) -> Self {
+        self.changes = diff(None, Some(to_value(after)));
+        self
+    }
+
+    pub fn updated(mut self, before: &impl Serialize, after: &impl Serialize) -> Self {
+        self.changes = diff(Some(to_value(before)), Some(to_value(after)));
+        self
+    }
+
+    pub fn deleted(mut self, before: &impl Serialize) -> Self {
+        self.changes = diff(Some(to_value(before)), None);
+        self
+    }
+
+    /// Free-form details, for events that aren't a row changing
+    pub fn detail(mut self, detail: Value) -> Self {
+        self.changes = detail;
+        self
+    }
+}
+
+fn to_value(row: &impl Serialize) -> Value {
+    serde_json::to_value(row).unwrap_or(Value::Null)
+}
+
+/// `{"before": ..., "after": ...}` holding only the fields that differ;
+/// a create has no `before` and a delete no `after`
+pub fn diff(before: Option<Value>, after: Option<Value>) -> Value {
+    let mut changes = Map::new();
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let (mut old, mut new) = (Map::new(), Map::new());
+            for (field, value) in &after {
+                if before.get(field) != Some(value) {
+                    old.insert(field.clone(), before.get(field).cloned().unwrap_or(Value::Null));
+                    new.insert(field.clone(), value.clone());
+                }
+            }
+            for (field, value) in &before {
+                if !after.contains_key(field) {
+                    old.insert(field.clone(), value.clone());
+                }
+            }
+            changes.insert("before".to_string(), Value::Object(old));
+            changes.insert("after".to_string(), Value::Object(new));
+        }
+        (before, after) => {
+            if let Some(before) = before {
+                changes.insert("before".to_string(), before);
+            }
+            if let Some(after) = after {
+                changes.insert("after".to_string(), after);
+            }
+        }
+    }
+    Value::Object(changes)
+}
+
+/// Extractor: appends to the audit trail, stamping each event with the
+/// request's id and peer address
+pub struct Audit {
+    pool: PgPool,
+    ip: Option<String>,
+    request_id: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Audit
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let pool = parts
+            .extensions
+            .get::<PgPool>()
+            .cloned()
+            .ok_or(ApiError::Internal("Audit log unavailable".to_string()))?;
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+        let request_id = parts.extensions.get::<RequestId>().map(|RequestId(id)| id.clone());
+        Ok(Audit { pool, ip, request_id })
+    }
+}
+
+impl Audit {
+    /// For events from outside a request, like the admin CLIs
+    pub fn new(pool: PgPool) -> Self {
+        Audit { pool, ip: None, request_id: None }
+    }
+
+    /// Append `entry` as done by `actor` (a token subject, or the account
+    /// signing in)
+    pub async fn try_record(&self, actor: &str, entry: AuditEntry) -> sqlx::Result<()> {
+        let event = NewAuditEvent {
+            actor,
+            org_id: entry.org_id,
+            action: entry.action,
+            target_type: entry.target_type,
+            target_id: entry.target_id,
+            changes: entry.changes,
+            ip: self.ip.as_deref(),
+            request_id: self.request_id.as_deref(),
+        };
+        queries::append_audit_event(&self.pool, event).await?;
+        Ok(())
+    }
+
+    /// [`Audit::try_record`] for handlers. The change it records has already
+    /// been committed, so a failed write is logged rather than turned into an
+    /// error the client would retry.
+    pub async fn record(&self, actor: &str, entry: AuditEntry) {
+        let action = entry.action;
+        if let Err(err) = self.try_record(actor, entry).await {
+            tracing::error!(action, actor, request_id = ?self.request_id, "⚠️ Audit write failed: {:?}", err);
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ChainReport {
+    pub events_checked: u64,
+    pub valid: bool,
+    pub first_invalid_id: Option<i64>, // the chain is broken from here on
+    pub head_hash: Option<String>, // keep a copy elsewhere to notice truncation
+}
+
+/// Reading the audit trail, for admins
+pub fn audit_routes() -> Router {
+    Router::new()
+        .route("/audit", get(list_events))
+        .route("/audit/verify", get(verify_chain))
+}
+
+/// Events matching the query's filters, newest first; page with `before_id`
+async fn list_events(
+    RequirePermission(_, _): RequirePermission<AuditRead>,
+    Extension(pool): Extension<PgPool>,
+    Query(mut filter): Query<AuditFilter>,
+) -> ApiResult<Json<Vec<AuditEvent>>> {
+    filter.limit = Some(filter.limit.unwrap_or(DEFAULT_PAGE).clamp(1, MAX_PAGE));
+    let events = queries::get_audit_events(&pool, &filter).await?;
+    Ok(Json(events))
+}
+
+/// Recompute the hash chain from the first event
+async fn verify_chain(
+    RequirePermission(_, _): RequirePermission<AuditRead>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<ChainReport>> {
+    let mut report = ChainReport { events_checked: 0, valid: true, first_invalid_id: None, head_hash: None };
+    let mut prev_hash = GENESIS_HASH.to_vec();
+    let mut after_id = 0;
+
+    loop {
+        let events = queries::get_audit_chain(&pool, after_id, MAX_PAGE).await?;
+        let Some(last) = events.last() else { break };
+        after_id = last.id;
+
+        for event in &events {
+            report.events_checked += 1;
+            if event.prev_hash != prev_hash || event.expected_hash() != event.hash {
+                report.valid = false;
+                report.first_invalid_id = Some(event.id);
+                return Ok(Json(report));
+            }
+            prev_hash.clone_from(&event.hash);
+        }
+    }
+
+    if report.events_checked > 0 {
+        report.head_hash = Some(prev_hash.iter().map(|byte| format!("{:02x}", byte)).collect());
+    }
+    Ok(Json(report))
+}
@@ -1,36 +1,54 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    Json,
+    http::{header, HeaderMap, StatusCode},
+    Extension, Json,
+};
+use oauth2::{
+    AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RequestTokenError, TokenResponse,
 };
-use oauth2::{AuthorizationCode, CsrfToken, TokenResponse};
 use serde::Deserialize;
-use serde_json::json;
 use std::collections::HashMap;
-use jsonwebtoken::{encode, EncodingKey, Header};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::NewOAuthState;
+
+use crate::routes::audit::{Audit, AuditEntry, ANONYMOUS};
+use crate::routes::auth_middleware::AuthenticatedUser;
+use crate::routes::error::ApiError;
+use crate::routes::identities::{resolve_login_user, ProviderProfile};
+use crate::routes::oidc::{verify_id_token, JwksCache};
+use crate::routes::providers::OAuthProvider;
+use crate::routes::sessions::{issue_tokens, AuthResponse, Session};
+use crate::routes::token_keys::TokenKeys;
 
 #[derive(Clone)]
 pub struct AuthState {
-    pub clients: HashMap<String, oauth2::basic::BasicClient>,
-    pub jwt_secret: String,
+    pub providers: HashMap<String, OAuthProvider>,
+    pub keys: TokenKeys, // signs our JWTs; public half served as JWKS
+    pub token_ttl_secs: i64, // lifetime of minted JWTs (retention policy)
+    pub refresh_ttl_secs: i64, // lifetime of refresh tokens
+    pub jwks: JwksCache,
+    pub admin_emails: Vec<String>, // granted the admin role at login (bootstrap)
 }
 
+/// How long a user has to finish the provider round trip
+const OAUTH_STATE_TTL_SECS: i64 = 600;
+
+/// Cookie binding a pending `state` to the browser that started the flow
+const STATE_COOKIE: &str = "oauth_state";
+
 #[derive(Debug, Deserialize)]
 pub struct AuthRequest {
-    pub code: String,
+    pub code: Option<String>,
     pub state: String,
+    pub error: Option<String>, // set instead of `code` when the user declines
 }
 
-#[derive(serde::Serialize)]
-struct Claims {
-    sub: String,     // subject (user id or email)
-    exp: usize,      // expiration timestamp
-    provider: String,
-}
-
-#[derive(serde::Serialize)]
-struct AuthResponse {
-    token: String,
+#[derive(Debug, Deserialize)]
+pub struct LoginQuery {
+    #[serde(default)]
+    pub pq: bool, // ask for ML-DSA signed access tokens
 }
 
 /// Expected minimal fields from userinfo endpoints
@@ -39,6 +57,8 @@ struct UserInfo {
     // Google / Amazon
     sub: Option<String>,
     email: Option<String>,
+    email_verified: Option<bool>,
+    user_id: Option<String>, // Amazon
 
     // GitHub
     id: Option<u64>,
@@ -51,66 +71,123 @@ pub fn auth_routes(state: AuthState) -> axum::Router {
     axum::Router::new()
         .route("/auth/login/:provider", get(login_handler))
         .route("/auth/callback/:provider", get(callback_handler))
+        .route("/auth/link/:provider", get(link_handler))
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .route("/.well-known/pq-jwks.json", get(pq_jwks_handler))
         .with_state(state)
 }
 
-/// Start OAuth login flow
+type Redirect = (StatusCode, [(String, String); 2]);
+
+
+/// Send the browser to the provider with a fresh state and PKCE challenge.
+///
+/// The state and verifier are kept server-side until the callback redeems
+/// them; the state is also set as a cookie so only this browser can.
+async fn authorize_redirect(
+    state: &AuthState,
+    pool: &PgPool,
+    provider: &str,
+    link_user_id: Option<Uuid>,
+    post_quantum: bool,
+) -> Result<Redirect, ApiError> {
+    let oauth = state
+        .providers
+        .get(provider)
+        .ok_or(ApiError::BadRequest("Unknown provider".to_string()))?;
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let mut request = oauth
+        .client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(oauth.scopes.iter().cloned().map(oauth2::Scope::new))
+        .set_pkce_challenge(pkce_challenge);
+
+    // OIDC: the ID token must echo this back, tying it to this login attempt
+    let nonce = oauth.oidc.as_ref().map(|_| CsrfToken::new_random().secret().clone());
+    if let Some(nonce) = &nonce {
+        request = request.add_extra_param("nonce", nonce.as_str());
+    }
+    let (auth_url, csrf_token) = request.url();
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(OAUTH_STATE_TTL_SECS);
+    db::queries::create_oauth_state(
+        pool,
+        NewOAuthState {
+            state: csrf_token.secret(),
+            provider,
+            pkce_verifier: pkce_verifier.secret(),
+            nonce: nonce.as_deref(),
+            link_user_id,
+            post_quantum,
+            expires_at,
+        },
+    )
+    .await?;
+
+    let cookie = format!(
+        "{}={}; Path=/auth/callback/{}; Max-Age={}; HttpOnly; SameSite=Lax",
+        STATE_COOKIE,
+        csrf_token.secret(),
+        provider,
+        OAUTH_STATE_TTL_SECS
+    );
+
+    Ok((
+        StatusCode::SEE_OTHER,
+        [
+            ("Location".to_string(), auth_url.to_string()),
+            ("Set-Cookie".to_string(), cookie),
+        ],
+    ))
+}
+
+/// Start OAuth login flow (`?pq=true` for post-quantum access tokens)
 async fn login_handler(
     State(state): State<AuthState>,
     Path(provider): Path<String>,
-) -> Result<(StatusCode, [(String, String); 1]), (StatusCode, String)> {
-    if let Some(client) = state.clients.get(&provider) {
-        let (auth_url, _csrf_token) = client
-            .authorize_url(CsrfToken::new_random)
-            .add_scope(oauth2::Scope::new("openid".to_string()))
-            .add_scope(oauth2::Scope::new("email".to_string()))
-            .add_scope(oauth2::Scope::new("profile".to_string()))
-            .url();
-
-        Ok((
-            StatusCode::SEE_OTHER,
-            [("Location".to_string(), auth_url.to_string())],
-        ))
-    } else {
-        Err((StatusCode::BAD_REQUEST, "Unknown provider".to_string()))
-    }
+    Query(query): Query<LoginQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<Redirect, ApiError> {
+    authorize_redirect(&state, &pool, &provider, None, query.pq).await
 }
 
-/// Handle OAuth callback, exchange code for token, fetch user info, issue JWT
-async fn callback_handler(
+/// Start OAuth flow that links another provider to the caller's account
+async fn link_handler(
     State(state): State<AuthState>,
     Path(provider): Path<String>,
-    Query(query): Query<AuthRequest>,
-) -> Result<Json<AuthResponse>, (StatusCode, String)> {
-    let client = state
-        .clients
-        .get(&provider)
-        .ok_or((StatusCode::BAD_REQUEST, "Unknown provider".to_string()))?;
+    AuthenticatedUser(user): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+) -> Result<Redirect, ApiError> {
+    let uid = user
+        .user_id()
+        .ok_or(ApiError::Forbidden("Token is not bound to a user".to_string()))?;
 
-    let token_result = client
-        .exchange_code(AuthorizationCode::new(query.code.clone()))
-        .request_async(oauth2::reqwest::async_http_client)
-        .await;
+    // keep the caller's token format
+    authorize_redirect(&state, &pool, &provider, Some(uid), user.post_quantum).await
+}
 
-    let token = match token_result {
-        Ok(tok) => tok,
-        Err(err) => {
-            eprintln!("OAuth error: {:?}", err);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Token exchange failed".to_string(),
-            ));
-        }
-    };
+fn state_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == STATE_COOKIE)
+        .map(|(_, value)| value)
+}
 
-    // 🔑 Use access_token to fetch user profile
-    let access_token = token.access_token().secret();
-    let userinfo_url = match provider.as_str() {
-        "google" => "https://www.googleapis.com/oauth2/v3/userinfo",
-        "github" => "https://api.github.com/user",
-        "amazon" => "https://api.amazon.com/user/profile",
-        _ => return Err((StatusCode::BAD_REQUEST, "Unknown provider".to_string())),
-    };
+/// Profile of a non-OIDC provider's user, from its userinfo endpoint
+async fn userinfo_profile(
+    provider: &str,
+    oauth: &OAuthProvider,
+    access_token: &str,
+) -> Result<ProviderProfile, ApiError> {
+    let userinfo_url = oauth
+        .userinfo_url
+        .as_ref()
+        .ok_or(ApiError::BadGateway("Provider has no userinfo endpoint".to_string()))?;
 
     let client = reqwest::Client::new();
     let userinfo_res = client
@@ -119,53 +196,149 @@ async fn callback_handler(
         .header("User-Agent", "TIDasONE-App") // GitHub requires UA
         .send()
         .await
-        .map_err(|_| (StatusCode::BAD_GATEWAY, "Failed to call userinfo endpoint".to_string()))?;
+        .map_err(|_| ApiError::BadGateway("Failed to call userinfo endpoint".to_string()))?;
 
     if !userinfo_res.status().is_success() {
-        return Err((
-            StatusCode::BAD_GATEWAY,
-            format!("Userinfo request failed: {}", userinfo_res.status()),
-        ));
+        return Err(ApiError::BadGateway(format!("Userinfo request failed: {}", userinfo_res.status())));
     }
 
     let userinfo: UserInfo = userinfo_res
         .json()
         .await
-        .map_err(|_| (StatusCode::BAD_GATEWAY, "Failed to parse userinfo".to_string()))?;
-
-    // ✅ pick best identifier depending on provider
-    let subject = userinfo
-        .email
-        .or_else(|| userinfo.login.clone())
-        .or_else(|| userinfo.name.clone())
-        .or_else(|| userinfo.sub.clone())
+        .map_err(|_| ApiError::BadGateway("Failed to parse userinfo".to_string()))?;
+
+    // ✅ stable provider-side id for the identity link
+    let provider_subject = userinfo
+        .sub
+        .clone()
+        .or_else(|| userinfo.user_id.clone())
         .or_else(|| userinfo.id.map(|id| id.to_string()))
-        .unwrap_or_else(|| "unknown".to_string());
-
-    // mint JWT
-    let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(1))
-        .unwrap()
-        .timestamp() as usize;
-
-    let claims = Claims {
-        sub: subject,
-        exp: expiration,
-        provider: provider.clone(),
+        .ok_or(ApiError::BadGateway("Userinfo has no subject".to_string()))?;
+
+    Ok(ProviderProfile {
+        provider: provider.to_string(),
+        subject: provider_subject,
+        // GitHub only returns public, verified emails on /user
+        email_verified: userinfo.email_verified.unwrap_or(provider == "github"),
+        email: userinfo.email.clone(),
+        username: userinfo.login.clone().or_else(|| userinfo.name.clone()),
+    })
+}
+
+/// Handle OAuth callback, exchange code for token, fetch user info, issue JWT
+async fn callback_handler(
+    State(state): State<AuthState>,
+    Path(provider): Path<String>,
+    Query(query): Query<AuthRequest>,
+    headers: HeaderMap,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> Result<([(String, String); 1], Json<AuthResponse>), ApiError> {
+    let oauth = state
+        .providers
+        .get(&provider)
+        .ok_or(ApiError::BadRequest("Unknown provider".to_string()))?;
+
+    // 🔐 the state must come back to the browser we sent out, exactly once
+    if state_cookie(&headers) != Some(query.state.as_str()) {
+        return Err(ApiError::BadRequest("OAuth state mismatch".to_string()));
+    }
+    let pending = db::queries::take_oauth_state(&pool, &query.state, &provider)
+        .await?
+        .filter(|pending| pending.expires_at > chrono::Utc::now())
+        .ok_or(ApiError::BadRequest("Unknown or expired OAuth state".to_string()))?;
+
+    let code = match (query.code, query.error) {
+        (_, Some(error)) => {
+            return Err(ApiError::Unauthorized(format!("Provider denied authorization: {}", error)))
+        }
+        (Some(code), None) => code,
+        (None, None) => return Err(ApiError::BadRequest("Missing authorization code".to_string())),
     };
 
-    let jwt = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(state.jwt_secret.as_bytes()),
-    )
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to encode JWT".to_string(),
-        )
-    })?;
-
-    Ok(Json(AuthResponse { token: jwt }))
+    let token_result = oauth
+        .client
+        .exchange_code(AuthorizationCode::new(code))
+        .set_pkce_verifier(PkceCodeVerifier::new(pending.pkce_verifier))
+        .request_async(oauth2::reqwest::async_http_client)
+        .await;
+
+    let token = match token_result {
+        Ok(tok) => tok,
+        // provider looked at the code and said no (bad, reused or PKCE mismatch)
+        Err(RequestTokenError::ServerResponse(err)) => {
+            eprintln!("OAuth error: {:?}", err);
+            audit.record(ANONYMOUS, AuditEntry::new("auth.login_failed", "provider", &provider)).await;
+            return Err(ApiError::Unauthorized("Authorization code rejected".to_string()));
+        }
+        Err(err) => {
+            eprintln!("OAuth error: {:?}", err);
+            return Err(ApiError::BadGateway("Token exchange failed".to_string()));
+        }
+    };
+
+    let profile = match &oauth.oidc {
+        // ✅ OIDC: identity comes from the signed ID token
+        Some(oidc) => {
+            let id_token = token
+                .extra_fields()
+                .id_token
+                .as_deref()
+                .ok_or(ApiError::Unauthorized("Provider returned no ID token".to_string()))?;
+            let claims = verify_id_token(
+                &state.jwks,
+                oidc,
+                oauth.client.client_id().as_str(),
+                id_token,
+                pending.nonce.as_deref(),
+            )
+            .await;
+            let claims = match claims {
+                Ok(claims) => claims,
+                Err(err) => {
+                    eprintln!("ID token error: {}", err);
+                    audit.record(ANONYMOUS, AuditEntry::new("auth.login_failed", "provider", &provider)).await;
+                    return Err(ApiError::Unauthorized("Invalid ID token".to_string()));
+                }
+            };
+
+            ProviderProfile {
+                provider: provider.clone(),
+                subject: claims.sub,
+                email_verified: claims.email_verified.unwrap_or(false),
+                email: claims.email,
+                username: claims.preferred_username.or(claims.name),
+            }
+        }
+        // plain OAuth 2.0 (GitHub, Amazon): ask the userinfo endpoint
+        None => userinfo_profile(&provider, oauth, token.access_token().secret()).await?,
+    };
+
+    let user = resolve_login_user(&pool, &profile, pending.link_user_id).await?;
+
+    // ✅ every login starts a new session
+    let session = Session::new(pending.post_quantum);
+    let tokens = issue_tokens(&state, &pool, &user, &provider, session).await?;
+    let action = if pending.link_user_id.is_some() { "identity.link" } else { "auth.login" };
+    audit.record(&user.id.to_string(), session.audit_entry(action, &provider)).await;
+
+    let clear_cookie = format!(
+        "{}=; Path=/auth/callback/{}; Max-Age=0; HttpOnly; SameSite=Lax",
+        STATE_COOKIE, provider
+    );
+    Ok((
+        [("Set-Cookie".to_string(), clear_cookie)],
+        Json(tokens),
+    ))
 }
 
+
+/// Public keys for verifying our access tokens (RFC 7517)
+async fn jwks_handler(State(state): State<AuthState>) -> Json<serde_json::Value> {
+    Json(state.keys.jwks())
+}
+
+/// Public ML-DSA keys for post-quantum tokens (same layout, `kty: AKP`)
+async fn pq_jwks_handler(State(state): State<AuthState>) -> Json<serde_json::Value> {
+    Json(state.keys.pq_jwks())
+}
@@ -1,48 +1,215 @@
 use axum::async_trait;
-use axum::http::{request::Parts, StatusCode};
+use axum::extract::{FromRequestParts, MatchedPath, Request};
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 use axum_extra::{
     extract::TypedHeader,
     headers::{authorization::Bearer, Authorization},
 };
-use jsonwebtoken::{decode, DecodingKey, Validation};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
 
-#[derive(Debug, Deserialize)]
+use crate::routes::error::ApiError;
+use crate::routes::service_accounts::{authenticate_api_key, is_api_key};
+use crate::routes::token_keys::TokenKeys;
+
+/// Our access token's claims, as minted by `sessions::issue_tokens` and read back here
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
-    pub sub: String,     // user ID or email
+    pub sub: String,     // users.id (OAuth logins, service accounts) or email (manual tokens)
     pub exp: usize,      // expiration timestamp
-    pub provider: String // oauth provider
+    pub provider: String, // oauth provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // token id (absent on manually minted tokens)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>, // login session (refresh token family)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub perms: Vec<String>, // granted through `roles` when the token was minted
+    #[serde(default)]
+    pub amr: Vec<String>, // how the user authenticated ("oauth" or "wallet", then "otp", "mfa")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfa_time: Option<i64>, // unix time of the session's last MFA verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<Uuid>, // organization the session is working in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_role: Option<String>, // the caller's role there when the token was minted
+    #[serde(skip)]
+    pub post_quantum: bool, // set by the extractor: token was ML-DSA signed
 }
 
-/// Extractor that validates JWT and injects claims into the handler
+impl Claims {
+    /// Internal `users.id`, when the token was minted for a linked identity
+    pub fn user_id(&self) -> Option<Uuid> {
+        Uuid::parse_str(&self.sub).ok()
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.perms.iter().any(|granted| granted == permission)
+    }
+
+    /// Whether the session passed MFA in the last `max_age_secs`
+    pub fn mfa_within(&self, max_age_secs: i64) -> bool {
+        let now = chrono::Utc::now().timestamp();
+        self.amr.iter().any(|method| method == "mfa")
+            && self.mfa_time.is_some_and(|at| at <= now + 60 && now - at <= max_age_secs)
+    }
+}
+
+/// Extractor that validates a JWT (or a service account API key) and injects claims into the handler.
+/// Behind [`require_auth`] (or another layer here) the claims it verified are reused.
 pub struct AuthenticatedUser(pub Claims);
 
 #[async_trait]
-impl<S> axum::extract::FromRequestParts<S> for AuthenticatedUser
+impl<S> FromRequestParts<S> for AuthenticatedUser
 where
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, String);
+    type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        // Extract the Authorization: Bearer <token> header
-        let TypedHeader(Authorization(bearer)) =
-            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, _state)
-                .await
-                .map_err(|_| (StatusCode::UNAUTHORIZED, "Missing or invalid Authorization header".into()))?;
+        // only the layers in this module put claims there, after verifying them
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(AuthenticatedUser(claims.clone()));
+        }
+        authenticate(parts).await.map(AuthenticatedUser)
+    }
+}
 
-        // Get JWT secret from env
-        let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+/// Full verification of the request's bearer token or API key
+async fn authenticate(parts: &mut Parts) -> Result<Claims, ApiError> {
+    // Extract the Authorization: Bearer <token> header
+    let TypedHeader(Authorization(bearer)) =
+        TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, &())
+            .await
+            .map_err(|_| ApiError::Unauthorized("Missing or invalid Authorization header".to_string()))?;
 
-        // Decode & validate JWT
-        let token_data = decode::<Claims>(
-            bearer.token(),
-            &DecodingKey::from_secret(secret.as_bytes()),
-            &Validation::default(),
-        )
-        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid or expired token".into()))?;
+    // 🔑 service accounts send an API key instead of a JWT
+    if is_api_key(bearer.token()) {
+        let pool = parts
+            .extensions
+            .get::<PgPool>()
+            .ok_or(ApiError::Internal("API key store unavailable".to_string()))?;
+        return authenticate_api_key(pool, bearer.token()).await;
+    }
 
-        Ok(AuthenticatedUser(token_data.claims))
+    // Verify against our signing keys (kid-selected, asymmetric only)
+    let keys = parts
+        .extensions
+        .get::<TokenKeys>()
+        .ok_or(ApiError::Internal("Token keys unavailable".to_string()))?;
+    let verified = keys
+        .verify::<Claims>(bearer.token())
+        .map_err(|_| ApiError::Unauthorized("Invalid or expired token".to_string()))?;
+    let claims = Claims {
+        post_quantum: verified.post_quantum,
+        ..verified.claims
+    };
+
+    // 🔐 tracked tokens can be revoked (logout, admin sign-out, refresh reuse)
+    if let Some(jti) = claims.jti.as_deref().and_then(|jti| Uuid::parse_str(jti).ok()) {
+        let pool = parts
+            .extensions
+            .get::<PgPool>()
+            .ok_or(ApiError::Internal("Token denylist unavailable".to_string()))?;
+        let revoked = db::queries::is_token_revoked(pool, jti).await?;
+        if revoked {
+            return Err(ApiError::Unauthorized("Token has been revoked".to_string()));
+        }
     }
+
+    Ok(claims)
 }
 
+/// Like [`AuthenticatedUser`], but only accepts ML-DSA signed tokens
+pub struct PostQuantumUser(pub Claims);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for PostQuantumUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthenticatedUser(claims) = AuthenticatedUser::from_request_parts(parts, state).await?;
+        if !claims.post_quantum {
+            return Err(ApiError::Forbidden("Post-quantum signed token required".to_string()));
+        }
+        Ok(PostQuantumUser(claims))
+    }
+}
+
+/// Route layer: reject callers without a post-quantum token
+/// (`router.route_layer(middleware::from_fn(require_post_quantum))`).
+/// The caller's claims are left in the request extensions.
+pub async fn require_post_quantum(PostQuantumUser(claims): PostQuantumUser, mut request: Request, next: Next) -> Response {
+    request.extensions_mut().insert(claims);
+    next.run(request).await
+}
+
+/// How long an MFA verification counts as recent for [`RecentMfa`]
+pub const MFA_MAX_AGE_SECS: i64 = 600;
+
+/// Step-up: like [`AuthenticatedUser`], but the session must have passed MFA
+/// within [`MFA_MAX_AGE_SECS`] (`POST /auth/mfa/verify` refreshes it)
+pub struct RecentMfa(pub Claims);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RecentMfa
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthenticatedUser(claims) = AuthenticatedUser::from_request_parts(parts, state).await?;
+        if !claims.mfa_within(MFA_MAX_AGE_SECS) {
+            return Err(ApiError::Forbidden("Recent MFA verification required".to_string()));
+        }
+        Ok(RecentMfa(claims))
+    }
+}
+
+/// Route layer form of [`RecentMfa`], for routers whose handlers don't take it
+pub async fn require_recent_mfa(RecentMfa(claims): RecentMfa, mut request: Request, next: Next) -> Response {
+    request.extensions_mut().insert(claims);
+    next.run(request).await
+}
+
+/// Routes reachable without a token. Everything else behind [`require_auth`]
+/// needs a valid one; matched against the route pattern, not the raw URI.
+pub const PUBLIC_ROUTES: &[&str] = &[
+    "/health",
+    "/.well-known/jwks.json",
+    "/.well-known/pq-jwks.json",
+    "/auth/login/:provider",
+    "/auth/callback/:provider",
+    "/auth/refresh",
+    "/auth/solana/challenge",
+    "/auth/solana/verify",
+];
+
+/// Route layer for the whole app (`router.route_layer(middleware::from_fn(require_auth))`,
+/// after every route is merged): 401 unless the route is in [`PUBLIC_ROUTES`].
+/// Handlers still extract [`AuthenticatedUser`] or a permission for the caller;
+/// the claims are left in the request extensions for inner layers.
+pub async fn require_auth(matched: Option<MatchedPath>, request: Request, next: Next) -> Response {
+    if matched.is_some_and(|path| PUBLIC_ROUTES.contains(&path.as_str())) {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    match AuthenticatedUser::from_request_parts(&mut parts, &()).await {
+        Ok(AuthenticatedUser(claims)) => {
+            parts.extensions.insert(claims);
+            next.run(Request::from_parts(parts, body)).await
+        }
+        Err(rejection) => rejection.into_response(),
+    }
+}
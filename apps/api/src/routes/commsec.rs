@@ -1,12 +1,22 @@
 use axum::{
-    routing::post,
+    routing::{get, post},
     Json as AxumJson, Router,
     response::IntoResponse,
-    http::StatusCode,
+    extract::{Query, State},
+    Extension,
 };
 use base64::{engine::general_purpose, Engine as _};
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
+use zeroize::Zeroizing;
+
+use commsec_client::types::{
+    AeadDecryptRequest, AeadDecryptResponse, AeadEncryptRequest, AeadEncryptResponse,
+    AlertsResponse, DecapsulateRequest, DecapsulateResponse, EncapsulateRequest,
+    EncapsulateResponse, KeypairResponse,
+};
 
 use pqcrypto_mlkem::mlkem1024::{
     keypair as kem_keypair, encapsulate as pq_encapsulate, decapsulate as pq_decapsulate,
@@ -21,37 +31,125 @@ use aes_gcm::{
     aead::{Aead, Payload},
 };
 
-/// Shared state containing persistent PQ keypair
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth_middleware::Claims;
+use crate::routes::commsec_monitor::{ClientId, MonitorConfig, Operation, TrafficMonitor};
+use crate::routes::error::ApiError;
+use crate::routes::retention::RetentionPolicy;
+
+/// Server KEM keypair. The secret is held as raw bytes that are zeroed
+/// when the key is rotated out and dropped.
+pub struct ServerKemKey {
+    pub public_key: Vec<u8>,
+    secret_key: Zeroizing<Vec<u8>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ServerKemKey {
+    pub fn generate() -> Self {
+        let (pk, sk) = kem_keypair();
+        ServerKemKey {
+            public_key: pk.as_bytes().to_vec(),
+            secret_key: Zeroizing::new(sk.as_bytes().to_vec()),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn secret_key(&self) -> &[u8] {
+        &self.secret_key
+    }
+
+    pub fn expires_at(&self, ttl: chrono::Duration) -> DateTime<Utc> {
+        self.created_at + ttl
+    }
+}
+
+/// Shared state containing the rotating PQ keypair and the traffic monitor
 #[derive(Clone)]
 pub struct CommsecState {
-    pub pk: PublicKey,
-    pub sk: SecretKey,
+    pub kem: Arc<RwLock<ServerKemKey>>,
+    pub kem_ttl: chrono::Duration,
+    pub monitor: Arc<TrafficMonitor>,
+}
+
+impl CommsecState {
+    /// Replace the KEM key if it has outlived its TTL; returns whether it rotated
+    pub fn rotate_kem_if_expired(&self) -> bool {
+        if self.kem.read().unwrap().expires_at(self.kem_ttl) > Utc::now() {
+            return false;
+        }
+
+        let mut kem = self.kem.write().unwrap();
+        if kem.expires_at(self.kem_ttl) > Utc::now() {
+            return false; // another caller rotated first
+        }
+        *kem = ServerKemKey::generate(); // old secret is zeroed on drop
+        tracing::info!("🔁 Rotated CommSec server KEM key");
+        true
+    }
 }
 
 pub fn init_commsec_state() -> CommsecState {
-    let (pk, sk) = kem_keypair();
-    CommsecState { pk, sk }
+    let policy = RetentionPolicy::from_env();
+    CommsecState {
+        kem: Arc::new(RwLock::new(ServerKemKey::generate())),
+        kem_ttl: chrono::Duration::from_std(policy.server_kem_key_ttl).unwrap_or(chrono::Duration::MAX),
+        monitor: Arc::new(TrafficMonitor::new(MonitorConfig::from_env())),
+    }
 }
 
-pub fn commsec_routes(state: CommsecState) -> Router {
+/// Hands out the server KEM key pair; main.rs requires a recent MFA check here
+pub fn commsec_key_routes(state: Arc<CommsecState>) -> Router {
     Router::new()
         .route("/commsec/keypair", post(get_keypair))
+        .with_state(state)
+}
+
+pub fn commsec_traffic_routes(state: Arc<CommsecState>) -> Router {
+    Router::new()
         .route("/commsec/encapsulate", post(encapsulate))
         .route("/commsec/decapsulate", post(decapsulate))
         .route("/commsec/aead/encrypt", post(aead_encrypt))
         .route("/commsec/aead/decrypt", post(aead_decrypt))
-        .with_state(Arc::new(state))
+        .route("/commsec/alerts", get(get_alerts))
+        .with_state(state)
 }
 
-#[derive(Serialize)]
-struct KeypairResponse {
-    public_key: String,
-    secret_key: String,
+/// Short, stable name for key material in the audit trail
+fn fingerprint(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))[..16].to_string()
 }
 
-async fn get_keypair(state: axum::extract::State<Arc<CommsecState>>) -> impl IntoResponse {
-    let pk_b64 = general_purpose::STANDARD.encode(state.pk.as_bytes());
-    let sk_b64 = general_purpose::STANDARD.encode(state.sk.as_bytes());
+/// Audit a key operation. The CommSec routers are also served bare (no
+/// database, no auth layer), so both the log and the caller's claims are optional.
+async fn audit_key_operation(
+    audit: Option<Audit>,
+    caller: Option<Extension<Claims>>,
+    client: &str,
+    entry: AuditEntry,
+) {
+    if let Some(audit) = audit {
+        let actor = caller.map(|Extension(claims)| claims.sub).unwrap_or_else(|| client.to_string());
+        audit.record(&actor, entry).await;
+    }
+}
+
+async fn get_keypair(
+    state: axum::extract::State<Arc<CommsecState>>,
+    ClientId(client): ClientId,
+    caller: Option<Extension<Claims>>,
+    audit: Option<Audit>,
+) -> impl IntoResponse {
+    state.rotate_kem_if_expired();
+    let (pk_b64, sk_b64, key_id) = {
+        let kem = state.kem.read().unwrap();
+        (
+            general_purpose::STANDARD.encode(&kem.public_key),
+            general_purpose::STANDARD.encode(kem.secret_key()),
+            fingerprint(&kem.public_key),
+        )
+    };
+    audit_key_operation(audit, caller, &client, AuditEntry::new("commsec.keypair", "kem_key", key_id)).await;
 
     AxumJson(KeypairResponse {
         public_key: pk_b64,
@@ -59,29 +157,27 @@ async fn get_keypair(state: axum::extract::State<Arc<CommsecState>>) -> impl Int
     }).into_response()
 }
 
-#[derive(Deserialize)]
-pub struct EncapsulateRequest {
-    pub public_key: String,
-}
-
-#[derive(Serialize)]
-pub struct EncapsulateResponse {
-    pub ciphertext: String,
-    pub shared_secret: String,
-}
+pub async fn encapsulate(
+    State(state): State<Arc<CommsecState>>,
+    ClientId(client): ClientId,
+    caller: Option<Extension<Claims>>,
+    audit: Option<Audit>,
+    AxumJson(req): AxumJson<EncapsulateRequest>,
+) -> impl IntoResponse {
+    state.monitor.record_call(&client, Operation::Encapsulate, req.public_key.len());
 
-pub async fn encapsulate(AxumJson(req): AxumJson<EncapsulateRequest>) -> impl IntoResponse {
     let pk_bytes = match general_purpose::STANDARD.decode(&req.public_key) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid base64".to_string()).into_response(),
     };
 
     let pk = match PublicKey::from_bytes(&pk_bytes) {
         Ok(p) => p,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid public key").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid public key".to_string()).into_response(),
     };
 
     let (ss, ct): (SharedSecret, Ciphertext) = pq_encapsulate(&pk);
+    audit_key_operation(audit, caller, &client, AuditEntry::new("commsec.encapsulate", "kem_key", fingerprint(&pk_bytes))).await;
 
     let ct_b64 = general_purpose::STANDARD.encode(ct.as_bytes());
     let ss_b64 = general_purpose::STANDARD.encode(ss.as_bytes());
@@ -92,66 +188,57 @@ pub async fn encapsulate(AxumJson(req): AxumJson<EncapsulateRequest>) -> impl In
     }).into_response()
 }
 
-#[derive(Deserialize)]
-pub struct DecapsulateRequest {
-    pub secret_key: String,
-    pub ciphertext: String,
-}
-
-#[derive(Serialize)]
-pub struct DecapsulateResponse {
-    pub shared_secret: String,
-}
+pub async fn decapsulate(
+    State(state): State<Arc<CommsecState>>,
+    ClientId(client): ClientId,
+    caller: Option<Extension<Claims>>,
+    audit: Option<Audit>,
+    AxumJson(req): AxumJson<DecapsulateRequest>,
+) -> impl IntoResponse {
+    state.monitor.record_call(&client, Operation::Decapsulate, req.ciphertext.len());
 
-pub async fn decapsulate(AxumJson(req): AxumJson<DecapsulateRequest>) -> impl IntoResponse {
     let sk_bytes = match general_purpose::STANDARD.decode(&req.secret_key) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid base64".to_string()).into_response(),
     };
     let ct_bytes = match general_purpose::STANDARD.decode(&req.ciphertext) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid base64".to_string()).into_response(),
     };
 
     let sk = match SecretKey::from_bytes(&sk_bytes) {
         Ok(s) => s,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid secret key").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid secret key".to_string()).into_response(),
     };
     let ct = match Ciphertext::from_bytes(&ct_bytes) {
         Ok(c) => c,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid ciphertext").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid ciphertext".to_string()).into_response(),
     };
 
     let ss = pq_decapsulate(&ct, &sk);
+    audit_key_operation(audit, caller, &client, AuditEntry::new("commsec.decapsulate", "kem_ciphertext", fingerprint(&ct_bytes))).await;
     let ss_b64 = general_purpose::STANDARD.encode(ss.as_bytes());
 
     AxumJson(DecapsulateResponse { shared_secret: ss_b64 }).into_response()
 }
 
-#[derive(Deserialize)]
-pub struct AeadEncryptRequest {
-    pub key: String,
-    pub nonce: String,  // ✅ now required
-    pub plaintext: String,
-    pub associated_data: Option<String>,
-}
+pub async fn aead_encrypt(
+    State(state): State<Arc<CommsecState>>,
+    ClientId(client): ClientId,
+    AxumJson(req): AxumJson<AeadEncryptRequest>,
+) -> impl IntoResponse {
+    state.monitor.record_call(&client, Operation::Encrypt, req.plaintext.len());
 
-#[derive(Serialize)]
-pub struct AeadEncryptResponse {
-    pub ciphertext: String,
-}
-
-pub async fn aead_encrypt(AxumJson(req): AxumJson<AeadEncryptRequest>) -> impl IntoResponse {
     let key_bytes = match general_purpose::STANDARD.decode(&req.key) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid key base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid key base64".to_string()).into_response(),
     };
     let nonce_bytes = match general_purpose::STANDARD.decode(&req.nonce) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid nonce base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid nonce base64".to_string()).into_response(),
     };
     if nonce_bytes.len() != 12 {
-        return (StatusCode::BAD_REQUEST, "nonce must be 12 bytes").into_response();
+        return ApiError::BadRequest("nonce must be 12 bytes".to_string()).into_response();
     }
 
     let key = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
@@ -169,39 +256,32 @@ pub async fn aead_encrypt(AxumJson(req): AxumJson<AeadEncryptRequest>) -> impl I
             let ct_b64 = general_purpose::STANDARD.encode(ct);
             AxumJson(AeadEncryptResponse { ciphertext: ct_b64 }).into_response()
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "encryption failed").into_response(),
+        Err(_) => ApiError::Internal("encryption failed".to_string()).into_response(),
     }
 }
 
-#[derive(Deserialize)]
-pub struct AeadDecryptRequest {
-    pub key: String,
-    pub nonce: String,  // ✅ now required
-    pub ciphertext: String,
-    pub associated_data: Option<String>,
-}
-
-#[derive(Serialize)]
-pub struct AeadDecryptResponse {
-    pub plaintext: String,
-}
+pub async fn aead_decrypt(
+    State(state): State<Arc<CommsecState>>,
+    ClientId(client): ClientId,
+    AxumJson(req): AxumJson<AeadDecryptRequest>,
+) -> impl IntoResponse {
+    state.monitor.record_call(&client, Operation::Decrypt, req.ciphertext.len());
 
-pub async fn aead_decrypt(AxumJson(req): AxumJson<AeadDecryptRequest>) -> impl IntoResponse {
     let key_bytes = match general_purpose::STANDARD.decode(&req.key) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid key base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid key base64".to_string()).into_response(),
     };
     let nonce_bytes = match general_purpose::STANDARD.decode(&req.nonce) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid nonce base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid nonce base64".to_string()).into_response(),
     };
     if nonce_bytes.len() != 12 {
-        return (StatusCode::BAD_REQUEST, "nonce must be 12 bytes").into_response();
+        return ApiError::BadRequest("nonce must be 12 bytes".to_string()).into_response();
     }
 
     let ct_bytes = match general_purpose::STANDARD.decode(&req.ciphertext) {
         Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid ciphertext base64").into_response(),
+        Err(_) => return ApiError::BadRequest("invalid ciphertext base64".to_string()).into_response(),
     };
 
     let key = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
@@ -214,15 +294,39 @@ pub async fn aead_decrypt(AxumJson(req): AxumJson<AeadDecryptRequest>) -> impl I
         Payload { msg: &ct_bytes, aad: &[] }
     };
 
-    match cipher.decrypt(nonce, payload) {
+    let result = cipher.decrypt(nonce, payload);
+    state.monitor.record_decrypt(
+        &client,
+        &nonce_bytes,
+        &ct_bytes,
+        req.associated_data.as_deref().map(str::as_bytes),
+        result.is_ok(),
+    );
+
+    match result {
         Ok(pt) => {
             let pt_str = match String::from_utf8(pt) {
                 Ok(s) => s,
-                Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "invalid utf-8").into_response(),
+                Err(_) => return ApiError::Internal("invalid utf-8".to_string()).into_response(),
             };
             AxumJson(AeadDecryptResponse { plaintext: pt_str }).into_response()
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "decryption failed").into_response(),
+        Err(_) => ApiError::Internal("decryption failed".to_string()).into_response(),
     }
 }
 
+
+#[derive(Deserialize)]
+pub struct AlertsQuery {
+    pub since: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+/// Anomalies raised by the traffic monitor, oldest first
+pub async fn get_alerts(
+    State(state): State<Arc<CommsecState>>,
+    Query(query): Query<AlertsQuery>,
+) -> impl IntoResponse {
+    let alerts = state.monitor.alerts(query.since, query.limit.unwrap_or(100));
+    AxumJson(AlertsResponse { alerts }).into_response()
+}
@@ -0,0 +1,537 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+use chrono::Utc;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::routes::auth_middleware::Claims;
+
+pub use commsec_client::client::CLIENT_ID_HEADER;
+pub use commsec_client::types::{Alert, AlertKind};
+
+/// Tunables for the CommSec traffic anomaly monitor
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    /// EWMA smoothing factor (0 < alpha <= 1)
+    pub alpha: f64,
+    /// |z| above which a sample is considered anomalous
+    pub z_threshold: f64,
+    /// Samples required before a baseline is trusted
+    pub warmup_samples: u64,
+    /// Length of the window used to measure call rates
+    pub rate_window: Duration,
+    /// Smoothed decrypt failure ratio that raises an alert
+    pub failure_ratio_threshold: f64,
+    /// Number of alerts kept in memory
+    pub max_alerts: usize,
+    /// Number of ciphertexts remembered for AD tampering detection
+    pub max_tracked_ciphertexts: usize,
+    /// Clients quiet for this long lose their baselines
+    pub client_idle_ttl: Duration,
+    /// Most clients tracked at once; the longest idle go first
+    pub max_clients: usize,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 0.2,
+            z_threshold: 4.0,
+            warmup_samples: 10,
+            rate_window: Duration::from_secs(10),
+            failure_ratio_threshold: 0.5,
+            max_alerts: 256,
+            max_tracked_ciphertexts: 4096,
+            client_idle_ttl: Duration::from_secs(3600),
+            max_clients: 10_000,
+        }
+    }
+}
+
+impl MonitorConfig {
+    /// Defaults overridden by optional `COMMSEC_MONITOR_*` env vars
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(key: &str) -> Option<T> {
+            std::env::var(key).ok().and_then(|v| v.parse().ok())
+        }
+
+        let mut config = Self::default();
+        if let Some(v) = var("COMMSEC_MONITOR_ALPHA") {
+            config.alpha = v;
+        }
+        if let Some(v) = var("COMMSEC_MONITOR_Z_THRESHOLD") {
+            config.z_threshold = v;
+        }
+        if let Some(v) = var("COMMSEC_MONITOR_WARMUP") {
+            config.warmup_samples = v;
+        }
+        if let Some(v) = var("COMMSEC_MONITOR_RATE_WINDOW_SECS") {
+            config.rate_window = Duration::from_secs(v);
+        }
+        if let Some(v) = var("COMMSEC_MONITOR_FAILURE_RATIO") {
+            config.failure_ratio_threshold = v;
+        }
+        config
+    }
+}
+
+/// Exponentially weighted mean/variance, updated one sample at a time
+#[derive(Debug, Clone, Default)]
+pub struct Ewma {
+    mean: f64,
+    var: f64,
+    samples: u64,
+}
+
+impl Ewma {
+    pub fn update(&mut self, x: f64, alpha: f64) {
+        if self.samples == 0 {
+            self.mean = x;
+            self.var = 0.0;
+        } else {
+            let diff = x - self.mean;
+            let incr = alpha * diff;
+            self.mean += incr;
+            self.var = (1.0 - alpha) * (self.var + diff * incr);
+        }
+        self.samples += 1;
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Standard score of `x` against the current baseline.
+    /// The deviation is floored at 1.0 so a perfectly flat baseline
+    /// doesn't turn every small change into an infinite score.
+    pub fn z_score(&self, x: f64) -> f64 {
+        (x - self.mean) / self.var.sqrt().max(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Encapsulate,
+    Decapsulate,
+    Encrypt,
+    Decrypt,
+}
+
+#[derive(Debug)]
+struct ClientStats {
+    last_seen: Instant,
+    window_start: Instant,
+    window_calls: u64,
+    rate_alerted: bool,
+    failure_alerted: bool,
+    rate: Ewma,
+    failures: Ewma,
+    sizes: Ewma,
+}
+
+impl ClientStats {
+    fn new(now: Instant) -> Self {
+        Self {
+            last_seen: now,
+            window_start: now,
+            window_calls: 0,
+            rate_alerted: false,
+            failure_alerted: false,
+            rate: Ewma::default(),
+            failures: Ewma::default(),
+            sizes: Ewma::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MonitorInner {
+    clients: HashMap<String, ClientStats>,
+    last_sweep: Option<Instant>,
+    alerts: VecDeque<Alert>,
+    next_alert_id: u64,
+    // ciphertext fingerprint -> hash of the AD it last decrypted with
+    known_ad: HashMap<[u8; 32], [u8; 32]>,
+    known_ad_order: VecDeque<[u8; 32]>,
+}
+
+impl MonitorInner {
+    /// The client's stats, forgetting idle clients to make room for new ones
+    fn stats_for(&mut self, client: &str, now: Instant, cfg: &MonitorConfig) -> &mut ClientStats {
+        if !self.clients.contains_key(client) {
+            let due = self.last_sweep.is_none_or(|at| now.duration_since(at) >= cfg.rate_window);
+            if due {
+                self.clients.retain(|_, stats| now.duration_since(stats.last_seen) < cfg.client_idle_ttl);
+                self.last_sweep = Some(now);
+            }
+            while self.clients.len() >= cfg.max_clients.max(1) {
+                let idlest = self.clients.iter().min_by_key(|(_, stats)| stats.last_seen).map(|(id, _)| id.clone());
+                match idlest {
+                    Some(id) => self.clients.remove(&id),
+                    None => break,
+                };
+            }
+        }
+        let stats = self.clients.entry(client.to_string()).or_insert_with(|| ClientStats::new(now));
+        stats.last_seen = now;
+        stats
+    }
+}
+
+/// Local, offline anomaly detector for CommSec traffic
+pub struct TrafficMonitor {
+    config: MonitorConfig,
+    inner: Mutex<MonitorInner>,
+}
+
+impl TrafficMonitor {
+    pub fn new(config: MonitorConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(MonitorInner::default()),
+        }
+    }
+
+    /// Record one CommSec call and score its rate and payload size
+    pub fn record_call(&self, client: &str, op: Operation, payload_len: usize) {
+        let cfg = &self.config;
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        let mut raised = Vec::new();
+
+        let stats = inner.stats_for(client, now, cfg);
+
+        // ⏱ close finished windows (idle windows count as zero calls, capped)
+        let elapsed = now.duration_since(stats.window_start);
+        if elapsed >= cfg.rate_window {
+            let windows = (elapsed.as_secs_f64() / cfg.rate_window.as_secs_f64()) as u64;
+            stats.rate.update(stats.window_calls as f64, cfg.alpha);
+            for _ in 1..windows.min(cfg.warmup_samples) {
+                stats.rate.update(0.0, cfg.alpha);
+            }
+            stats.window_start = now;
+            stats.window_calls = 0;
+            stats.rate_alerted = false;
+        }
+
+        stats.window_calls += 1;
+        if stats.rate.samples() >= cfg.warmup_samples && !stats.rate_alerted {
+            let z = stats.rate.z_score(stats.window_calls as f64);
+            if z > cfg.z_threshold {
+                stats.rate_alerted = true;
+                raised.push((
+                    AlertKind::CallRate,
+                    z,
+                    format!(
+                        "{} calls in current window (baseline {:.1})",
+                        stats.window_calls,
+                        stats.rate.mean()
+                    ),
+                ));
+            }
+        }
+
+        // 📦 payload size distribution
+        let size = payload_len as f64;
+        if stats.sizes.samples() >= cfg.warmup_samples {
+            let z = stats.sizes.z_score(size);
+            if z.abs() > cfg.z_threshold {
+                raised.push((
+                    AlertKind::PayloadSize,
+                    z,
+                    format!(
+                        "{:?} payload of {} bytes (baseline {:.0})",
+                        op,
+                        payload_len,
+                        stats.sizes.mean()
+                    ),
+                ));
+            }
+        }
+        stats.sizes.update(size, cfg.alpha);
+
+        for (kind, score, detail) in raised {
+            self.push_alert(&mut inner, client, kind, score, detail);
+        }
+    }
+
+    /// Record the outcome of an AEAD decryption and check for AD tampering
+    pub fn record_decrypt(
+        &self,
+        client: &str,
+        nonce: &[u8],
+        ciphertext: &[u8],
+        associated_data: Option<&[u8]>,
+        ok: bool,
+    ) {
+        let cfg = &self.config;
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        let mut raised = Vec::new();
+
+        let stats = inner.stats_for(client, now, cfg);
+
+        stats.failures.update(if ok { 0.0 } else { 1.0 }, cfg.alpha);
+        let ratio = stats.failures.mean();
+        if stats.failures.samples() >= cfg.warmup_samples {
+            if ratio >= cfg.failure_ratio_threshold && !stats.failure_alerted {
+                stats.failure_alerted = true;
+                raised.push((
+                    AlertKind::DecryptFailures,
+                    ratio,
+                    format!("decrypt failure ratio {:.2}", ratio),
+                ));
+            } else if ratio < cfg.failure_ratio_threshold / 2.0 {
+                stats.failure_alerted = false;
+            }
+        }
+
+        // 🏷 same nonce+ciphertext previously opened under a different AD
+        let fingerprint: [u8; 32] = Sha256::new()
+            .chain_update(nonce)
+            .chain_update(ciphertext)
+            .finalize()
+            .into();
+        let ad_hash: [u8; 32] = Sha256::digest(associated_data.unwrap_or_default()).into();
+
+        if ok {
+            if inner.known_ad.insert(fingerprint, ad_hash).is_none() {
+                inner.known_ad_order.push_back(fingerprint);
+                if inner.known_ad_order.len() > cfg.max_tracked_ciphertexts {
+                    if let Some(old) = inner.known_ad_order.pop_front() {
+                        inner.known_ad.remove(&old);
+                    }
+                }
+            }
+        } else if inner.known_ad.get(&fingerprint).is_some_and(|h| *h != ad_hash) {
+            raised.push((
+                AlertKind::AdTampering,
+                1.0,
+                "known ciphertext presented with altered associated data".to_string(),
+            ));
+        }
+
+        for (kind, score, detail) in raised {
+            self.push_alert(&mut inner, client, kind, score, detail);
+        }
+    }
+
+    /// Alerts newer than `since`, oldest first
+    pub fn alerts(&self, since: Option<u64>, limit: usize) -> Vec<Alert> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .alerts
+            .iter()
+            .filter(|a| since.is_none_or(|s| a.id > s))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn push_alert(
+        &self,
+        inner: &mut MonitorInner,
+        client: &str,
+        kind: AlertKind,
+        score: f64,
+        detail: String,
+    ) {
+        inner.next_alert_id += 1;
+        let alert = Alert {
+            id: inner.next_alert_id,
+            at: Utc::now(),
+            client: client.to_string(),
+            kind,
+            score,
+            detail,
+        };
+
+        tracing::warn!(
+            client = %alert.client,
+            kind = ?alert.kind,
+            score = alert.score,
+            "🚨 CommSec anomaly: {}",
+            alert.detail
+        );
+
+        inner.alerts.push_back(alert);
+        if inner.alerts.len() > self.config.max_alerts {
+            inner.alerts.pop_front();
+        }
+    }
+}
+
+/// Identifies the caller for monitoring: the verified token subject (or
+/// service account) when an auth layer ran, else — on the bare CommSec
+/// routers — the `X-Client-Id` header, else the peer IP
+pub struct ClientId(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientId
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // a header the caller picks can't be trusted once there's an identity
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(ClientId(claims.sub.clone()));
+        }
+        if let Some(id) = parts
+            .headers
+            .get(CLIENT_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+        {
+            return Ok(ClientId(id.to_string()));
+        }
+
+        let id = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        Ok(ClientId(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Short rate windows so a test can close a few of them in real time
+    fn monitor() -> TrafficMonitor {
+        TrafficMonitor::new(MonitorConfig {
+            warmup_samples: 5,
+            rate_window: Duration::from_millis(40),
+            ..MonitorConfig::default()
+        })
+    }
+
+    fn kinds(monitor: &TrafficMonitor) -> Vec<AlertKind> {
+        monitor.alerts(None, usize::MAX).into_iter().map(|alert| alert.kind).collect()
+    }
+
+    /// One call per window, payloads a few bytes either side of 100
+    fn baseline(monitor: &TrafficMonitor, client: &str) {
+        for n in 0..6 {
+            monitor.record_call(client, Operation::Encrypt, 100 + (n % 2) * 2);
+            std::thread::sleep(Duration::from_millis(45));
+        }
+    }
+
+    #[test]
+    fn steady_traffic_raises_nothing() {
+        let monitor = monitor();
+        baseline(&monitor, "steady");
+        monitor.record_call("steady", Operation::Encrypt, 101);
+        for _ in 0..20 {
+            monitor.record_decrypt("steady", &[1; 12], &[2; 32], None, true);
+        }
+        assert!(kinds(&monitor).is_empty());
+    }
+
+    #[test]
+    fn burst_of_calls_raises_call_rate_once_per_window() {
+        let monitor = monitor();
+        baseline(&monitor, "bursty");
+        for _ in 0..30 {
+            monitor.record_call("bursty", Operation::Encapsulate, 101);
+        }
+        assert_eq!(kinds(&monitor), [AlertKind::CallRate]);
+        assert_eq!(monitor.alerts(None, 1)[0].client, "bursty");
+    }
+
+    #[test]
+    fn outsized_payload_raises_payload_size() {
+        let monitor = monitor();
+        baseline(&monitor, "bulky");
+        monitor.record_call("bulky", Operation::Encrypt, 64 * 1024);
+        assert_eq!(kinds(&monitor), [AlertKind::PayloadSize]);
+        assert!(monitor.alerts(None, 1)[0].score > 4.0);
+    }
+
+    #[test]
+    fn failing_decrypts_raise_failure_ratio_once() {
+        let monitor = monitor();
+        for _ in 0..5 {
+            monitor.record_decrypt("prober", &[1; 12], &[2; 32], None, true);
+        }
+        // the smoothed ratio climbs past 0.5 on the fourth failure in a row
+        for n in 0..3 {
+            monitor.record_decrypt("prober", &[1; 12], &[3 + n; 32], None, false);
+        }
+        assert!(kinds(&monitor).is_empty());
+        for n in 0..5 {
+            monitor.record_decrypt("prober", &[1; 12], &[10 + n; 32], None, false);
+        }
+        assert_eq!(kinds(&monitor), [AlertKind::DecryptFailures]);
+    }
+
+    #[test]
+    fn idle_and_surplus_clients_are_forgotten() {
+        let monitor = TrafficMonitor::new(MonitorConfig {
+            rate_window: Duration::from_millis(10),
+            client_idle_ttl: Duration::from_millis(30),
+            max_clients: 3,
+            ..MonitorConfig::default()
+        });
+        let tracked = |monitor: &TrafficMonitor| monitor.inner.lock().unwrap().clients.len();
+
+        // a fresh id per call can't grow the table past the cap
+        for n in 0..10 {
+            monitor.record_call(&format!("rotating-{}", n), Operation::Encrypt, 100);
+        }
+        assert_eq!(tracked(&monitor), 3);
+        assert!(monitor.inner.lock().unwrap().clients.contains_key("rotating-9"));
+
+        // and the quiet ones go once they've idled out
+        std::thread::sleep(Duration::from_millis(40));
+        monitor.record_call("newcomer", Operation::Encrypt, 100);
+        assert_eq!(tracked(&monitor), 1);
+    }
+
+    #[tokio::test]
+    async fn verified_callers_are_known_by_their_subject() {
+        let request = || {
+            axum::http::Request::builder()
+                .header(CLIENT_ID_HEADER, "someone-else")
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+        let ClientId(id) = ClientId::from_request_parts(&mut request(), &()).await.unwrap();
+        assert_eq!(id, "someone-else");
+
+        let claims: Claims = serde_json::from_value(serde_json::json!({ "sub": "svc-42", "exp": 0, "provider": "test" })).unwrap();
+        let mut parts = request();
+        parts.extensions.insert(claims);
+        let ClientId(id) = ClientId::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(id, "svc-42");
+    }
+
+    #[test]
+    fn baselines_are_per_client() {
+        let monitor = monitor();
+        baseline(&monitor, "small");
+        // a newcomer's first large payload has no baseline to stand out against
+        monitor.record_call("large", Operation::Encrypt, 64 * 1024);
+        assert!(kinds(&monitor).is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+
+/// Every way a handler or extractor can fail. Rendered as an RFC 7807
+/// `application/problem+json` body with a machine-readable `code`.
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    PreconditionFailed(String), // If-Match named a version that's no longer current
+    Unprocessable(String), // well-formed, but refers to rows that don't exist or breaks a constraint
+    Validation(Vec<FieldError>), // payload fields that broke their rules
+    TooManyRequests(String),
+    BadGateway(String), // an upstream (OAuth provider, JWKS) misbehaved
+    Internal(String), // logged; clients only see a generic detail
+    Database(sqlx::Error), // anything sqlx reports that doesn't map onto the above
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// One broken rule of a payload, listed under `errors` in the problem body
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String, // the rule: "length", "email", "range", ...
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ApiError::Unprocessable(_) | ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::BadGateway(_) => StatusCode::BAD_GATEWAY,
+            ApiError::Internal(_) | ApiError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable identifier clients can match on, unlike `detail`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::PreconditionFailed(_) => "precondition_failed",
+            ApiError::Unprocessable(_) => "unprocessable",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::TooManyRequests(_) => "rate_limited",
+            ApiError::BadGateway(_) => "bad_gateway",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::Database(_) => "database_error",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            ApiError::BadRequest(detail)
+            | ApiError::Unauthorized(detail)
+            | ApiError::Forbidden(detail)
+            | ApiError::NotFound(detail)
+            | ApiError::Conflict(detail)
+            | ApiError::PreconditionFailed(detail)
+            | ApiError::Unprocessable(detail)
+            | ApiError::TooManyRequests(detail)
+            | ApiError::BadGateway(detail) => detail,
+            ApiError::Validation(_) => "Request payload is invalid",
+            ApiError::Internal(_) => "Internal server error",
+            ApiError::Database(_) => "Database error",
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::RowNotFound => ApiError::NotFound("Not found".to_string()),
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                ApiError::Conflict("A record with these values already exists".to_string())
+            }
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                ApiError::Unprocessable("Refers to a record that doesn't exist".to_string())
+            }
+            sqlx::Error::Database(e) if e.is_check_violation() => {
+                ApiError::Unprocessable(format!("Violates constraint {}", e.constraint().unwrap_or("check")))
+            }
+            _ => ApiError::Database(err),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match &self {
+            ApiError::Internal(message) => eprintln!("Internal error: {}", message),
+            ApiError::Database(err) => eprintln!("DB error: {:?}", err),
+            _ => {}
+        }
+
+        let status = self.status();
+        let mut body = json!({
+            "type": "about:blank",
+            "title": status.canonical_reason().unwrap_or_default(),
+            "status": status.as_u16(),
+            "detail": self.detail(),
+            "code": self.code(),
+        });
+        if let ApiError::Validation(errors) = &self {
+            body["errors"] = json!(errors);
+        }
+        let mut response = (status, Json(body)).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+        response
+    }
+}
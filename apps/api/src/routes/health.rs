@@ -0,0 +1,10 @@
+use axum::{routing::get, Router};
+
+/// Liveness probe, public
+pub fn health_routes() -> Router {
+    Router::new().route("/health", get(health))
+}
+
+async fn health() -> &'static str {
+    "ok"
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::Path,
+    routing::{get, delete},
+    Router, Json, Extension,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::{User, UserIdentity};
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth_middleware::AuthenticatedUser;
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::orgs::{OrgRole, DEFAULT_ORG_ID};
+use crate::routes::roles::DEFAULT_ROLE;
+
+/// What a provider told us about the account that just logged in
+#[derive(Debug, Clone)]
+pub struct ProviderProfile {
+    pub provider: String,
+    pub subject: String, // stable provider-side id (Google `sub`, GitHub `id`, ...)
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub username: Option<String>,
+}
+
+pub fn identity_routes() -> Router {
+    Router::new()
+        .route("/auth/identities", get(list_identities))
+        .route("/auth/identities/:id", delete(unlink_identity))
+}
+
+fn conflict_on_unique(err: sqlx::Error) -> ApiError {
+    match &err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            ApiError::Conflict("Identity already linked".to_string())
+        }
+        _ => err.into(),
+    }
+}
+
+/// Find the user behind a provider login, provisioning one on first login.
+///
+/// With `link_to`, the identity is attached to that existing user instead
+/// (account linking). Without it, a first-time identity joins the user with
+/// the same verified email, or a fresh user is created.
+pub async fn resolve_login_user(
+    pool: &PgPool,
+    profile: &ProviderProfile,
+    link_to: Option<Uuid>,
+) -> ApiResult<User> {
+    let email = profile.email.as_deref();
+
+    if let Some(identity) = queries::find_identity(pool, &profile.provider, &profile.subject)
+        .await?
+    {
+        if link_to.is_some_and(|uid| uid != identity.user_id) {
+            return Err(ApiError::Conflict("Identity is linked to another user".to_string()));
+        }
+        queries::touch_identity(pool, identity.id, email).await?;
+        return queries::get_user(pool, identity.user_id)
+            .await?
+            .ok_or(ApiError::NotFound("User not found".to_string()));
+    }
+
+    let user = match link_to {
+        Some(uid) => queries::get_user(pool, uid)
+            .await?
+            .ok_or(ApiError::NotFound("User not found".to_string()))?,
+        None => provision_user(pool, profile).await?,
+    };
+
+    queries::link_identity(pool, user.id, &profile.provider, &profile.subject, email)
+        .await
+        .map_err(conflict_on_unique)?;
+
+    println!("🔗 Linked {} identity {} to user {}", profile.provider, profile.subject, user.id);
+    Ok(user)
+}
+
+async fn provision_user(pool: &PgPool, profile: &ProviderProfile) -> ApiResult<User> {
+    // only trust an email for matching if the provider vouches for it
+    if let Some(email) = profile.email.as_deref().filter(|_| profile.email_verified) {
+        if let Some(user) = queries::get_user_by_email(pool, email).await? {
+            return Ok(user);
+        }
+    }
+
+    // emails are unique: an unverified claim to a taken one gets a placeholder
+    let email = match profile.email.clone() {
+        Some(email) if queries::get_user_by_email(pool, &email).await?.is_none() => email,
+        _ => format!("{}-{}@users.noreply.tidasone", profile.provider, profile.subject),
+    };
+    let username = profile
+        .username
+        .clone()
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| email.split('@').next().map(str::to_string))
+        .unwrap_or_else(|| format!("{}-{}", profile.provider, profile.subject));
+    // usernames are unique too: a taken one gets a short random suffix
+    let username = match queries::get_user_by_username(pool, &username).await? {
+        Some(_) => format!("{}-{}", username, &Uuid::new_v4().simple().to_string()[..6]),
+        None => username,
+    };
+
+    let user = queries::create_user(pool, &username, &email).await?;
+    queries::assign_role(pool, user.id, DEFAULT_ROLE, None).await?;
+    queries::set_org_member(pool, DEFAULT_ORG_ID, user.id, OrgRole::Member.as_str())
+        .await?;
+    Ok(user)
+}
+
+fn caller_id(user: &crate::routes::auth_middleware::Claims) -> ApiResult<Uuid> {
+    user.user_id()
+        .ok_or(ApiError::Forbidden("Token is not bound to a user".to_string()))
+}
+
+async fn list_identities(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<UserIdentity>>> {
+    let user_id = caller_id(&user)?;
+    let identities = queries::get_user_identities(&pool, user_id).await?;
+    Ok(Json(identities))
+}
+
+async fn unlink_identity(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let user_id = caller_id(&user)?;
+    let identities = queries::get_user_identities(&pool, user_id).await?;
+
+    let Some(identity) = identities.iter().find(|i| i.id == id) else {
+        return Err(ApiError::NotFound("Identity not found".to_string()));
+    };
+    if identities.len() == 1 {
+        return Err(ApiError::Conflict("Cannot unlink the last identity".to_string()));
+    }
+
+    queries::delete_identity(&pool, user_id, id).await?;
+    audit.record(&user.sub, AuditEntry::new("identity.unlink", "user", user_id).deleted(identity)).await;
+    Ok(Json("Identity unlinked"))
+}
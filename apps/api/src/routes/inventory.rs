@@ -1,38 +1,55 @@
 use axum::{
     extract::Path,
+    response::Response,
     routing::{get, post, put, delete},
     Router, Json, Extension,
 };
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use serde_json::json;
 use sqlx::PgPool;
 use uuid::Uuid;
-use chrono::{Utc, DateTime};
-use axum::http::StatusCode;
+use validator::Validate;
 
-use crate::routes::auth_middleware::AuthenticatedUser; // ✅ import middleware
+use db::models::{InventoryFields, Share};
+use db::queries;
 
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
-pub struct InventoryItem {
-    pub id: Uuid,
-    pub owner_id: Uuid,
-    pub name: String,
-    pub description: Option<String>,
-    pub quantity: i32,
-    pub location: Option<String>,
-    pub token_id: Option<String>,
-    pub created_at: DateTime<Utc>,
-}
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth_middleware::{AuthenticatedUser, RecentMfa}; // ✅ import middleware
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::orgs::{active_org, Tenant};
+use crate::routes::ownership::{check_share_target, require, share_error, Access, ShareRequest};
+use crate::routes::validation::{not_blank, ValidJson};
+use crate::routes::versioning::{MergePatch, Preconditions, Tagged};
+
+pub use db::models::Inventory as InventoryItem;
 
-#[derive(Deserialize)]
+/// Item fields; the owner is always the caller who created it
+#[derive(Deserialize, Validate)]
 pub struct NewInventoryItem {
-    pub owner_id: Uuid,
+    #[validate(length(max = 200, message = "must be at most 200 characters"), custom(function = "not_blank"))]
     pub name: String,
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
     pub description: Option<String>,
+    #[validate(range(min = 0, message = "must not be negative"))]
     pub quantity: i32,
+    #[validate(length(max = 200, message = "must be at most 200 characters"))]
     pub location: Option<String>,
+    #[validate(length(max = 200, message = "must be at most 200 characters"))]
     pub token_id: Option<String>,
 }
 
+impl NewInventoryItem {
+    fn fields(&self) -> InventoryFields<'_> {
+        InventoryFields {
+            name: &self.name,
+            description: self.description.as_deref(),
+            quantity: self.quantity,
+            location: self.location.as_deref(),
+            token_id: self.token_id.as_deref(),
+        }
+    }
+}
+
 pub fn inventory_routes() -> Router {
     Router::new()
         .route(
@@ -43,119 +60,191 @@ pub fn inventory_routes() -> Router {
             "/inventory/:id",
             get(get_inventory_item)
                 .put(update_inventory_item)
+                .patch(patch_inventory_item)
                 .delete(delete_inventory_item),
         )
+        .route("/inventory/:id/shares", get(list_shares))
+        .route("/inventory/:id/shares/:user_id", put(share_item).delete(unshare_item))
 }
 
-/// List all inventory items (protected)
+/// Load an item of the tenant's organization the caller holds at least `needed` access to
+pub async fn load_item(pool: &PgPool, tenant: &Tenant, id: Uuid, needed: Access) -> ApiResult<InventoryItem> {
+    let item = queries::get_inventory_item(pool, tenant.org_id, id)
+        .await?
+        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+
+    let access = if item.owner_id == tenant.user_id {
+        Some(Access::Owner)
+    } else {
+        queries::get_inventory_share(pool, id, tenant.user_id)
+            .await?
+            .map(|share| Access::granted_by(&share))
+    };
+    require(access, needed, "Item not found")?;
+    Ok(item)
+}
+
+/// List the caller's items and those shared with them (protected)
 async fn get_inventory(
     AuthenticatedUser(user): AuthenticatedUser, // ✅ now requires valid JWT
     Extension(pool): Extension<PgPool>,
-) -> Json<Vec<InventoryItem>> {
+) -> ApiResult<Json<Vec<InventoryItem>>> {
     println!("🔐 Authenticated user: {}", user.sub);
+    let tenant = active_org(&pool, &user).await?;
 
-    let items = sqlx::query_as::<_, InventoryItem>("SELECT * FROM inventory")
-        .fetch_all(&pool)
-        .await
-        .unwrap_or_default();
+    let items = queries::get_inventory(&pool, tenant.org_id, tenant.user_id).await?;
 
-    Json(items)
+    Ok(Json(items))
 }
 
 async fn get_inventory_item(
-    AuthenticatedUser(_user): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<InventoryItem>, StatusCode> {
-    let item = sqlx::query_as::<_, InventoryItem>("SELECT * FROM inventory WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    match item {
-        Some(i) => Ok(Json(i)),
-        None => Err(StatusCode::NOT_FOUND),
-    }
+    preconditions: Preconditions,
+) -> ApiResult<Response> {
+    let tenant = active_org(&pool, &user).await?;
+    let item = load_item(&pool, &tenant, id, Access::Read).await?;
+    Ok(preconditions.respond(item))
 }
 
 async fn create_inventory_item(
     AuthenticatedUser(user): AuthenticatedUser,
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<NewInventoryItem>,
-) -> Result<Json<InventoryItem>, StatusCode> {
+    audit: Audit,
+    ValidJson(payload): ValidJson<NewInventoryItem>,
+) -> ApiResult<Tagged<InventoryItem>> {
     println!("🛠 Creating item for {}", user.sub);
+    let tenant = active_org(&pool, &user).await?;
 
-    let item = sqlx::query_as!(
-        InventoryItem,
-        r#"
-        INSERT INTO inventory (id, owner_id, name, description, quantity, location, token_id, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        RETURNING id, owner_id, name, description, quantity, location, token_id, created_at
-        "#,
-        Uuid::new_v4(),
-        payload.owner_id,
-        payload.name,
-        payload.description,
-        payload.quantity,
-        payload.location,
-        payload.token_id,
-        Utc::now()
-    )
-    .fetch_one(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Json(item))
+    let item = queries::create_inventory(&pool, tenant.org_id, tenant.user_id, payload.fields())
+        .await?;
+
+    audit.record(&user.sub, AuditEntry::new("inventory.create", "inventory", item.id).in_org(tenant.org_id).created(&item)).await;
+    Ok(Tagged(item))
 }
 
+/// Replace every field; `If-Match` makes it conditional
 async fn update_inventory_item(
-    AuthenticatedUser(_user): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<NewInventoryItem>,
-) -> Result<Json<InventoryItem>, StatusCode> {
-    let item = sqlx::query_as!(
-        InventoryItem,
-        r#"
-        UPDATE inventory
-        SET owner_id = $1, name = $2, description = $3, quantity = $4, location = $5, token_id = $6
-        WHERE id = $7
-        RETURNING id, owner_id, name, description, quantity, location, token_id, created_at
-        "#,
-        payload.owner_id,
-        payload.name,
-        payload.description,
-        payload.quantity,
-        payload.location,
-        payload.token_id,
-        id
-    )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    match item {
-        Some(i) => Ok(Json(i)),
-        None => Err(StatusCode::NOT_FOUND),
-    }
+    audit: Audit,
+    preconditions: Preconditions,
+    ValidJson(payload): ValidJson<NewInventoryItem>,
+) -> ApiResult<Tagged<InventoryItem>> {
+    let tenant = active_org(&pool, &user).await?;
+    let before = load_item(&pool, &tenant, id, Access::Write).await?;
+    preconditions.check(&before)?;
+
+    save_item(&pool, &tenant, &audit, &user.sub, before, payload).await
+}
+
+/// Change only the fields in the merge patch; `null` clears an optional one
+async fn patch_inventory_item(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    preconditions: Preconditions,
+    patch: MergePatch,
+) -> ApiResult<Tagged<InventoryItem>> {
+    let tenant = active_org(&pool, &user).await?;
+    let before = load_item(&pool, &tenant, id, Access::Write).await?;
+    preconditions.check(&before)?;
+
+    let payload: NewInventoryItem = patch.apply(&before)?;
+    save_item(&pool, &tenant, &audit, &user.sub, before, payload).await
+}
+
+/// Write over the version that was read, so a concurrent update makes this one fail
+async fn save_item(
+    pool: &PgPool,
+    tenant: &Tenant,
+    audit: &Audit,
+    actor: &str,
+    before: InventoryItem,
+    payload: NewInventoryItem,
+) -> ApiResult<Tagged<InventoryItem>> {
+    let item = queries::update_inventory(pool, tenant.org_id, before.id, before.version, payload.fields())
+        .await?
+        .ok_or(ApiError::PreconditionFailed("Item was changed by another request".to_string()))?;
+
+    audit.record(actor, AuditEntry::new("inventory.update", "inventory", item.id).in_org(tenant.org_id).updated(&before, &item)).await;
+    Ok(Tagged(item))
 }
 
+/// Owner only, and only right after an MFA check
 async fn delete_inventory_item(
-    AuthenticatedUser(_user): AuthenticatedUser,
+    RecentMfa(user): RecentMfa,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<&'static str>, StatusCode> {
-    let rows_affected = sqlx::query!("DELETE FROM inventory WHERE id = $1", id)
-        .execute(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .rows_affected();
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = active_org(&pool, &user).await?;
+    let before = load_item(&pool, &tenant, id, Access::Owner).await?;
+
+    let rows_affected = queries::delete_inventory(&pool, tenant.org_id, id)
+        .await?;
 
     if rows_affected == 0 {
-        Err(StatusCode::NOT_FOUND)
+        Err(ApiError::NotFound("Item not found".to_string()))
     } else {
+        audit.record(&user.sub, AuditEntry::new("inventory.delete", "inventory", id).in_org(tenant.org_id).deleted(&before)).await;
         Ok(Json("Item deleted"))
     }
 }
 
+/// Owner: who else can see this item
+async fn list_shares(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<Share>>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_item(&pool, &tenant, id, Access::Owner).await?;
+
+    let shares = queries::get_inventory_shares(&pool, id).await?;
+    Ok(Json(shares))
+}
+
+/// Owner: grant (or change) another user's access
+async fn share_item(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<ShareRequest>,
+) -> ApiResult<Json<Share>> {
+    let tenant = active_org(&pool, &user).await?;
+    let item = load_item(&pool, &tenant, id, Access::Owner).await?;
+    check_share_target(&pool, &tenant, item.owner_id, user_id).await?;
+
+    let share = queries::share_inventory(&pool, id, user_id, payload.can_write, tenant.user_id)
+        .await
+        .map_err(share_error)?;
+
+    println!("🤝 Shared item {} with {} (write: {})", id, user_id, payload.can_write);
+    let detail = json!({ "user_id": user_id, "can_write": payload.can_write });
+    audit.record(&user.sub, AuditEntry::new("inventory.share", "inventory", id).in_org(tenant.org_id).detail(detail)).await;
+    Ok(Json(share))
+}
+
+async fn unshare_item(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_item(&pool, &tenant, id, Access::Owner).await?;
+
+    let rows_affected = queries::unshare_inventory(&pool, id, user_id).await?;
+    if rows_affected == 0 {
+        Err(ApiError::NotFound("Share not found".to_string()))
+    } else {
+        let detail = json!({ "user_id": user_id });
+        audit.record(&user.sub, AuditEntry::new("inventory.unshare", "inventory", id).in_org(tenant.org_id).detail(detail)).await;
+        Ok(Json("Share removed"))
+    }
+}
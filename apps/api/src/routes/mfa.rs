@@ -0,0 +1,257 @@
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Router, Json, Extension,
+    http::StatusCode,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::UserTotp;
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth::AuthState;
+use crate::routes::auth_middleware::{AuthenticatedUser, Claims, RecentMfa};
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::service_accounts::API_KEY_PROVIDER;
+use crate::routes::sessions::{issue_tokens, AuthResponse, Session};
+use crate::routes::totp;
+
+/// Shown as the account's issuer in authenticator apps
+const TOTP_ISSUER: &str = "TIDasONE";
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Serialize)]
+pub struct MfaStatus {
+    pub totp_enabled: bool,
+    pub totp_pending: bool,
+    pub recovery_codes_left: i64,
+}
+
+#[derive(Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub recovery_codes: Vec<String>, // shown once
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MfaCode {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+/// TOTP enrollment and step-up. Verifying returns a new token pair whose
+/// `amr` includes "mfa", as needed by [`RecentMfa`] routes.
+pub fn mfa_routes(state: AuthState) -> Router {
+    Router::new()
+        .route("/auth/mfa", get(status_handler))
+        .route("/auth/mfa/totp", post(enroll_handler).delete(disable_handler))
+        .route("/auth/mfa/totp/confirm", post(confirm_handler))
+        .route("/auth/mfa/verify", post(verify_handler))
+        .route("/auth/mfa/recovery-codes", post(regenerate_codes_handler))
+        .with_state(state)
+}
+
+fn invalid_code() -> ApiError {
+    ApiError::Unauthorized("Invalid MFA code".to_string())
+}
+
+/// MFA belongs to people: the token must name a user and not be an API key
+fn mfa_user(claims: &Claims) -> ApiResult<Uuid> {
+    if claims.provider == API_KEY_PROVIDER {
+        return Err(ApiError::Forbidden("Service accounts can't use MFA".to_string()));
+    }
+    claims
+        .user_id()
+        .ok_or(ApiError::Forbidden("Token is not bound to a user".to_string()))
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// Store a fresh set of recovery codes, returning the plaintext
+async fn new_recovery_codes(pool: &PgPool, user_id: Uuid) -> ApiResult<Vec<String>> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let code = totp::base32_encode(&bytes).to_ascii_lowercase();
+            format!("{}-{}", &code[..4], &code[4..])
+        })
+        .collect();
+    let hashes: Vec<String> = codes.iter().map(|code| hash_recovery_code(code)).collect();
+
+    queries::replace_recovery_codes(pool, user_id, &hashes).await?;
+    Ok(codes)
+}
+
+/// Check a TOTP code and burn its time step so it can't be replayed
+async fn accept_totp(pool: &PgPool, enrollment: &UserTotp, code: &str) -> ApiResult<()> {
+    let step = totp::verify(&enrollment.secret, code, chrono::Utc::now().timestamp()).ok_or_else(invalid_code)?;
+    if !queries::use_totp_step(pool, enrollment.user_id, step).await? {
+        return Err(ApiError::Unauthorized("MFA code already used".to_string()));
+    }
+    Ok(())
+}
+
+/// New tokens for the caller's session, marked as MFA-verified now
+async fn step_up(state: &AuthState, pool: &PgPool, audit: &Audit, claims: &Claims, user_id: Uuid) -> ApiResult<AuthResponse> {
+    let user = queries::get_user(pool, user_id)
+        .await?
+        .ok_or(ApiError::Forbidden("No user record for caller".to_string()))?;
+    let session = Session {
+        id: claims.sid.unwrap_or_else(Uuid::new_v4),
+        post_quantum: claims.post_quantum,
+        mfa_at: Some(chrono::Utc::now()),
+        org_id: claims.org,
+    };
+
+    let tokens = issue_tokens(state, pool, &user, &claims.provider, session).await?;
+    audit.record(&claims.sub, session.audit_entry("mfa.step_up", &claims.provider)).await;
+    Ok(tokens)
+}
+
+async fn status_handler(
+    AuthenticatedUser(claims): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<MfaStatus>> {
+    let user_id = mfa_user(&claims)?;
+    let enrollment = queries::get_totp(&pool, user_id).await?;
+    let recovery_codes_left = queries::count_recovery_codes_left(&pool, user_id).await?;
+
+    Ok(Json(MfaStatus {
+        totp_enabled: enrollment.as_ref().is_some_and(|e| e.confirmed_at.is_some()),
+        totp_pending: enrollment.as_ref().is_some_and(|e| e.confirmed_at.is_none()),
+        recovery_codes_left,
+    }))
+}
+
+/// Start enrollment: a new secret for the authenticator app, plus recovery
+/// codes. Nothing is enforced until a first code confirms it.
+async fn enroll_handler(
+    AuthenticatedUser(claims): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<TotpEnrollment>> {
+    let user_id = mfa_user(&claims)?;
+    let account = claims.email.clone().unwrap_or_else(|| user_id.to_string());
+
+    let secret = totp::generate_secret();
+    if !queries::set_pending_totp(&pool, user_id, &secret).await? {
+        return Err(ApiError::Conflict("TOTP is already enabled".to_string()));
+    }
+    let recovery_codes = new_recovery_codes(&pool, user_id).await?;
+    audit.record(&claims.sub, AuditEntry::new("mfa.enroll", "user", user_id)).await;
+
+    Ok(Json(TotpEnrollment {
+        otpauth_uri: totp::otpauth_uri(&secret, &account, TOTP_ISSUER),
+        secret,
+        recovery_codes,
+    }))
+}
+
+/// Finish enrollment with a first code; returns MFA-verified tokens
+async fn confirm_handler(
+    State(state): State<AuthState>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<MfaCode>,
+) -> ApiResult<Json<AuthResponse>> {
+    let user_id = mfa_user(&claims)?;
+    let enrollment = queries::get_totp(&pool, user_id)
+        .await?
+        .filter(|e| e.confirmed_at.is_none())
+        .ok_or(ApiError::Conflict("No pending TOTP enrollment".to_string()))?;
+
+    accept_totp(&pool, &enrollment, payload.code.as_deref().unwrap_or_default()).await?;
+
+    println!("🔐 TOTP enabled for user {}", user_id);
+    audit.record(&claims.sub, AuditEntry::new("mfa.enable", "user", user_id)).await;
+    Ok(Json(step_up(&state, &pool, &audit, &claims, user_id).await?))
+}
+
+/// Step-up: a TOTP code or an unused recovery code
+async fn verify_handler(
+    State(state): State<AuthState>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<MfaCode>,
+) -> ApiResult<Json<AuthResponse>> {
+    let user_id = mfa_user(&claims)?;
+    let enrollment = queries::get_totp(&pool, user_id)
+        .await?
+        .filter(|e| e.confirmed_at.is_some())
+        .ok_or(ApiError::Conflict("MFA is not enabled".to_string()))?;
+
+    let verified = match (payload.code.as_deref(), payload.recovery_code.as_deref()) {
+        (Some(code), _) => accept_totp(&pool, &enrollment, code).await,
+        (None, Some(recovery_code)) => {
+            let used = queries::use_recovery_code(&pool, user_id, &hash_recovery_code(recovery_code))
+                .await?;
+            if used {
+                println!("🔐 User {} used a recovery code", user_id);
+                Ok(())
+            } else {
+                Err(invalid_code())
+            }
+        }
+        (None, None) => return Err(ApiError::BadRequest("code or recovery_code required".to_string())),
+    };
+    if let Err(err) = verified {
+        audit.record(&claims.sub, AuditEntry::new("mfa.verify_failed", "user", user_id)).await;
+        return Err(err);
+    }
+
+    Ok(Json(step_up(&state, &pool, &audit, &claims, user_id).await?))
+}
+
+async fn disable_handler(
+    RecentMfa(claims): RecentMfa,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<StatusCode> {
+    let user_id = mfa_user(&claims)?;
+    if queries::delete_mfa(&pool, user_id).await? == 0 {
+        return Err(ApiError::NotFound("MFA is not enabled".to_string()));
+    }
+
+    println!("🔓 MFA disabled for user {}", user_id);
+    audit.record(&claims.sub, AuditEntry::new("mfa.disable", "user", user_id)).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn regenerate_codes_handler(
+    RecentMfa(claims): RecentMfa,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<RecoveryCodes>> {
+    let user_id = mfa_user(&claims)?;
+    let enabled = queries::get_totp(&pool, user_id)
+        .await?
+        .is_some_and(|e| e.confirmed_at.is_some());
+    if !enabled {
+        return Err(ApiError::Conflict("MFA is not enabled".to_string()));
+    }
+    let recovery_codes = new_recovery_codes(&pool, user_id).await?;
+    audit.record(&claims.sub, AuditEntry::new("mfa.recovery_codes", "user", user_id)).await;
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
@@ -0,0 +1,384 @@
+use axum::{
+    extract::Path,
+    routing::{get, put, delete},
+    Router, Json, Extension,
+};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use pqcrypto_mlkem::mlkem1024::{Ciphertext, PublicKey};
+use pqcrypto_traits::kem::{Ciphertext as CTTrait, PublicKey as PKTrait};
+
+use db::models::{Beacon, Mission, MissionAssignment, TaskUpdate};
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth_middleware::AuthenticatedUser;
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::orgs::{active_org, Tenant};
+use crate::routes::permissions::{MissionsRead, MissionsWrite, RequirePermission};
+use crate::routes::validation::ValidJson;
+
+/// Where a mission can be in its life; new missions start out `Planned`
+pub const MISSION_STATUSES: &[&str] = &["Planned", "Active", "On Hold", "Completed", "Aborted"];
+
+#[derive(Deserialize)]
+pub struct NewMission {
+    pub name: String,
+    pub description: Option<String>,
+    pub kem_public_key: Option<String>,
+    pub checkin_interval_secs: Option<i32>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct MissionStatusUpdate {
+    #[validate(custom(function = "known_status"))]
+    pub status: String,
+}
+
+fn known_status(status: &str) -> Result<(), ValidationError> {
+    if !MISSION_STATUSES.contains(&status) {
+        let message = format!("must be one of: {}", MISSION_STATUSES.join(", "));
+        return Err(ValidationError::new("one_of").with_message(message.into()));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct NewAssignment {
+    pub user_id: Uuid,
+    pub role: Option<String>,
+}
+
+/// Task update sealed by the operator with CommSec:
+/// AES-256-GCM under a key from ML-KEM encapsulation to the mission key
+#[derive(Deserialize)]
+pub struct NewTaskUpdate {
+    pub kem_ciphertext: Option<String>,
+    pub nonce: String,
+    pub ciphertext: String,
+    pub associated_data: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BeaconCheckin {
+    pub location: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BeaconStatus {
+    #[serde(flatten)]
+    pub beacon: Beacon,
+    pub missed: bool,
+}
+
+pub fn mission_routes() -> Router {
+    Router::new()
+        .route("/missions", get(list_missions).post(create_mission))
+        .route("/missions/:id", get(get_mission).delete(delete_mission))
+        .route("/missions/:id/status", put(update_mission_status))
+        .route("/missions/:id/assignments", get(list_assignments).post(assign_user))
+        .route("/missions/:id/assignments/:user_id", delete(unassign_user))
+        .route("/missions/:id/updates", get(list_task_updates).post(post_task_update))
+        .route("/missions/:id/beacons", get(list_beacons).post(beacon_checkin))
+}
+
+async fn load_mission(pool: &PgPool, tenant: &Tenant, id: Uuid) -> ApiResult<Mission> {
+    queries::get_mission(pool, tenant.org_id, id)
+        .await?
+        .ok_or(ApiError::NotFound("Mission not found".to_string()))
+}
+
+/// Only operators assigned to the mission may post or read field traffic
+async fn require_assignment(pool: &PgPool, mission_id: Uuid, tenant: &Tenant) -> ApiResult<Uuid> {
+    let assigned = queries::is_assigned_to_mission(pool, mission_id, tenant.user_id)
+        .await?;
+
+    if assigned {
+        Ok(tenant.user_id)
+    } else {
+        Err(ApiError::Forbidden("Not assigned to this mission".to_string()))
+    }
+}
+
+fn decode_b64(value: &str, field: &str) -> ApiResult<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(value)
+        .map_err(|_| ApiError::BadRequest(format!("invalid {} base64", field)))
+}
+
+async fn list_missions(
+    RequirePermission(user, _): RequirePermission<MissionsRead>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<Mission>>> {
+    let tenant = active_org(&pool, &user).await?;
+    let missions = queries::get_missions(&pool, tenant.org_id).await?;
+    Ok(Json(missions))
+}
+
+async fn get_mission(
+    RequirePermission(user, _): RequirePermission<MissionsRead>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Mission>> {
+    let tenant = active_org(&pool, &user).await?;
+    Ok(Json(load_mission(&pool, &tenant, id).await?))
+}
+
+async fn create_mission(
+    RequirePermission(user, _): RequirePermission<MissionsWrite>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<NewMission>,
+) -> ApiResult<Json<Mission>> {
+    if let Some(pk) = &payload.kem_public_key {
+        let bytes = decode_b64(pk, "kem_public_key")?;
+        PublicKey::from_bytes(&bytes)
+            .map_err(|_| ApiError::BadRequest("invalid kem_public_key".to_string()))?;
+    }
+
+    let interval = payload.checkin_interval_secs.unwrap_or(3600);
+    if interval <= 0 {
+        return Err(ApiError::BadRequest("checkin_interval_secs must be positive".to_string()));
+    }
+
+    println!("🛰 Creating mission '{}' for {}", payload.name, user.sub);
+    let tenant = active_org(&pool, &user).await?;
+
+    let mission = queries::create_mission(
+        &pool,
+        tenant.org_id,
+        &payload.name,
+        payload.description.as_deref(),
+        payload.kem_public_key.as_deref(),
+        interval,
+    )
+    .await?;
+
+    audit.record(&user.sub, AuditEntry::new("mission.create", "mission", mission.id).in_org(tenant.org_id).created(&mission)).await;
+    Ok(Json(mission))
+}
+
+async fn update_mission_status(
+    RequirePermission(user, _): RequirePermission<MissionsWrite>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    ValidJson(payload): ValidJson<MissionStatusUpdate>,
+) -> ApiResult<Json<Mission>> {
+    let tenant = active_org(&pool, &user).await?;
+    let before = load_mission(&pool, &tenant, id).await?;
+    let mission = queries::update_mission_status(&pool, tenant.org_id, id, &payload.status)
+        .await?
+        .ok_or(ApiError::NotFound("Mission not found".to_string()))?;
+
+    audit.record(&user.sub, AuditEntry::new("mission.update", "mission", id).in_org(tenant.org_id).updated(&before, &mission)).await;
+    Ok(Json(mission))
+}
+
+async fn delete_mission(
+    RequirePermission(user, _): RequirePermission<MissionsWrite>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = active_org(&pool, &user).await?;
+    let before = load_mission(&pool, &tenant, id).await?;
+    let rows_affected = queries::delete_mission(&pool, tenant.org_id, id).await?;
+
+    if rows_affected == 0 {
+        Err(ApiError::NotFound("Mission not found".to_string()))
+    } else {
+        audit.record(&user.sub, AuditEntry::new("mission.delete", "mission", id).in_org(tenant.org_id).deleted(&before)).await;
+        Ok(Json("Mission deleted"))
+    }
+}
+
+async fn list_assignments(
+    RequirePermission(user, _): RequirePermission<MissionsRead>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<MissionAssignment>>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_mission(&pool, &tenant, id).await?;
+    let assignments = queries::get_mission_assignments(&pool, id)
+        .await?;
+    Ok(Json(assignments))
+}
+
+async fn assign_user(
+    RequirePermission(user, _): RequirePermission<MissionsWrite>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<NewAssignment>,
+) -> ApiResult<Json<MissionAssignment>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_mission(&pool, &tenant, id).await?;
+    let role = payload.role.unwrap_or_else(|| "operator".to_string());
+
+    // operators come from the mission's own organization
+    if queries::get_membership(&pool, tenant.org_id, payload.user_id).await?.is_none() {
+        return Err(ApiError::NotFound("User not found".to_string()));
+    }
+
+    let assignment = queries::assign_user_to_mission(&pool, id, payload.user_id, &role)
+        .await
+        .map_err(|err| match &err {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                ApiError::NotFound("User not found".to_string())
+            }
+            _ => err.into(),
+        })?;
+
+    audit.record(&user.sub, AuditEntry::new("mission.assign", "mission", id).in_org(tenant.org_id).created(&assignment)).await;
+    Ok(Json(assignment))
+}
+
+async fn unassign_user(
+    RequirePermission(user, _): RequirePermission<MissionsWrite>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_mission(&pool, &tenant, id).await?;
+    let rows_affected = queries::unassign_user_from_mission(&pool, id, user_id)
+        .await?;
+
+    if rows_affected == 0 {
+        Err(ApiError::NotFound("Assignment not found".to_string()))
+    } else {
+        let detail = json!({ "user_id": user_id });
+        audit.record(&user.sub, AuditEntry::new("mission.unassign", "mission", id).in_org(tenant.org_id).detail(detail)).await;
+        Ok(Json("Assignment removed"))
+    }
+}
+
+async fn list_task_updates(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<TaskUpdate>>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_mission(&pool, &tenant, id).await?;
+    require_assignment(&pool, id, &tenant).await?;
+
+    let updates = queries::get_task_updates(&pool, id).await?;
+    Ok(Json(updates))
+}
+
+async fn post_task_update(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<NewTaskUpdate>,
+) -> ApiResult<Json<TaskUpdate>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_mission(&pool, &tenant, id).await?;
+    let author_id = require_assignment(&pool, id, &tenant).await?;
+
+    // 🔐 the server never sees plaintext, but rejects malformed envelopes
+    if decode_b64(&payload.nonce, "nonce")?.len() != 12 {
+        return Err(ApiError::BadRequest("nonce must be 12 bytes".to_string()));
+    }
+    if decode_b64(&payload.ciphertext, "ciphertext")?.len() < 16 {
+        return Err(ApiError::BadRequest("ciphertext too short".to_string()));
+    }
+    if let Some(kem_ct) = &payload.kem_ciphertext {
+        let bytes = decode_b64(kem_ct, "kem_ciphertext")?;
+        Ciphertext::from_bytes(&bytes)
+            .map_err(|_| ApiError::BadRequest("invalid kem_ciphertext".to_string()))?;
+    }
+
+    let update = queries::create_task_update(
+        &pool,
+        id,
+        author_id,
+        payload.kem_ciphertext.as_deref(),
+        &payload.nonce,
+        &payload.ciphertext,
+        payload.associated_data.as_deref(),
+    )
+    .await?;
+
+    // sealed payloads stay out of the trail
+    let detail = json!({ "update_id": update.id });
+    audit.record(&user.sub, AuditEntry::new("mission.task_update", "mission", id).in_org(tenant.org_id).detail(detail)).await;
+    Ok(Json(update))
+}
+
+async fn list_beacons(
+    RequirePermission(user, _): RequirePermission<MissionsRead>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<BeaconStatus>>> {
+    let tenant = active_org(&pool, &user).await?;
+    let mission = load_mission(&pool, &tenant, id).await?;
+    let beacons = queries::get_beacons(&pool, id).await?;
+
+    let interval = chrono::Duration::seconds(mission.checkin_interval_secs.into());
+    let now = chrono::Utc::now();
+
+    let statuses = beacons
+        .into_iter()
+        .map(|beacon| BeaconStatus {
+            missed: beacon.last_seen_at + interval < now,
+            beacon,
+        })
+        .collect();
+
+    Ok(Json(statuses))
+}
+
+async fn beacon_checkin(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Json(payload): Json<BeaconCheckin>,
+) -> ApiResult<Json<Beacon>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_mission(&pool, &tenant, id).await?;
+    let user_id = require_assignment(&pool, id, &tenant).await?;
+
+    let beacon = queries::record_beacon_checkin(&pool, id, user_id, payload.location.as_deref())
+        .await?;
+
+    Ok(Json(beacon))
+}
+
+/// Periodically alert (once per outage) on operators that missed a check-in
+pub fn spawn_beacon_watch(pool: PgPool, every: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+
+            let missed = match queries::get_missed_beacons(&pool).await {
+                Ok(m) => m,
+                Err(err) => {
+                    tracing::error!("beacon watch query failed: {:?}", err);
+                    continue;
+                }
+            };
+
+            for beacon in missed {
+                tracing::warn!(
+                    mission_id = %beacon.mission_id,
+                    user_id = %beacon.user_id,
+                    last_seen_at = %beacon.last_seen_at,
+                    "📡 Missed beacon check-in"
+                );
+                if let Err(err) = queries::mark_beacon_alerted(&pool, beacon.mission_id, beacon.user_id).await {
+                    tracing::error!("failed to mark beacon alerted: {:?}", err);
+                }
+            }
+        }
+    })
+}
@@ -1,9 +1,31 @@
 pub mod user;
 pub mod inventory;
 pub mod packages;
+pub mod ownership;
+pub mod orgs;
 pub mod auth;
 pub mod auth_middleware;
+pub mod health;
+pub mod identities;
+pub mod providers;
+pub mod oidc;
+pub mod sessions;
+pub mod solana;
+pub mod mfa;
+pub mod totp;
+pub mod permissions;
+pub mod roles;
+pub mod service_accounts;
+pub mod token_keys;
 pub mod commsec;
+pub mod commsec_monitor;
+pub mod missions;
+pub mod retention;
+pub mod error;
+pub mod audit;
+pub mod rate_limit;
+pub mod validation;
+pub mod versioning;
 
 pub use user::user_routes;
 pub use inventory::inventory_routes;
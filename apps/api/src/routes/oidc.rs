@@ -0,0 +1,136 @@
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, Jwk, JwkSet},
+    DecodingKey, Validation,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::routes::providers::OidcMetadata;
+
+/// How long a fetched key set is trusted before it is fetched again
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+
+/// An unknown `kid` triggers a refetch (key rotation), at most this often
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+/// Claims we read from a verified ID token
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Provider signing keys, keyed by `jwks_uri`
+#[derive(Clone)]
+pub struct JwksCache {
+    sets: Arc<RwLock<HashMap<String, CachedJwks>>>,
+    ttl: Duration,
+    min_refresh: Duration,
+}
+
+impl Default for JwksCache {
+    fn default() -> Self {
+        Self::new(JWKS_TTL, JWKS_MIN_REFRESH)
+    }
+}
+
+fn find_key(keys: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    match kid {
+        Some(kid) => keys.find(kid).cloned(),
+        // without a kid we can only pick a key if there is exactly one
+        None if keys.keys.len() == 1 => keys.keys.first().cloned(),
+        None => None,
+    }
+}
+
+impl JwksCache {
+    pub fn new(ttl: Duration, min_refresh: Duration) -> Self {
+        Self {
+            sets: Arc::default(),
+            ttl,
+            min_refresh,
+        }
+    }
+
+    /// Signing key `kid` from `jwks_uri`, fetching the set when stale or rotated
+    pub async fn key(&self, jwks_uri: &str, kid: Option<&str>) -> Result<Jwk, String> {
+        let age = {
+            let sets = self.sets.read().unwrap();
+            match sets.get(jwks_uri) {
+                Some(cached) if cached.fetched_at.elapsed() < self.ttl => {
+                    if let Some(key) = find_key(&cached.keys, kid) {
+                        return Ok(key);
+                    }
+                    Some(cached.fetched_at.elapsed())
+                }
+                _ => None,
+            }
+        };
+
+        if age.is_some_and(|age| age < self.min_refresh) {
+            return Err("ID token signed with an unknown key".to_string());
+        }
+
+        let keys: JwkSet = reqwest::get(jwks_uri)
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| format!("JWKS request to {} failed: {}", jwks_uri, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid JWKS at {}: {}", jwks_uri, e))?;
+
+        let key = find_key(&keys, kid);
+        self.sets.write().unwrap().insert(
+            jwks_uri.to_string(),
+            CachedJwks {
+                keys,
+                fetched_at: Instant::now(),
+            },
+        );
+        key.ok_or_else(|| "ID token signed with an unknown key".to_string())
+    }
+}
+
+/// Check an ID token's signature, issuer, audience, expiry and nonce
+pub async fn verify_id_token(
+    cache: &JwksCache,
+    oidc: &OidcMetadata,
+    client_id: &str,
+    id_token: &str,
+    expected_nonce: Option<&str>,
+) -> Result<IdTokenClaims, String> {
+    let header = decode_header(id_token).map_err(|e| format!("Malformed ID token: {}", e))?;
+    let jwk = cache.key(&oidc.jwks_uri, header.kid.as_deref()).await?;
+
+    // a published symmetric key would let anyone mint tokens
+    if matches!(jwk.algorithm, AlgorithmParameters::OctetKey(_)) {
+        return Err("Provider published a symmetric signing key".to_string());
+    }
+    let key = DecodingKey::from_jwk(&jwk).map_err(|e| format!("Unusable signing key: {}", e))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&oidc.issuer]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| format!("ID token rejected: {}", e))?
+        .claims;
+
+    if claims.nonce.as_deref() != expected_nonce {
+        return Err("ID token nonce mismatch".to_string());
+    }
+    Ok(claims)
+}
@@ -0,0 +1,274 @@
+use axum::{
+    extract::{Path, State},
+    routing::{get, post, put},
+    Router, Json, Extension,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::{OrgMembership, Organization, UserOrg};
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth::AuthState;
+use crate::routes::auth_middleware::{AuthenticatedUser, Claims};
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::ownership::caller_id;
+use crate::routes::permissions::{OrgsManage, RequirePermission};
+use crate::routes::service_accounts::API_KEY_PROVIDER;
+use crate::routes::sessions::{issue_tokens, AuthResponse, Session};
+
+/// Organization that pre-tenancy data was moved into; accounts provisioned
+/// on first login join it, like they get [`DEFAULT_ROLE`](crate::routes::roles::DEFAULT_ROLE)
+pub const DEFAULT_ORG_ID: Uuid = Uuid::from_u128(1);
+
+/// A member's standing within one organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Member,
+    Admin, // manages members
+    Owner, // also manages admins and owners
+}
+
+impl OrgRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrgRole::Member => "member",
+            OrgRole::Admin => "admin",
+            OrgRole::Owner => "owner",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "member" => Some(OrgRole::Member),
+            "admin" => Some(OrgRole::Admin),
+            "owner" => Some(OrgRole::Owner),
+            _ => None,
+        }
+    }
+}
+
+/// The organization a request acts in, and the caller's place in it
+#[derive(Debug, Clone, Copy)]
+pub struct Tenant {
+    pub org_id: Uuid,
+    pub user_id: Uuid,
+    pub role: OrgRole,
+}
+
+#[derive(Deserialize)]
+pub struct NewOrganization {
+    pub slug: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct MemberRole {
+    pub role: OrgRole,
+}
+
+/// Organizations and their members. `POST /orgs/:id/switch` re-issues the
+/// caller's tokens for another organization they belong to.
+pub fn org_routes(state: AuthState) -> Router {
+    Router::new()
+        .route("/orgs", get(list_orgs).post(create_org))
+        .route("/orgs/:id/members", get(list_members))
+        .route("/orgs/:id/members/:user_id", put(set_member).delete(remove_member))
+        .route("/orgs/:id/switch", post(switch_org))
+        .with_state(state)
+}
+
+fn tenant_of(membership: OrgMembership) -> Tenant {
+    Tenant {
+        org_id: membership.org_id,
+        user_id: membership.user_id,
+        role: OrgRole::parse(&membership.role).unwrap_or(OrgRole::Member),
+    }
+}
+
+/// The caller as a member of `org_id`; non-members get a 404 so orgs can't be probed
+pub async fn member_of(pool: &PgPool, claims: &Claims, org_id: Uuid) -> ApiResult<Tenant> {
+    let user_id = caller_id(pool, claims).await?;
+    queries::get_membership(pool, org_id, user_id)
+        .await?
+        .map(tenant_of)
+        .ok_or(ApiError::NotFound("Organization not found".to_string()))
+}
+
+/// The organization the caller is working in: the token's `org` claim, or
+/// their oldest membership for tokens without one.
+///
+/// Membership is checked on every request, so removing someone from an
+/// organization locks them out before their token expires.
+pub async fn active_org(pool: &PgPool, claims: &Claims) -> ApiResult<Tenant> {
+    let user_id = caller_id(pool, claims).await?;
+    let membership = match claims.org {
+        Some(org_id) => queries::get_membership(pool, org_id, user_id).await,
+        None => queries::get_default_membership(pool, user_id).await,
+    }?;
+
+    membership
+        .map(tenant_of)
+        .ok_or(ApiError::Forbidden("Not a member of this organization".to_string()))
+}
+
+fn require_org_role(tenant: &Tenant, needed: OrgRole) -> ApiResult<()> {
+    if tenant.role < needed {
+        return Err(ApiError::Forbidden(format!("Requires the {} role in this organization", needed.as_str())));
+    }
+    Ok(())
+}
+
+fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.len() <= 64
+        && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Organizations the caller belongs to
+async fn list_orgs(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<UserOrg>>> {
+    let user_id = caller_id(&pool, &user).await?;
+    let orgs = queries::get_user_orgs(&pool, user_id).await?;
+    Ok(Json(orgs))
+}
+
+/// Platform admins open a new organization and become its first owner
+async fn create_org(
+    RequirePermission(caller, _): RequirePermission<OrgsManage>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<NewOrganization>,
+) -> ApiResult<Json<Organization>> {
+    if !is_valid_slug(&payload.slug) {
+        return Err(ApiError::BadRequest("Organization slugs use a-z, 0-9 and '-'".to_string()));
+    }
+    let owner_id = caller_id(&pool, &caller).await?;
+
+    let org = queries::create_organization(&pool, &payload.slug, &payload.name, owner_id)
+        .await
+        .map_err(|err| match &err {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                ApiError::Conflict("Organization already exists".to_string())
+            }
+            _ => err.into(),
+        })?;
+
+    println!("🏢 {} created organization {}", caller.sub, org.slug);
+    audit.record(&caller.sub, AuditEntry::new("org.create", "organization", org.id).in_org(org.id).created(&org)).await;
+    Ok(Json(org))
+}
+
+async fn list_members(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<OrgMembership>>> {
+    member_of(&pool, &user, id).await?;
+    let members = queries::get_org_members(&pool, id).await?;
+    Ok(Json(members))
+}
+
+/// Whether changing `current` would leave the organization without an owner
+async fn is_last_owner(pool: &PgPool, org_id: Uuid, current: Option<&OrgMembership>) -> ApiResult<bool> {
+    if current.map(|m| m.role.as_str()) != Some(OrgRole::Owner.as_str()) {
+        return Ok(false);
+    }
+    Ok(queries::count_org_owners(pool, org_id).await? <= 1)
+}
+
+/// Admins add members or change their role; only owners touch admins and owners
+async fn set_member(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<MemberRole>,
+) -> ApiResult<Json<OrgMembership>> {
+    let tenant = member_of(&pool, &user, id).await?;
+    require_org_role(&tenant, OrgRole::Admin)?;
+
+    let current = queries::get_membership(&pool, id, user_id).await?;
+    let current_role = current.as_ref().and_then(|m| OrgRole::parse(&m.role));
+    if payload.role.max(current_role.unwrap_or(OrgRole::Member)) > OrgRole::Member {
+        require_org_role(&tenant, OrgRole::Owner)?;
+    }
+    if payload.role != OrgRole::Owner && is_last_owner(&pool, id, current.as_ref()).await? {
+        return Err(ApiError::Conflict("An organization needs at least one owner".to_string()));
+    }
+
+    let membership = queries::set_org_member(&pool, id, user_id, payload.role.as_str())
+        .await
+        .map_err(|err| match &err {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                ApiError::NotFound("User not found".to_string())
+            }
+            _ => err.into(),
+        })?;
+
+    println!("🏢 {} is now {} of organization {}", user_id, payload.role.as_str(), id);
+    let entry = AuditEntry::new("org.set_member", "organization", id).in_org(id);
+    let entry = match &current {
+        Some(before) => entry.updated(before, &membership),
+        None => entry.created(&membership),
+    };
+    audit.record(&user.sub, entry).await;
+    Ok(Json(membership))
+}
+
+/// Admins remove members; anyone may leave
+async fn remove_member(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = member_of(&pool, &user, id).await?;
+    let current = queries::get_membership(&pool, id, user_id)
+        .await?
+        .ok_or(ApiError::NotFound("Member not found".to_string()))?;
+
+    if user_id != tenant.user_id {
+        let needed = if current.role == OrgRole::Member.as_str() { OrgRole::Admin } else { OrgRole::Owner };
+        require_org_role(&tenant, needed)?;
+    }
+    if is_last_owner(&pool, id, Some(&current)).await? {
+        return Err(ApiError::Conflict("An organization needs at least one owner".to_string()));
+    }
+
+    queries::remove_org_member(&pool, id, user_id).await?;
+    audit.record(&user.sub, AuditEntry::new("org.remove_member", "organization", id).in_org(id).deleted(&current)).await;
+    Ok(Json("Member removed"))
+}
+
+/// New tokens for the caller's session, working in organization `id`
+async fn switch_org(
+    State(state): State<AuthState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<AuthResponse>> {
+    if user.provider == API_KEY_PROVIDER {
+        return Err(ApiError::Forbidden("API keys act in their service account's organization".to_string()));
+    }
+    let tenant = member_of(&pool, &user, id).await?;
+    let account = queries::get_user(&pool, tenant.user_id)
+        .await?
+        .ok_or(ApiError::Forbidden("No user record for caller".to_string()))?;
+
+    let session = Session {
+        id: user.sid.unwrap_or_else(Uuid::new_v4),
+        post_quantum: user.post_quantum,
+        mfa_at: user.mfa_time.and_then(|at| chrono::DateTime::from_timestamp(at, 0)),
+        org_id: Some(id),
+    };
+    let tokens = issue_tokens(&state, &pool, &account, &user.provider, session).await?;
+    audit.record(&user.sub, session.audit_entry("org.switch", &user.provider)).await;
+    Ok(Json(tokens))
+}
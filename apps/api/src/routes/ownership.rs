@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::Share;
+use db::queries;
+
+use crate::routes::auth_middleware::Claims;
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::orgs::Tenant;
+
+/// What the caller may do with an inventory item or package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Read,
+    Write,
+    Owner, // write, delete and manage shares
+}
+
+impl Access {
+    pub fn granted_by(share: &Share) -> Self {
+        if share.can_write { Access::Write } else { Access::Read }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ShareRequest {
+    #[serde(default)]
+    pub can_write: bool,
+}
+
+/// Map the caller onto a row in `users` (manual tokens carry an email as `sub`)
+pub async fn caller_id(pool: &PgPool, claims: &Claims) -> ApiResult<Uuid> {
+    if let Some(id) = claims.user_id() {
+        return Ok(id);
+    }
+    queries::get_user_by_email(pool, &claims.sub)
+        .await?
+        .map(|u| u.id)
+        .ok_or(ApiError::Forbidden("No user record for caller".to_string()))
+}
+
+/// Check `access` against what the operation needs.
+///
+/// Callers with no access get the same 404 as for a missing row, so ids of
+/// other users' resources can't be probed; 403 only goes to those who can
+/// already see the resource.
+pub fn require(access: Option<Access>, needed: Access, not_found: &str) -> ApiResult<Access> {
+    match access {
+        None => Err(ApiError::NotFound(not_found.to_string())),
+        Some(access) if access < needed => {
+            Err(ApiError::Forbidden("Insufficient access to this resource".to_string()))
+        }
+        Some(access) => Ok(access),
+    }
+}
+
+/// Validate a share target: another member of the same organization
+pub async fn check_share_target(pool: &PgPool, tenant: &Tenant, owner_id: Uuid, user_id: Uuid) -> ApiResult<()> {
+    if owner_id == user_id {
+        return Err(ApiError::BadRequest("Owners can't share with themselves".to_string()));
+    }
+    if queries::get_membership(pool, tenant.org_id, user_id).await?.is_none() {
+        return Err(ApiError::NotFound("User not found".to_string()));
+    }
+    Ok(())
+}
+
+pub fn share_error(err: sqlx::Error) -> ApiError {
+    match &err {
+        sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+            ApiError::NotFound("User not found".to_string())
+        }
+        _ => err.into(),
+    }
+}
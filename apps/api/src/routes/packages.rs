@@ -1,134 +1,260 @@
 use axum::{
+    response::Response,
     routing::{get, post, put, delete},
     Router, Json, Extension,
     extract::Path,
-    http::StatusCode,
 };
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use serde_json::json;
 use sqlx::PgPool;
 use uuid::Uuid;
-use chrono::Utc;
+use validator::{Validate, ValidationError};
 
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
-pub struct Package {
-    pub id: Uuid,
-    pub owner_id: Uuid,
-    pub inventory_item_id: Option<Uuid>,
-    pub status: String,
-    pub destination: String,
-    pub nft_token: Option<String>,  // ✅ added NFT token support
-    pub created_at: chrono::DateTime<Utc>,
-}
+use db::models::{PackageFields, Share};
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth_middleware::AuthenticatedUser;
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::inventory::load_item;
+use crate::routes::orgs::{active_org, Tenant};
+use crate::routes::ownership::{check_share_target, require, share_error, Access, ShareRequest};
+use crate::routes::validation::{not_blank, ValidJson};
+use crate::routes::versioning::{MergePatch, Preconditions, Tagged};
 
-#[derive(Deserialize)]
+pub use db::models::Package;
+
+/// Where a package can be in its journey; new packages start out `Pending`
+pub const PACKAGE_STATUSES: &[&str] = &["Pending", "Shipped", "In Transit", "Delivered", "Returned", "Cancelled"];
+
+/// Package fields; the owner is always the caller who created it
+#[derive(Deserialize, Validate)]
 pub struct NewPackage {
-    pub owner_id: Uuid,
     pub inventory_item_id: Option<Uuid>,
+    #[validate(custom(function = "known_status"))]
     pub status: Option<String>,
+    #[validate(length(max = 200, message = "must be at most 200 characters"), custom(function = "not_blank"))]
     pub destination: String,
     #[serde(rename = "nft_token")] // ✅ maps from JSON key "nft_token"
+    #[validate(length(max = 200, message = "must be at most 200 characters"))]
     pub nft_token: Option<String>,
 }
 
+fn known_status(status: &str) -> Result<(), ValidationError> {
+    if !PACKAGE_STATUSES.contains(&status) {
+        let message = format!("must be one of: {}", PACKAGE_STATUSES.join(", "));
+        return Err(ValidationError::new("one_of").with_message(message.into()));
+    }
+    Ok(())
+}
+
+impl NewPackage {
+    fn fields(&self) -> PackageFields<'_> {
+        PackageFields {
+            inventory_item_id: self.inventory_item_id,
+            status: self.status.as_deref().unwrap_or("Pending"),
+            destination: &self.destination,
+            nft_token: self.nft_token.as_deref(),
+        }
+    }
+}
+
 pub fn package_routes() -> Router {
     Router::new()
         .route("/packages", get(get_packages).post(create_package))
-        .route("/packages/:id", get(get_package).put(update_package).delete(delete_package))
+        .route("/packages/:id", get(get_package).put(update_package).patch(patch_package).delete(delete_package))
+        .route("/packages/:id/shares", get(list_shares))
+        .route("/packages/:id/shares/:user_id", put(share_package).delete(unshare_package))
 }
 
-async fn get_packages(Extension(pool): Extension<PgPool>) -> Json<Vec<Package>> {
-    let rows = sqlx::query_as::<_, Package>("SELECT * FROM packages")
-        .fetch_all(&pool)
-        .await
-        .unwrap_or_default();
-    Json(rows)
+/// Load a package of the tenant's organization the caller holds at least `needed` access to
+async fn load_package(pool: &PgPool, tenant: &Tenant, id: Uuid, needed: Access) -> ApiResult<Package> {
+    let pkg = queries::get_package(pool, tenant.org_id, id)
+        .await?
+        .ok_or(ApiError::NotFound("Package not found".to_string()))?;
+
+    let access = if pkg.owner_id == tenant.user_id {
+        Some(Access::Owner)
+    } else {
+        queries::get_package_share(pool, id, tenant.user_id)
+            .await?
+            .map(|share| Access::granted_by(&share))
+    };
+    require(access, needed, "Package not found")?;
+    Ok(pkg)
+}
+
+/// Packages may only point at inventory the caller can see
+async fn check_inventory_item(pool: &PgPool, tenant: &Tenant, item_id: Option<Uuid>) -> ApiResult<()> {
+    if let Some(item_id) = item_id {
+        load_item(pool, tenant, item_id, Access::Read).await?;
+    }
+    Ok(())
+}
+
+/// List the caller's packages and those shared with them
+async fn get_packages(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<Package>>> {
+    let tenant = active_org(&pool, &user).await?;
+
+    let rows = queries::get_packages(&pool, tenant.org_id, tenant.user_id).await?;
+    Ok(Json(rows))
 }
 
 async fn get_package(
+    AuthenticatedUser(user): AuthenticatedUser,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<Package>, StatusCode> {
-    let row = sqlx::query_as::<_, Package>("SELECT * FROM packages WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    match row {
-        Some(pkg) => Ok(Json(pkg)),
-        None => Err(StatusCode::NOT_FOUND),
-    }
+    preconditions: Preconditions,
+) -> ApiResult<Response> {
+    let tenant = active_org(&pool, &user).await?;
+    let pkg = load_package(&pool, &tenant, id, Access::Read).await?;
+    Ok(preconditions.respond(pkg))
 }
 
 async fn create_package(
+    AuthenticatedUser(user): AuthenticatedUser,
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<NewPackage>,
-) -> Result<Json<Package>, StatusCode> {
-    let pkg = sqlx::query_as!(
-        Package,
-        r#"
-        INSERT INTO packages (id, owner_id, inventory_item_id, status, destination, nft_token, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
-        RETURNING id, owner_id, inventory_item_id, status, destination, nft_token, created_at
-        "#,
-        Uuid::new_v4(),
-        payload.owner_id,
-        payload.inventory_item_id,
-        payload.status.unwrap_or_else(|| "Pending".to_string()),
-        payload.destination,
-        payload.nft_token,
-        Utc::now(),
-    )
-    .fetch_one(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Json(pkg))
+    audit: Audit,
+    ValidJson(payload): ValidJson<NewPackage>,
+) -> ApiResult<Tagged<Package>> {
+    let tenant = active_org(&pool, &user).await?;
+    check_inventory_item(&pool, &tenant, payload.inventory_item_id).await?;
+
+    let pkg = queries::create_package(&pool, tenant.org_id, tenant.user_id, payload.fields())
+        .await?;
+
+    audit.record(&user.sub, AuditEntry::new("package.create", "package", pkg.id).in_org(tenant.org_id).created(&pkg)).await;
+    Ok(Tagged(pkg))
 }
 
+/// Replace every field; `If-Match` makes it conditional
 async fn update_package(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    preconditions: Preconditions,
+    ValidJson(payload): ValidJson<NewPackage>,
+) -> ApiResult<Tagged<Package>> {
+    let tenant = active_org(&pool, &user).await?;
+    let current = load_package(&pool, &tenant, id, Access::Write).await?;
+    preconditions.check(&current)?;
+
+    save_package(&pool, &tenant, &audit, &user.sub, current, payload).await
+}
+
+/// Change only the fields in the merge patch; `null` clears an optional one
+async fn patch_package(
+    AuthenticatedUser(user): AuthenticatedUser,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<NewPackage>,
-) -> Result<Json<Package>, StatusCode> {
-    let pkg = sqlx::query_as!(
-        Package,
-        r#"
-        UPDATE packages
-        SET owner_id = $1, inventory_item_id = $2, status = $3, destination = $4, nft_token = $5
-        WHERE id = $6
-        RETURNING id, owner_id, inventory_item_id, status, destination, nft_token, created_at
-        "#,
-        payload.owner_id,
-        payload.inventory_item_id,
-        payload.status.unwrap_or_else(|| "Pending".to_string()),
-        payload.destination,
-        payload.nft_token,
-        id,
-    )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    match pkg {
-        Some(pkg) => Ok(Json(pkg)),
-        None => Err(StatusCode::NOT_FOUND),
+    audit: Audit,
+    preconditions: Preconditions,
+    patch: MergePatch,
+) -> ApiResult<Tagged<Package>> {
+    let tenant = active_org(&pool, &user).await?;
+    let current = load_package(&pool, &tenant, id, Access::Write).await?;
+    preconditions.check(&current)?;
+
+    let payload: NewPackage = patch.apply(&current)?;
+    save_package(&pool, &tenant, &audit, &user.sub, current, payload).await
+}
+
+/// Write over the version that was read, so a concurrent update makes this one fail
+async fn save_package(
+    pool: &PgPool,
+    tenant: &Tenant,
+    audit: &Audit,
+    actor: &str,
+    current: Package,
+    payload: NewPackage,
+) -> ApiResult<Tagged<Package>> {
+    if payload.inventory_item_id != current.inventory_item_id {
+        check_inventory_item(pool, tenant, payload.inventory_item_id).await?;
     }
+
+    let pkg = queries::update_package(pool, tenant.org_id, current.id, current.version, payload.fields())
+        .await?
+        .ok_or(ApiError::PreconditionFailed("Package was changed by another request".to_string()))?;
+
+    audit.record(actor, AuditEntry::new("package.update", "package", pkg.id).in_org(tenant.org_id).updated(&current, &pkg)).await;
+    Ok(Tagged(pkg))
 }
 
 async fn delete_package(
+    AuthenticatedUser(user): AuthenticatedUser,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<&'static str>, StatusCode> {
-    let result = sqlx::query!("DELETE FROM packages WHERE id = $1", id)
-        .execute(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = active_org(&pool, &user).await?;
+    let before = load_package(&pool, &tenant, id, Access::Owner).await?;
+
+    let rows_affected = queries::delete_package(&pool, tenant.org_id, id)
+        .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+    if rows_affected == 0 {
+        return Err(ApiError::NotFound("Package not found".to_string()));
     }
 
+    audit.record(&user.sub, AuditEntry::new("package.delete", "package", id).in_org(tenant.org_id).deleted(&before)).await;
+
     Ok(Json("Package deleted"))
 }
 
+/// Owner: who else can see this package
+async fn list_shares(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<Share>>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_package(&pool, &tenant, id, Access::Owner).await?;
+
+    let shares = queries::get_package_shares(&pool, id).await?;
+    Ok(Json(shares))
+}
+
+/// Owner: grant (or change) another user's access
+async fn share_package(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<ShareRequest>,
+) -> ApiResult<Json<Share>> {
+    let tenant = active_org(&pool, &user).await?;
+    let pkg = load_package(&pool, &tenant, id, Access::Owner).await?;
+    check_share_target(&pool, &tenant, pkg.owner_id, user_id).await?;
+
+    let share = queries::share_package(&pool, id, user_id, payload.can_write, tenant.user_id)
+        .await
+        .map_err(share_error)?;
+
+    println!("🤝 Shared package {} with {} (write: {})", id, user_id, payload.can_write);
+    let detail = json!({ "user_id": user_id, "can_write": payload.can_write });
+    audit.record(&user.sub, AuditEntry::new("package.share", "package", id).in_org(tenant.org_id).detail(detail)).await;
+    Ok(Json(share))
+}
+
+async fn unshare_package(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = active_org(&pool, &user).await?;
+    load_package(&pool, &tenant, id, Access::Owner).await?;
+
+    let rows_affected = queries::unshare_package(&pool, id, user_id).await?;
+    if rows_affected == 0 {
+        Err(ApiError::NotFound("Share not found".to_string()))
+    } else {
+        let detail = json!({ "user_id": user_id });
+        audit.record(&user.sub, AuditEntry::new("package.unshare", "package", id).in_org(tenant.org_id).detail(detail)).await;
+        Ok(Json("Share removed"))
+    }
+}
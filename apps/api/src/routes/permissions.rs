@@ -0,0 +1,81 @@
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use std::marker::PhantomData;
+
+use crate::routes::auth_middleware::{AuthenticatedUser, Claims};
+use crate::routes::error::ApiError;
+
+/// A permission a route can require; `NAME` is what roles grant and tokens carry
+pub trait Permission {
+    const NAME: &'static str;
+}
+
+/// Every permission a role may grant
+pub const ALL_PERMISSIONS: &[&str] = &[
+    "users:read",
+    "users:write",
+    "inventory:read",
+    "inventory:write",
+    "packages:read",
+    "packages:write",
+    "missions:read",
+    "missions:write",
+    "sessions:revoke",
+    "roles:manage",
+    "service_accounts:manage",
+    "orgs:manage",
+    "audit:read",
+    "retention:read",
+];
+
+macro_rules! permissions {
+    ($($ty:ident => $name:literal),* $(,)?) => {
+        $(
+            pub struct $ty;
+            impl Permission for $ty {
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+permissions! {
+    UsersRead => "users:read",
+    UsersWrite => "users:write",
+    MissionsRead => "missions:read",
+    MissionsWrite => "missions:write",
+    SessionsRevoke => "sessions:revoke",
+    RolesManage => "roles:manage",
+    ServiceAccountsManage => "service_accounts:manage",
+    OrgsManage => "orgs:manage",
+    AuditRead => "audit:read",
+    RetentionRead => "retention:read",
+}
+
+pub fn is_known_permission(name: &str) -> bool {
+    ALL_PERMISSIONS.contains(&name)
+}
+
+/// Extractor: a valid token whose `perms` claim includes `P`.
+///
+/// Permissions are read from the token, so role changes apply from the
+/// holder's next login or refresh.
+pub struct RequirePermission<P>(pub Claims, pub PhantomData<P>);
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for RequirePermission<P>
+where
+    S: Send + Sync,
+    P: Permission,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthenticatedUser(claims) = AuthenticatedUser::from_request_parts(parts, state).await?;
+        if !claims.has_permission(P::NAME) {
+            return Err(ApiError::Forbidden(format!("Missing permission {}", P::NAME)));
+        }
+        Ok(RequirePermission(claims, PhantomData))
+    }
+}
@@ -0,0 +1,230 @@
+use oauth2::{
+    basic::{BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse, BasicTokenType},
+    AuthUrl, ClientId, ClientSecret, ExtraTokenFields, RedirectUrl, StandardRevocableToken,
+    StandardTokenResponse, TokenUrl,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const DEFAULT_CONFIG_PATH: &str = "config/oauth_providers.toml";
+const DEFAULT_REDIRECT_BASE_URL: &str = "http://127.0.0.1:3000";
+
+/// `config/oauth_providers.toml`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProvidersConfig {
+    /// Public origin of this API; callbacks go to `{base}/auth/callback/{provider}`
+    #[serde(default = "default_redirect_base_url")]
+    pub redirect_base_url: String,
+    #[serde(default)]
+    pub providers: BTreeMap<String, ProviderConfig>,
+}
+
+/// One `[providers.<name>]` table.
+///
+/// Set `issuer` for OpenID Connect providers (endpoints come from discovery),
+/// or `auth_url` / `token_url` / `userinfo_url` for plain OAuth 2.0 ones.
+/// Credentials default to the `<NAME>_CLIENT_ID` / `<NAME>_CLIENT_SECRET` env vars.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProviderConfig {
+    pub enabled: bool,
+    pub required: bool, // refuse to start if this provider can't be set up
+    pub issuer: Option<String>,
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
+    pub userinfo_url: Option<String>, // overrides the discovered endpoint
+    pub scopes: Vec<String>,
+    pub client_id: Option<String>,
+    pub client_id_env: Option<String>,
+    pub client_secret: Option<String>, // only for local/mock providers; use the env var otherwise
+    pub client_secret_env: Option<String>,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            required: false,
+            issuer: None,
+            auth_url: None,
+            token_url: None,
+            userinfo_url: None,
+            scopes: Vec::new(),
+            client_id: None,
+            client_id_env: None,
+            client_secret: None,
+            client_secret_env: None,
+        }
+    }
+}
+
+/// Subset of `.well-known/openid-configuration` we rely on
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+}
+
+/// Token endpoint fields beyond RFC 6749; OIDC providers add `id_token`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdTokenFields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+/// `BasicClient`, but keeping the `id_token` from the token response
+pub type OAuthClient = oauth2::Client<
+    BasicErrorResponse,
+    StandardTokenResponse<IdTokenFields, BasicTokenType>,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
+/// A provider ready to use in the login flow
+#[derive(Clone)]
+pub struct OAuthProvider {
+    pub client: OAuthClient,
+    pub scopes: Vec<String>,
+    pub userinfo_url: Option<String>,
+    pub oidc: Option<OidcMetadata>,
+}
+
+fn default_redirect_base_url() -> String {
+    DEFAULT_REDIRECT_BASE_URL.to_string()
+}
+
+impl ProvidersConfig {
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        toml::from_str(source).map_err(|e| format!("Invalid provider config: {}", e))
+    }
+
+    /// Read `OAUTH_PROVIDERS_CONFIG` (default `config/oauth_providers.toml`).
+    ///
+    /// A missing file means no providers; `OAUTH_REDIRECT_BASE_URL` overrides
+    /// the file's redirect base.
+    pub fn load() -> Result<Self, String> {
+        let path = std::env::var("OAUTH_PROVIDERS_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(source) => Self::from_toml(&source)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("⚠️  {} not found, no OAuth providers configured", path);
+                Self::from_toml("")?
+            }
+            Err(err) => return Err(format!("Failed to read {}: {}", path, err)),
+        };
+
+        if let Ok(base) = std::env::var("OAUTH_REDIRECT_BASE_URL") {
+            config.redirect_base_url = base;
+        }
+        Ok(config)
+    }
+}
+
+/// Fetch an issuer's discovery document
+pub async fn discover(issuer: &str) -> Result<OidcMetadata, String> {
+    let issuer = issuer.trim_end_matches('/');
+    let url = format!("{}/.well-known/openid-configuration", issuer);
+
+    let metadata: OidcMetadata = reqwest::get(&url)
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| format!("Discovery request to {} failed: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid discovery document at {}: {}", url, e))?;
+
+    // the document must describe the issuer we asked for
+    if metadata.issuer.trim_end_matches('/') != issuer {
+        return Err(format!("Discovery issuer mismatch: expected {}, got {}", issuer, metadata.issuer));
+    }
+    Ok(metadata)
+}
+
+fn credential(name: &str, literal: &Option<String>, env_key: &Option<String>, suffix: &str) -> Result<String, String> {
+    if let Some(value) = literal {
+        return Ok(value.clone());
+    }
+    let key = env_key
+        .clone()
+        .unwrap_or_else(|| format!("{}_{}", name.to_uppercase().replace('-', "_"), suffix));
+    std::env::var(&key).map_err(|_| format!("{} is not set", key))
+}
+
+async fn build_provider(name: &str, config: &ProviderConfig, redirect_base_url: &str) -> Result<OAuthProvider, String> {
+    let client_id = credential(name, &config.client_id, &config.client_id_env, "CLIENT_ID")?;
+    let client_secret = credential(name, &config.client_secret, &config.client_secret_env, "CLIENT_SECRET")?;
+
+    let oidc = match &config.issuer {
+        Some(issuer) => Some(discover(issuer).await?),
+        None => None,
+    };
+
+    let auth_url = config
+        .auth_url
+        .clone()
+        .or_else(|| oidc.as_ref().map(|m| m.authorization_endpoint.clone()))
+        .ok_or("needs either `issuer` or `auth_url`")?;
+    let token_url = config
+        .token_url
+        .clone()
+        .or_else(|| oidc.as_ref().map(|m| m.token_endpoint.clone()))
+        .ok_or("needs either `issuer` or `token_url`")?;
+    let userinfo_url = config
+        .userinfo_url
+        .clone()
+        .or_else(|| oidc.as_ref().and_then(|m| m.userinfo_endpoint.clone()));
+
+    let redirect_uri = format!("{}/auth/callback/{}", redirect_base_url.trim_end_matches('/'), name);
+    let client = OAuthClient::new(
+        ClientId::new(client_id),
+        Some(ClientSecret::new(client_secret)),
+        AuthUrl::new(auth_url).map_err(|e| format!("invalid auth_url: {}", e))?,
+        Some(TokenUrl::new(token_url).map_err(|e| format!("invalid token_url: {}", e))?),
+    )
+    .set_redirect_uri(RedirectUrl::new(redirect_uri).map_err(|e| format!("invalid redirect_base_url: {}", e))?);
+
+    let scopes = if config.scopes.is_empty() && oidc.is_some() {
+        vec!["openid".to_string(), "email".to_string(), "profile".to_string()]
+    } else {
+        config.scopes.clone()
+    };
+
+    Ok(OAuthProvider {
+        client,
+        scopes,
+        userinfo_url,
+        oidc,
+    })
+}
+
+/// Set up every enabled provider.
+///
+/// Optional providers that can't be set up (missing credentials, failed
+/// discovery) are skipped with a warning; a failing `required` one is an error.
+pub async fn load_providers(config: &ProvidersConfig) -> Result<HashMap<String, OAuthProvider>, String> {
+    let mut providers = HashMap::new();
+
+    for (name, provider) in config.providers.iter().filter(|(_, p)| p.enabled) {
+        match build_provider(name, provider, &config.redirect_base_url).await {
+            Ok(ready) => {
+                match &ready.oidc {
+                    Some(oidc) => println!("🔑 OIDC provider `{}` enabled ({}, keys at {})", name, oidc.issuer, oidc.jwks_uri),
+                    None => println!("🔑 OAuth provider `{}` enabled", name),
+                }
+                providers.insert(name.clone(), ready);
+            }
+            Err(err) if provider.required => return Err(format!("Provider `{}`: {}", name, err)),
+            Err(err) => eprintln!("⚠️  Provider `{}` disabled: {}", name, err),
+        }
+    }
+
+    Ok(providers)
+}
@@ -0,0 +1,289 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::headers::{authorization::Bearer, Authorization, HeaderMapExt};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::routes::auth_middleware::Claims;
+use crate::routes::error::ApiError;
+use crate::routes::service_accounts::{hash_api_key, is_api_key};
+
+/// Sign-in endpoints: budgeted per IP (per user for MFA), and a run of
+/// failed attempts locks the caller out
+pub const LOGIN_ROUTES: &[&str] = &[
+    "/auth/login/:provider",
+    "/auth/callback/:provider",
+    "/auth/refresh",
+    "/auth/solana/challenge",
+    "/auth/solana/verify",
+    "/auth/mfa/verify",
+    "/auth/mfa/totp/confirm",
+];
+
+/// CommSec's KEM endpoints, which burn CPU per call
+pub const CRYPTO_ROUTES: &[&str] = &[
+    "/commsec/keypair",
+    "/commsec/encapsulate",
+    "/commsec/decapsulate",
+];
+
+/// Token bucket size: `burst` calls at once, refilled at `per_minute`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Budget {
+    pub const fn new(burst: u32, per_minute: u32) -> Self {
+        Self { burst, per_minute }
+    }
+}
+
+/// Budgets for each class of route
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    pub login: Budget,
+    pub login_failures: Budget, // out of tokens means locked out
+    pub crypto: Budget,
+    pub api: Budget,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            login: Budget::new(20, 10),
+            login_failures: Budget::new(5, 4),
+            crypto: Budget::new(30, 120),
+            api: Budget::new(120, 600),
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    /// Defaults overridden by optional `RATE_LIMIT_<CLASS>_BURST` and
+    /// `RATE_LIMIT_<CLASS>_PER_MIN` env vars (classes: LOGIN, LOGIN_FAILURES, CRYPTO, API).
+    /// Zero would lock everyone out for good, so it's ignored like an unparsable value.
+    pub fn from_env() -> Self {
+        fn budget(class: &str, default: Budget) -> Budget {
+            let var = |suffix: &str, default: u32| {
+                let key = format!("RATE_LIMIT_{}_{}", class, suffix);
+                match std::env::var(&key).ok().map(|v| v.parse::<u32>()) {
+                    Some(Ok(value)) if value > 0 => value,
+                    Some(_) => {
+                        tracing::warn!("{} must be a positive number; using {}", key, default);
+                        default
+                    }
+                    None => default,
+                }
+            };
+            Budget {
+                burst: var("BURST", default.burst),
+                per_minute: var("PER_MIN", default.per_minute),
+            }
+        }
+
+        let defaults = Self::default();
+        Self {
+            login: budget("LOGIN", defaults.login),
+            login_failures: budget("LOGIN_FAILURES", defaults.login_failures),
+            crypto: budget("CRYPTO", defaults.crypto),
+            api: budget("API", defaults.api),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Allowed,
+    Limited { retry_after: Duration },
+}
+
+/// Where the buckets live. The in-memory store is per process; run several
+/// API instances against a shared implementation (Redis, Postgres, ...).
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Take `cost` tokens from `key`'s bucket, or say when there will be enough.
+    /// A `cost` of 0 only checks that the bucket isn't empty.
+    async fn take(&self, key: &str, budget: Budget, cost: u32) -> Decision;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    burst: u32,
+}
+
+/// Buckets are dropped once they've refilled, so idle callers cost nothing
+const PRUNE_ABOVE: usize = 10_000;
+
+#[derive(Default)]
+pub struct InMemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryStore {
+    async fn take(&self, key: &str, budget: Budget, cost: u32) -> Decision {
+        let now = Instant::now();
+        let per_sec = budget.per_minute as f64 / 60.0;
+        let refilled = |bucket: &Bucket| {
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * per_sec).min(bucket.burst as f64)
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > PRUNE_ABOVE {
+            buckets.retain(|_, bucket| refilled(bucket) < bucket.burst as f64);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: budget.burst as f64,
+            updated: now,
+            burst: budget.burst,
+        });
+        bucket.tokens = refilled(bucket);
+        bucket.updated = now;
+        bucket.burst = budget.burst;
+
+        let needed = cost.max(1) as f64;
+        if bucket.tokens >= needed {
+            bucket.tokens -= cost as f64;
+            return Decision::Allowed;
+        }
+        let retry_after = if per_sec > 0.0 && needed <= budget.burst as f64 {
+            Duration::from_secs_f64((needed - bucket.tokens) / per_sec)
+        } else {
+            Duration::MAX
+        };
+        Decision::Limited { retry_after }
+    }
+}
+
+/// Policy plus the store holding its buckets
+#[derive(Clone)]
+pub struct RateLimiter {
+    pub policy: RateLimitPolicy,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    pub fn new(policy: RateLimitPolicy, store: impl RateLimitStore + 'static) -> Self {
+        Self { policy, store: Arc::new(store) }
+    }
+
+    pub fn in_memory(policy: RateLimitPolicy) -> Self {
+        Self::new(policy, InMemoryStore::default())
+    }
+}
+
+/// The peer address a request is charged to; callers without one (in-process,
+/// or a server run without connect info) share a single anonymous bucket
+fn peer_key(request: &Request) -> String {
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or_else(|| "ip:unknown".to_string(), |ConnectInfo(addr)| format!("ip:{}", addr.ip()))
+}
+
+/// The verified identity a request is charged to: the API key, else the token's user
+fn identity_key(request: &Request) -> Option<String> {
+    if let Some(Authorization(bearer)) = request.headers().typed_get::<Authorization<Bearer>>() {
+        if is_api_key(bearer.token()) {
+            return Some(format!("key:{}", hash_api_key(bearer.token())));
+        }
+    }
+    request
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| format!("user:{}", claims.sub))
+}
+
+/// Longest `Retry-After` we send; a bucket that can never refill would say forever
+const MAX_RETRY_AFTER_SECS: u64 = 24 * 3600;
+
+fn too_many_requests(retry_after: Duration, message: &str) -> Response {
+    // round up, so a client waiting exactly that long gets through
+    let secs = retry_after
+        .as_secs()
+        .saturating_add(u64::from(retry_after.subsec_nanos() > 0))
+        .clamp(1, MAX_RETRY_AFTER_SECS);
+    let mut response = ApiError::TooManyRequests(message.to_string()).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    response
+}
+
+/// Route layer outside [`require_auth`](crate::routes::auth_middleware::require_auth), i.e. added
+/// after it (`router.route_layer(middleware::from_fn_with_state(limiter, rate_limit_by_ip))`):
+/// charges every request to its peer IP, so requests with a bad or missing token cost too.
+pub async fn rate_limit_by_ip(
+    State(limiter): State<RateLimiter>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let caller = peer_key(&request);
+    charge(&limiter, matched, caller, request, next).await
+}
+
+/// Route layer, inside [`require_auth`](crate::routes::auth_middleware::require_auth) so the
+/// caller's claims are known (`router.route_layer(middleware::from_fn_with_state(limiter, rate_limit_by_caller))`):
+/// charges the API key or user as well, from a bucket separate from the IP's,
+/// so neither many tokens behind one address nor one token from many addresses
+/// gets past its budget. Public routes called without a token were charged by IP only.
+pub async fn rate_limit_by_caller(
+    State(limiter): State<RateLimiter>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(caller) = identity_key(&request) else {
+        return next.run(request).await;
+    };
+    charge(&limiter, matched, caller, request, next).await
+}
+
+/// Charges `caller` for the request from the budget of its route's class;
+/// 429 with `Retry-After` once that's spent. A 401 from a login route also
+/// costs a token from the caller's failure budget, and an empty one locks
+/// them out of every login route until it refills.
+async fn charge(limiter: &RateLimiter, matched: Option<MatchedPath>, caller: String, request: Request, next: Next) -> Response {
+    let route = matched.as_ref().map(MatchedPath::as_str).unwrap_or_default();
+    let policy = &limiter.policy;
+    let store = &limiter.store;
+
+    if LOGIN_ROUTES.contains(&route) {
+        let failures_key = format!("login_failures:{}", caller);
+        if let Decision::Limited { retry_after } = store.take(&failures_key, policy.login_failures, 0).await {
+            return too_many_requests(retry_after, "Too many failed sign-in attempts");
+        }
+        if let Decision::Limited { retry_after } = store.take(&format!("login:{}", caller), policy.login, 1).await {
+            return too_many_requests(retry_after, "Too many sign-in requests");
+        }
+
+        let response = next.run(request).await;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            store.take(&failures_key, policy.login_failures, 1).await;
+        }
+        return response;
+    }
+
+    let (class, budget) = if CRYPTO_ROUTES.contains(&route) {
+        ("crypto", policy.crypto)
+    } else {
+        ("api", policy.api)
+    };
+    if let Decision::Limited { retry_after } = store.take(&format!("{}:{}", class, caller), budget, 1).await {
+        return too_many_requests(retry_after, "Too many requests");
+    }
+    next.run(request).await
+}
@@ -0,0 +1,183 @@
+use axum::{
+    extract::State,
+    routing::get,
+    Router, Json, Extension,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use db::queries;
+
+use crate::routes::commsec::CommsecState;
+use crate::routes::error::ApiError;
+use crate::routes::permissions::{RequirePermission, RetentionRead};
+
+/// How long each class of secret may live before it is erased
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    pub server_kem_key_ttl: Duration,
+    pub ciphertext_ttl: Duration,
+    pub issued_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+    pub sweep_interval: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            server_kem_key_ttl: Duration::from_secs(24 * 3600),
+            ciphertext_ttl: Duration::from_secs(30 * 24 * 3600),
+            issued_token_ttl: Duration::from_secs(3600),
+            refresh_token_ttl: Duration::from_secs(30 * 24 * 3600),
+            sweep_interval: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Defaults overridden by optional `RETENTION_*_SECS` env vars
+    pub fn from_env() -> Self {
+        fn secs(key: &str) -> Option<Duration> {
+            std::env::var(key).ok().and_then(|v| v.parse().ok()).map(Duration::from_secs)
+        }
+
+        let defaults = Self::default();
+        Self {
+            server_kem_key_ttl: secs("RETENTION_KEM_KEY_TTL_SECS").unwrap_or(defaults.server_kem_key_ttl),
+            ciphertext_ttl: secs("RETENTION_CIPHERTEXT_TTL_SECS").unwrap_or(defaults.ciphertext_ttl),
+            issued_token_ttl: secs("RETENTION_TOKEN_TTL_SECS").unwrap_or(defaults.issued_token_ttl),
+            refresh_token_ttl: secs("RETENTION_REFRESH_TOKEN_TTL_SECS").unwrap_or(defaults.refresh_token_ttl),
+            sweep_interval: secs("RETENTION_SWEEP_INTERVAL_SECS").unwrap_or(defaults.sweep_interval),
+        }
+    }
+}
+
+/// Everything the sweeper and report endpoint need
+#[derive(Clone)]
+pub struct RetentionState {
+    pub policy: RetentionPolicy,
+    pub commsec: CommsecState,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SweepReport {
+    pub kem_key_rotated: bool,
+    pub ciphertexts_erased: u64,
+    pub tokens_deleted: u64,
+    pub oauth_states_deleted: u64,
+    pub wallet_challenges_deleted: u64,
+    pub refresh_tokens_deleted: u64,
+}
+
+/// Counts and expiry times only: the report is instance-wide, so it names
+/// no rows (task updates and tokens belong to other tenants and users)
+#[derive(Serialize)]
+pub struct ClassReport {
+    pub class: &'static str,
+    pub ttl_secs: u64,
+    pub tracked: i64,
+    pub next_expiries: Vec<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct RetentionReport {
+    pub generated_at: DateTime<Utc>,
+    pub classes: Vec<ClassReport>,
+}
+
+const REPORT_LIMIT: i64 = 10;
+
+pub fn retention_routes(state: RetentionState) -> Router {
+    Router::new()
+        .route("/retention/report", get(get_report))
+        .with_state(state)
+}
+
+fn cutoff(ttl: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| Utc::now().checked_sub_signed(ttl))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Run one retention pass over every object class
+pub async fn sweep_once(pool: &PgPool, state: &RetentionState) -> sqlx::Result<SweepReport> {
+    let policy = &state.policy;
+
+    Ok(SweepReport {
+        kem_key_rotated: state.commsec.rotate_kem_if_expired(),
+        ciphertexts_erased: queries::erase_task_updates_before(pool, cutoff(policy.ciphertext_ttl)).await?,
+        tokens_deleted: queries::delete_expired_tokens(pool, cutoff(policy.issued_token_ttl)).await?,
+        oauth_states_deleted: queries::delete_expired_oauth_states(pool).await?,
+        wallet_challenges_deleted: queries::delete_expired_wallet_challenges(pool).await?,
+        refresh_tokens_deleted: queries::delete_expired_refresh_tokens(pool).await?,
+    })
+}
+
+pub fn spawn_retention_sweeper(pool: PgPool, state: RetentionState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(state.policy.sweep_interval);
+        loop {
+            ticker.tick().await;
+            match sweep_once(&pool, &state).await {
+                Ok(report) => tracing::info!(
+                    kem_key_rotated = report.kem_key_rotated,
+                    ciphertexts_erased = report.ciphertexts_erased,
+                    tokens_deleted = report.tokens_deleted,
+                    oauth_states_deleted = report.oauth_states_deleted,
+                    wallet_challenges_deleted = report.wallet_challenges_deleted,
+                    refresh_tokens_deleted = report.refresh_tokens_deleted,
+                    "🧹 Retention sweep complete"
+                ),
+                Err(err) => tracing::error!("retention sweep failed: {:?}", err),
+            }
+        }
+    })
+}
+
+/// What each object class holds and what will expire next, for admins
+async fn get_report(
+    RequirePermission(_, _): RequirePermission<RetentionRead>,
+    State(state): State<RetentionState>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<Json<RetentionReport>, ApiError> {
+    let policy = &state.policy;
+
+    let kem_expiry = {
+        let kem = state.commsec.kem.read().unwrap();
+        kem.expires_at(state.commsec.kem_ttl)
+    };
+
+    let ciphertext_ttl = policy.ciphertext_ttl.as_secs() as i64;
+    let token_ttl = policy.issued_token_ttl.as_secs() as i64;
+
+    let classes = vec![
+        ClassReport {
+            class: "server_kem_key",
+            ttl_secs: policy.server_kem_key_ttl.as_secs(),
+            tracked: 1,
+            next_expiries: vec![kem_expiry],
+        },
+        ClassReport {
+            class: "ciphertext",
+            ttl_secs: policy.ciphertext_ttl.as_secs(),
+            tracked: queries::count_task_updates(&pool).await?,
+            next_expiries: queries::next_task_update_expiries(&pool, ciphertext_ttl, REPORT_LIMIT)
+                .await?,
+        },
+        ClassReport {
+            class: "issued_token",
+            ttl_secs: policy.issued_token_ttl.as_secs(),
+            tracked: queries::count_issued_tokens(&pool).await?,
+            next_expiries: queries::next_token_expiries(&pool, token_ttl, REPORT_LIMIT)
+                .await?,
+        },
+    ];
+
+    Ok(Json(RetentionReport {
+        generated_at: Utc::now(),
+        classes,
+    }))
+}
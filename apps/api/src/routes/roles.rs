@@ -0,0 +1,197 @@
+use axum::{
+    extract::Path,
+    routing::{get, put},
+    Router, Json, Extension,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::{Role, UserRole};
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::permissions::{is_known_permission, RequirePermission, RolesManage};
+
+/// Built-in role holding every permission
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Role given to accounts provisioned on first login
+pub const DEFAULT_ROLE: &str = "viewer";
+
+#[derive(Deserialize)]
+pub struct NewRole {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RolePermissions {
+    pub permissions: Vec<String>,
+}
+
+/// Role management. Grants show up in a user's token on their next login or
+/// refresh; revoke their sessions to apply a removal immediately.
+pub fn role_routes() -> Router {
+    Router::new()
+        .route("/auth/admin/roles", get(list_roles).post(create_role))
+        .route("/auth/admin/roles/:name", put(update_role).delete(delete_role))
+        .route("/auth/admin/users/:id/roles", get(list_user_roles))
+        .route("/auth/admin/users/:id/roles/:role", put(assign_role).delete(unassign_role))
+}
+
+fn validate_permissions(permissions: &[String]) -> ApiResult<()> {
+    match permissions.iter().find(|p| !is_known_permission(p)) {
+        Some(unknown) => Err(ApiError::BadRequest(format!("Unknown permission {}", unknown))),
+        None => Ok(()),
+    }
+}
+
+async fn load_role(pool: &PgPool, name: &str) -> ApiResult<Role> {
+    queries::get_role(pool, name)
+        .await?
+        .ok_or(ApiError::NotFound("Role not found".to_string()))
+}
+
+async fn list_roles(
+    RequirePermission(_caller, _): RequirePermission<RolesManage>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<Role>>> {
+    let roles = queries::get_roles(&pool).await?;
+    Ok(Json(roles))
+}
+
+async fn create_role(
+    RequirePermission(caller, _): RequirePermission<RolesManage>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<NewRole>,
+) -> ApiResult<Json<Role>> {
+    let valid_name = !payload.name.is_empty()
+        && payload
+            .name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(ApiError::BadRequest("Role names use a-z, 0-9, '-' and '_'".to_string()));
+    }
+    validate_permissions(&payload.permissions)?;
+
+    queries::create_role(&pool, &payload.name, &payload.description, &payload.permissions)
+        .await
+        .map_err(|err| match &err {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                ApiError::Conflict("Role already exists".to_string())
+            }
+            _ => err.into(),
+        })?;
+
+    println!("🛡 Created role {} ({})", payload.name, payload.permissions.join(", "));
+    let role = load_role(&pool, &payload.name).await?;
+    audit.record(&caller.sub, AuditEntry::new("role.create", "role", &role.name).created(&role)).await;
+    Ok(Json(role))
+}
+
+async fn update_role(
+    RequirePermission(caller, _): RequirePermission<RolesManage>,
+    Path(name): Path<String>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<RolePermissions>,
+) -> ApiResult<Json<Role>> {
+    validate_permissions(&payload.permissions)?;
+    let before = load_role(&pool, &name).await?;
+    if before.builtin {
+        return Err(ApiError::Conflict("Built-in roles can't be changed".to_string()));
+    }
+
+    if !queries::set_role_permissions(&pool, &name, &payload.permissions).await? {
+        return Err(ApiError::NotFound("Role not found".to_string()));
+    }
+    let role = load_role(&pool, &name).await?;
+    audit.record(&caller.sub, AuditEntry::new("role.update", "role", &name).updated(&before, &role)).await;
+    Ok(Json(role))
+}
+
+async fn delete_role(
+    RequirePermission(caller, _): RequirePermission<RolesManage>,
+    Path(name): Path<String>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let before = load_role(&pool, &name).await?;
+    if before.builtin {
+        return Err(ApiError::Conflict("Built-in roles can't be deleted".to_string()));
+    }
+
+    let rows_affected = queries::delete_role(&pool, &name).await?;
+    if rows_affected == 0 {
+        Err(ApiError::NotFound("Role not found".to_string()))
+    } else {
+        audit.record(&caller.sub, AuditEntry::new("role.delete", "role", &name).deleted(&before)).await;
+        Ok(Json("Role deleted"))
+    }
+}
+
+async fn list_user_roles(
+    RequirePermission(_caller, _): RequirePermission<RolesManage>,
+    Path(user_id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<UserRole>>> {
+    let roles = queries::get_user_roles(&pool, user_id).await?;
+    Ok(Json(roles))
+}
+
+async fn assign_role(
+    RequirePermission(caller, _): RequirePermission<RolesManage>,
+    Path((user_id, role)): Path<(Uuid, String)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<Vec<UserRole>>> {
+    load_role(&pool, &role).await?;
+
+    queries::assign_role(&pool, user_id, &role, caller.user_id())
+        .await
+        .map_err(|err| match &err {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                ApiError::NotFound("User not found".to_string())
+            }
+            _ => err.into(),
+        })?;
+
+    println!("🛡 {} granted {} to user {}", caller.sub, role, user_id);
+    audit.record(&caller.sub, AuditEntry::new("role.grant", "user", user_id).detail(json!({ "role": role }))).await;
+    let roles = queries::get_user_roles(&pool, user_id).await?;
+    Ok(Json(roles))
+}
+
+async fn unassign_role(
+    RequirePermission(caller, _): RequirePermission<RolesManage>,
+    Path((user_id, role)): Path<(Uuid, String)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<Vec<UserRole>>> {
+    let holds_role = queries::get_user_roles(&pool, user_id)
+        .await?
+        .iter()
+        .any(|grant| grant.role == role);
+    if !holds_role {
+        return Err(ApiError::NotFound("Role assignment not found".to_string()));
+    }
+
+    // never lock everyone out of role management
+    if role == ADMIN_ROLE && queries::count_role_members(&pool, ADMIN_ROLE).await? <= 1 {
+        return Err(ApiError::Conflict("Can't remove the last admin".to_string()));
+    }
+
+    queries::unassign_role(&pool, user_id, &role).await?;
+
+    println!("🛡 {} removed {} from user {}", caller.sub, role, user_id);
+    audit.record(&caller.sub, AuditEntry::new("role.revoke", "user", user_id).detail(json!({ "role": role }))).await;
+    let roles = queries::get_user_roles(&pool, user_id).await?;
+    Ok(Json(roles))
+}
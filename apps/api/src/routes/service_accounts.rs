@@ -0,0 +1,265 @@
+use axum::{
+    extract::Path,
+    routing::{delete, get},
+    Router, Json, Extension,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::{ApiKey, ServiceAccount};
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth_middleware::Claims;
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::orgs::active_org;
+use crate::routes::permissions::{is_known_permission, RequirePermission, ServiceAccountsManage};
+
+/// Every API key starts with this, so the extractor can tell keys from JWTs
+pub const API_KEY_PREFIX: &str = "tdk_";
+
+/// `provider` claim of callers authenticated by API key
+pub const API_KEY_PROVIDER: &str = "api_key";
+
+/// A freshly minted key; `key` is shown once and never stored
+pub struct GeneratedKey {
+    pub key: String,
+    pub prefix: String,
+    pub hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewServiceAccount {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewApiKey {
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>, // None: no expiry
+}
+
+#[derive(Serialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// Management endpoints; the `service_account` CLI covers the same ground offline
+pub fn service_account_routes() -> Router {
+    Router::new()
+        .route("/auth/admin/service-accounts", get(list_accounts).post(create_account))
+        .route("/auth/admin/service-accounts/:id", get(get_account).delete(disable_account))
+        .route("/auth/admin/service-accounts/:id/keys", get(list_keys).post(issue_key))
+        .route("/auth/admin/service-accounts/:id/keys/:key_id", delete(revoke_key))
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// `tdk_<8 hex>_<32 random bytes, base64url>`; the part before the secret is the prefix
+pub fn generate_api_key() -> GeneratedKey {
+    let mut id = [0u8; 4];
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut id);
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    let prefix = format!("{}{}", API_KEY_PREFIX, id.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    let key = format!("{}_{}", prefix, URL_SAFE_NO_PAD.encode(secret));
+    GeneratedKey { hash: hash_api_key(&key), key, prefix }
+}
+
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+/// Names are also usernames: a-z, 0-9, '-' and '_'
+pub fn is_valid_account_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+pub fn validate_scopes(scopes: &[String]) -> Result<(), String> {
+    if scopes.is_empty() {
+        return Err("An API key needs at least one scope".to_string());
+    }
+    match scopes.iter().find(|scope| !is_known_permission(scope)) {
+        Some(unknown) => Err(format!("Unknown permission {}", unknown)),
+        None => Ok(()),
+    }
+}
+
+/// Claims for a request carrying an API key: the account is the subject,
+/// the key's scopes are its permissions
+pub async fn authenticate_api_key(pool: &PgPool, key: &str) -> ApiResult<Claims> {
+    let invalid = || ApiError::Unauthorized("Invalid API key".to_string());
+
+    let api_key = queries::get_active_api_key_by_hash(pool, &hash_api_key(key))
+        .await?
+        .ok_or_else(invalid)?;
+
+    if api_key.revoked_at.is_some() {
+        return Err(ApiError::Unauthorized("API key has been revoked".to_string()));
+    }
+    if api_key.expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now()) {
+        return Err(ApiError::Unauthorized("API key has expired".to_string()));
+    }
+
+    queries::touch_api_key(pool, api_key.id).await?;
+
+    Ok(Claims {
+        sub: api_key.service_account_id.to_string(),
+        exp: api_key.expires_at.map_or(usize::MAX, |expires_at| expires_at.timestamp() as usize),
+        provider: API_KEY_PROVIDER.to_string(),
+        jti: None,
+        sid: None,
+        email: None,
+        roles: Vec::new(),
+        perms: api_key.scopes,
+        amr: vec![API_KEY_PROVIDER.to_string()],
+        mfa_time: None,
+        org: None, // the account's membership decides
+        org_role: None,
+        post_quantum: false,
+    })
+}
+
+/// Accounts of other organizations are as missing as ones never created
+async fn load_account(pool: &PgPool, caller: &Claims, id: Uuid) -> ApiResult<ServiceAccount> {
+    let tenant = active_org(pool, caller).await?;
+    queries::get_service_account(pool, tenant.org_id, id)
+        .await?
+        .ok_or(ApiError::NotFound("Service account not found".to_string()))
+}
+
+async fn list_accounts(
+    RequirePermission(caller, _): RequirePermission<ServiceAccountsManage>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<ServiceAccount>>> {
+    let tenant = active_org(&pool, &caller).await?;
+    let accounts = queries::get_service_accounts(&pool, tenant.org_id).await?;
+    Ok(Json(accounts))
+}
+
+async fn create_account(
+    RequirePermission(caller, _): RequirePermission<ServiceAccountsManage>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<NewServiceAccount>,
+) -> ApiResult<Json<ServiceAccount>> {
+    if !is_valid_account_name(&payload.name) {
+        return Err(ApiError::BadRequest("Service account names use a-z, 0-9, '-' and '_'".to_string()));
+    }
+
+    // the account works in the organization it was created from
+    let tenant = active_org(&pool, &caller).await?;
+    let account = queries::create_service_account(&pool, tenant.org_id, &payload.name, &payload.description, caller.user_id())
+        .await
+        .map_err(|err| match &err {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                ApiError::Conflict("Service account already exists".to_string())
+            }
+            _ => err.into(),
+        })?;
+
+    println!("🤖 {} created service account {}", caller.sub, account.name);
+    audit.record(&caller.sub, AuditEntry::new("service_account.create", "service_account", account.id).in_org(tenant.org_id).created(&account)).await;
+    Ok(Json(account))
+}
+
+async fn get_account(
+    RequirePermission(caller, _): RequirePermission<ServiceAccountsManage>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<ServiceAccount>> {
+    Ok(Json(load_account(&pool, &caller, id).await?))
+}
+
+/// Disable the account and revoke all of its keys
+async fn disable_account(
+    RequirePermission(caller, _): RequirePermission<ServiceAccountsManage>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<ServiceAccount>> {
+    let before = load_account(&pool, &caller, id).await?;
+    let disabled = queries::disable_service_account(&pool, id).await?;
+    let account = load_account(&pool, &caller, id).await?;
+    if disabled {
+        println!("🤖 {} disabled service account {}", caller.sub, id);
+        audit.record(&caller.sub, AuditEntry::new("service_account.disable", "service_account", id).updated(&before, &account)).await;
+    }
+    Ok(Json(account))
+}
+
+async fn list_keys(
+    RequirePermission(caller, _): RequirePermission<ServiceAccountsManage>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<ApiKey>>> {
+    load_account(&pool, &caller, id).await?;
+    let keys = queries::get_api_keys(&pool, id).await?;
+    Ok(Json(keys))
+}
+
+/// Mint a key. The plaintext is in this response only.
+async fn issue_key(
+    RequirePermission(caller, _): RequirePermission<ServiceAccountsManage>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<NewApiKey>,
+) -> ApiResult<Json<CreatedApiKey>> {
+    validate_scopes(&payload.scopes).map_err(ApiError::BadRequest)?;
+    // no handing out more than the caller holds
+    if let Some(scope) = payload.scopes.iter().find(|scope| !caller.has_permission(scope)) {
+        return Err(ApiError::Forbidden(format!("Can't grant {} without holding it", scope)));
+    }
+    let expires_at = match payload.expires_in_days {
+        Some(days) if days <= 0 => {
+            return Err(ApiError::BadRequest("expires_in_days must be positive".to_string()));
+        }
+        Some(days) => Some(chrono::Utc::now() + chrono::Duration::days(days)),
+        None => None,
+    };
+
+    let account = load_account(&pool, &caller, id).await?;
+    if account.disabled_at.is_some() {
+        return Err(ApiError::Conflict("Service account is disabled".to_string()));
+    }
+
+    let generated = generate_api_key();
+    let api_key = queries::create_api_key(&pool, id, &generated.prefix, &generated.hash, &payload.scopes, expires_at)
+        .await?;
+
+    println!("🔑 {} issued API key {} for {} ({})", caller.sub, api_key.prefix, account.name, payload.scopes.join(", "));
+    audit.record(&caller.sub, AuditEntry::new("api_key.create", "service_account", id).created(&api_key)).await;
+    Ok(Json(CreatedApiKey { key: generated.key, api_key }))
+}
+
+async fn revoke_key(
+    RequirePermission(caller, _): RequirePermission<ServiceAccountsManage>,
+    Path((id, key_id)): Path<(Uuid, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    load_account(&pool, &caller, id).await?;
+    let rows_affected = queries::revoke_api_key(&pool, id, key_id).await?;
+    if rows_affected == 0 {
+        return Err(ApiError::NotFound("Active API key not found".to_string()));
+    }
+
+    println!("🔒 {} revoked API key {} of service account {}", caller.sub, key_id, id);
+    audit.record(&caller.sub, AuditEntry::new("api_key.revoke", "service_account", id).detail(json!({ "key_id": key_id }))).await;
+    Ok(Json("API key revoked"))
+}
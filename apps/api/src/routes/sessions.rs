@@ -0,0 +1,301 @@
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Router, Json, Extension,
+    http::StatusCode,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use db::models::{NewRefreshToken, User};
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth::AuthState;
+use crate::routes::auth_middleware::{AuthenticatedUser, Claims};
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::permissions::{RequirePermission, SessionsRevoke};
+use crate::routes::roles::ADMIN_ROLE;
+use crate::routes::solana::SOLANA_PROVIDER;
+
+/// How a provider's users prove who they are, the first `amr` entry
+fn login_method(provider: &str) -> &'static str {
+    match provider {
+        SOLANA_PROVIDER => "wallet", // signed a challenge with their key
+        _ => "oauth",
+    }
+}
+
+/// What a login session carries from one token pair to the next
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    pub id: Uuid,
+    pub post_quantum: bool, // ML-DSA signed access tokens, now and on refresh
+    pub mfa_at: Option<DateTime<Utc>>, // the session's last MFA verification
+    pub org_id: Option<Uuid>, // active organization; None starts in the default one
+}
+
+impl Session {
+    /// A fresh login
+    pub fn new(post_quantum: bool) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            post_quantum,
+            mfa_at: None,
+            org_id: None,
+        }
+    }
+
+    /// Audit entry for tokens minted for this session
+    pub fn audit_entry(&self, action: &'static str, provider: &str) -> AuditEntry {
+        let entry = AuditEntry::new(action, "session", self.id).detail(json!({
+            "provider": provider,
+            "post_quantum": self.post_quantum,
+            "mfa": self.mfa_at.is_some(),
+        }));
+        match self.org_id {
+            Some(org_id) => entry.in_org(org_id),
+            None => entry,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuthResponse {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct RevokeReport {
+    pub refresh_tokens_revoked: u64,
+    pub access_tokens_revoked: u64,
+}
+
+/// What the caller's access token grants, as `GET /auth/me` reports it
+#[derive(Serialize)]
+pub struct TokenInfo {
+    pub sub: String,
+    pub provider: String,
+    pub email: Option<String>,
+    pub roles: Vec<String>,
+    pub perms: Vec<String>,
+    pub amr: Vec<String>,
+    pub org: Option<Uuid>,
+    pub org_role: Option<String>,
+    pub session_id: Option<Uuid>,
+    pub post_quantum: bool,
+    pub expires_at: Option<DateTime<Utc>>, // None for API keys that don't expire
+}
+
+pub fn session_routes(state: AuthState) -> Router {
+    Router::new()
+        .route("/auth/me", get(me_handler))
+        .route("/auth/refresh", post(refresh_handler))
+        .route("/auth/logout", post(logout_handler))
+        .route("/auth/admin/users/:id/revoke-sessions", post(revoke_user_handler))
+        .with_state(state)
+}
+
+fn hash_refresh_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Mint an access token and a fresh refresh token for `session`.
+///
+/// The session's organization is used while the user is still a member of
+/// it; otherwise the token falls back to their default membership.
+pub async fn issue_tokens(
+    state: &AuthState,
+    pool: &PgPool,
+    user: &User,
+    provider: &str,
+    session: Session,
+) -> ApiResult<AuthResponse> {
+    // bootstrap: ADMIN_EMAILS accounts are made admins on login
+    if state.admin_emails.iter().any(|admin| admin.eq_ignore_ascii_case(&user.email))
+        && queries::assign_role(pool, user.id, ADMIN_ROLE, None).await?
+    {
+        println!("👑 Granted {} to {} (ADMIN_EMAILS)", ADMIN_ROLE, user.email);
+    }
+    let roles = queries::get_user_roles(pool, user.id)
+        .await?
+        .into_iter()
+        .map(|grant| grant.role)
+        .collect();
+    let perms = queries::get_user_permissions(pool, user.id).await?;
+
+    let membership = match session.org_id {
+        Some(org_id) => queries::get_membership(pool, org_id, user.id).await?,
+        None => None,
+    };
+    let membership = match membership {
+        Some(membership) => Some(membership),
+        None => queries::get_default_membership(pool, user.id).await?,
+    };
+
+    let subject = user.id.to_string();
+    let now = chrono::Utc::now();
+    let expires_at = now + chrono::Duration::seconds(state.token_ttl_secs);
+    let jti = Uuid::new_v4();
+
+    // track the token so retention can expire it and logout can revoke it
+    queries::record_issued_token(pool, jti, &subject, provider, Some(session.id), expires_at)
+        .await?;
+
+    let mut amr = vec![login_method(provider).to_string()];
+    if session.mfa_at.is_some() {
+        amr.extend(["otp", "mfa"].map(String::from));
+    }
+
+    let claims = Claims {
+        sub: subject,
+        exp: expires_at.timestamp() as usize,
+        provider: provider.to_string(),
+        jti: Some(jti.to_string()),
+        sid: Some(session.id),
+        email: Some(user.email.clone()),
+        roles,
+        perms,
+        amr,
+        mfa_time: session.mfa_at.map(|at| at.timestamp()),
+        org: membership.as_ref().map(|m| m.org_id),
+        org_role: membership.as_ref().map(|m| m.role.clone()),
+        post_quantum: session.post_quantum,
+    };
+    let token = if session.post_quantum {
+        state.keys.sign_post_quantum(&claims).ok()
+    } else {
+        state.keys.sign(&claims).ok()
+    }
+    .ok_or(ApiError::Internal("Failed to encode JWT".to_string()))?;
+
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let refresh_token = URL_SAFE_NO_PAD.encode(secret);
+
+    queries::create_refresh_token(pool, NewRefreshToken {
+        session_id: session.id,
+        user_id: user.id,
+        provider,
+        token_hash: &hash_refresh_token(&refresh_token),
+        post_quantum: session.post_quantum,
+        mfa_at: session.mfa_at,
+        org_id: membership.map(|m| m.org_id),
+        expires_at: now + chrono::Duration::seconds(state.refresh_ttl_secs),
+    })
+    .await?;
+
+    Ok(AuthResponse { token, refresh_token })
+}
+
+/// Trade a refresh token for a new access + refresh token pair.
+///
+/// Each refresh token works once. Presenting one that was already rotated
+/// means it was copied, so the whole session is revoked.
+async fn refresh_handler(
+    State(state): State<AuthState>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(payload): Json<RefreshRequest>,
+) -> ApiResult<Json<AuthResponse>> {
+    let invalid = || ApiError::Unauthorized("Invalid refresh token".to_string());
+
+    let stored = queries::get_refresh_token_by_hash(&pool, &hash_refresh_token(&payload.refresh_token))
+        .await?
+        .ok_or_else(invalid)?;
+
+    if stored.revoked_at.is_some() || stored.expires_at <= chrono::Utc::now() {
+        return Err(invalid());
+    }
+
+    if !queries::mark_refresh_token_used(&pool, stored.id).await? {
+        queries::revoke_session(&pool, stored.session_id).await?;
+        tracing::warn!(
+            session_id = %stored.session_id,
+            user_id = %stored.user_id,
+            "🚨 Refresh token reuse detected, session revoked"
+        );
+        let entry = AuditEntry::new("session.reuse_detected", "session", stored.session_id);
+        audit.record(&stored.user_id.to_string(), entry).await;
+        return Err(ApiError::Unauthorized("Refresh token reuse detected; session revoked".to_string()));
+    }
+
+    let user = queries::get_user(&pool, stored.user_id)
+        .await?
+        .ok_or_else(invalid)?;
+
+    let session = Session {
+        id: stored.session_id,
+        post_quantum: stored.post_quantum,
+        mfa_at: stored.mfa_at,
+        org_id: stored.org_id,
+    };
+    let tokens = issue_tokens(&state, &pool, &user, &stored.provider, session).await?;
+    audit.record(&user.id.to_string(), session.audit_entry("token.refresh", &stored.provider)).await;
+    Ok(Json(tokens))
+}
+
+/// The caller's token as the server reads it, so clients can show roles and
+/// the active organization without decoding the JWT themselves
+async fn me_handler(AuthenticatedUser(user): AuthenticatedUser) -> Json<TokenInfo> {
+    Json(TokenInfo {
+        expires_at: i64::try_from(user.exp).ok().and_then(|exp| DateTime::from_timestamp(exp, 0)),
+        sub: user.sub,
+        provider: user.provider,
+        email: user.email,
+        roles: user.roles,
+        perms: user.perms,
+        amr: user.amr,
+        org: user.org,
+        org_role: user.org_role,
+        session_id: user.sid,
+        post_quantum: user.post_quantum,
+    })
+}
+
+/// End the caller's session: this access token and every token of its session
+async fn logout_handler(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<StatusCode> {
+    if let Some(jti) = user.jti.as_deref().and_then(|jti| Uuid::parse_str(jti).ok()) {
+        queries::revoke_issued_token(&pool, jti).await?;
+    }
+    if let Some(session_id) = user.sid {
+        queries::revoke_session(&pool, session_id).await?;
+        audit.record(&user.sub, AuditEntry::new("session.logout", "session", session_id)).await;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Admin: sign a user out everywhere
+async fn revoke_user_handler(
+    RequirePermission(caller, _): RequirePermission<SessionsRevoke>,
+    Path(user_id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<RevokeReport>> {
+    let (refresh_tokens_revoked, access_tokens_revoked) =
+        queries::revoke_user_sessions(&pool, user_id).await?;
+
+    println!("🔒 Revoked all sessions of user {} ({} refresh, {} access)", user_id, refresh_tokens_revoked, access_tokens_revoked);
+    let detail = json!({ "refresh_tokens_revoked": refresh_tokens_revoked, "access_tokens_revoked": access_tokens_revoked });
+    audit.record(&caller.sub, AuditEntry::new("session.revoke_all", "user", user_id).detail(detail)).await;
+    Ok(Json(RevokeReport {
+        refresh_tokens_revoked,
+        access_tokens_revoked,
+    }))
+}
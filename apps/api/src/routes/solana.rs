@@ -0,0 +1,217 @@
+use axum::{
+    extract::State,
+    routing::post,
+    Router, Json, Extension,
+};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use db::models::NewWalletChallenge;
+use db::queries;
+
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::auth::AuthState;
+use crate::routes::auth_middleware::AuthenticatedUser;
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::identities::{resolve_login_user, ProviderProfile};
+use crate::routes::service_accounts::API_KEY_PROVIDER;
+use crate::routes::sessions::{issue_tokens, AuthResponse, Session};
+
+/// Provider name for wallet identities; the subject is the base58 address
+pub const SOLANA_PROVIDER: &str = "solana";
+
+/// How long a wallet has to sign a challenge
+const CHALLENGE_TTL_SECS: i64 = 300;
+
+/// Domain named in the signed message unless `SIWS_DOMAIN` is set
+const DEFAULT_DOMAIN: &str = "localhost:3000";
+
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Deserialize)]
+pub struct ChallengeRequest {
+    pub address: String,
+}
+
+#[derive(Serialize)]
+pub struct Challenge {
+    pub nonce: String,
+    pub message: String, // sign these exact bytes
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyRequest {
+    pub nonce: String,
+    pub signature: String, // base58, as wallets return it
+}
+
+/// Sign-In-With-Solana: the wallet signs a server-issued challenge with its
+/// ed25519 key and gets our usual token pair back. Signing a challenge from
+/// `/auth/solana/link` adds the wallet to the caller's account instead.
+pub fn solana_routes(state: AuthState) -> Router {
+    Router::new()
+        .route("/auth/solana/challenge", post(challenge_handler))
+        .route("/auth/solana/link", post(link_handler))
+        .route("/auth/solana/verify", post(verify_handler))
+        .with_state(state)
+}
+
+/// Bitcoin-alphabet base58, as used for Solana addresses and signatures
+pub fn base58_encode(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = Vec::new(); // little-endian base58 digits
+    for &byte in bytes {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    let mut out = "1".repeat(zeros);
+    out.extend(digits.iter().rev().map(|&digit| BASE58[digit as usize] as char));
+    out
+}
+
+pub fn base58_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::new(); // little-endian bytes
+    for c in text.bytes() {
+        let mut carry = BASE58.iter().position(|&b| b == c)? as u32;
+        for byte in out.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            out.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    out.resize(out.len() + zeros, 0);
+    out.reverse();
+    Some(out)
+}
+
+/// A wallet address is a base58 ed25519 public key, in its canonical spelling
+fn parse_address(address: &str) -> ApiResult<Vec<u8>> {
+    base58_decode(address)
+        .filter(|key| key.len() == 32 && base58_encode(key) == address)
+        .ok_or(ApiError::BadRequest("Invalid Solana address".to_string()))
+}
+
+/// Whether `signature` (base58) is `address`'s ed25519 signature over `message`
+pub fn verify_signature(address: &str, message: &[u8], signature: &str) -> bool {
+    let (Some(key), Some(signature)) = (base58_decode(address), base58_decode(signature)) else {
+        return false;
+    };
+    key.len() == 32
+        && signature.len() == 64
+        && UnparsedPublicKey::new(&ED25519, key).verify(message, &signature).is_ok()
+}
+
+/// The human-readable text the wallet shows and signs (SIWS message format)
+pub fn challenge_message(domain: &str, address: &str, nonce: &str, issued_at: DateTime<Utc>, expires_at: DateTime<Utc>) -> String {
+    format!(
+        "{domain} wants you to sign in with your Solana account:\n\
+         {address}\n\
+         \n\
+         Sign in to TIDasONE\n\
+         \n\
+         Version: 1\n\
+         Nonce: {nonce}\n\
+         Issued At: {}\n\
+         Expiration Time: {}",
+        issued_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        expires_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    )
+}
+
+async fn new_challenge(pool: &PgPool, address: &str, link_user_id: Option<Uuid>) -> ApiResult<Challenge> {
+    parse_address(address)?;
+
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let nonce = base58_encode(&bytes);
+
+    let domain = std::env::var("SIWS_DOMAIN").unwrap_or_else(|_| DEFAULT_DOMAIN.to_string());
+    let issued_at = Utc::now();
+    let expires_at = issued_at + chrono::Duration::seconds(CHALLENGE_TTL_SECS);
+    let message = challenge_message(&domain, address, &nonce, issued_at, expires_at);
+
+    queries::create_wallet_challenge(pool, NewWalletChallenge {
+        nonce: &nonce,
+        address,
+        message: &message,
+        link_user_id,
+        expires_at,
+    })
+    .await?;
+
+    Ok(Challenge { nonce, message, expires_at })
+}
+
+async fn challenge_handler(
+    Extension(pool): Extension<PgPool>,
+    Json(request): Json<ChallengeRequest>,
+) -> ApiResult<Json<Challenge>> {
+    Ok(Json(new_challenge(&pool, &request.address, None).await?))
+}
+
+/// Like `/auth/link/:provider`: a challenge whose signature links the wallet to the caller
+async fn link_handler(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Extension(pool): Extension<PgPool>,
+    Json(request): Json<ChallengeRequest>,
+) -> ApiResult<Json<Challenge>> {
+    let user_id = user
+        .user_id()
+        .filter(|_| user.provider != API_KEY_PROVIDER)
+        .ok_or(ApiError::Forbidden("Only user accounts can link a wallet".to_string()))?;
+    Ok(Json(new_challenge(&pool, &request.address, Some(user_id)).await?))
+}
+
+async fn verify_handler(
+    State(state): State<AuthState>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+    Json(request): Json<VerifyRequest>,
+) -> ApiResult<Json<AuthResponse>> {
+    // 🔐 taken before checking the signature, so a nonce gets exactly one try
+    let challenge = queries::take_wallet_challenge(&pool, &request.nonce)
+        .await?
+        .filter(|challenge| challenge.expires_at > Utc::now())
+        .ok_or(ApiError::Unauthorized("Unknown or expired challenge".to_string()))?;
+
+    if !verify_signature(&challenge.address, challenge.message.as_bytes(), &request.signature) {
+        audit.record(&challenge.address, AuditEntry::new("auth.login_failed", "provider", SOLANA_PROVIDER)).await;
+        return Err(ApiError::Unauthorized("Invalid wallet signature".to_string()));
+    }
+
+    let profile = ProviderProfile {
+        provider: SOLANA_PROVIDER.to_string(),
+        subject: challenge.address,
+        email: None,
+        email_verified: false,
+        username: None,
+    };
+    let user = resolve_login_user(&pool, &profile, challenge.link_user_id).await?;
+
+    // ✅ every login starts a new session
+    let session = Session::new(false);
+    let tokens = issue_tokens(&state, &pool, &user, SOLANA_PROVIDER, session).await?;
+    let action = if challenge.link_user_id.is_some() { "identity.link" } else { "auth.login" };
+    audit.record(&user.id.to_string(), session.audit_entry(action, SOLANA_PROVIDER)).await;
+    Ok(Json(tokens))
+}
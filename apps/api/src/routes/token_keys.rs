@@ -0,0 +1,613 @@
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use pqcrypto_mldsa::mldsa65;
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _, SecretKey as _};
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+const DEFAULT_KEYS_DIR: &str = "keys/jwt";
+
+/// JOSE `alg` of our post-quantum tokens (ML-DSA-65, FIPS 204)
+pub const ML_DSA_65: &str = "ML-DSA-65";
+
+/// Clock skew allowed on `exp`, same as jsonwebtoken's default
+const EXP_LEEWAY_SECS: i64 = 60;
+
+/// When to roll the signing key, and how long a replaced key still verifies
+#[derive(Debug, Clone)]
+pub struct KeyRotationPolicy {
+    pub rotate_after: Duration,
+    pub overlap: Duration, // keep ≥ access token lifetime so live tokens stay valid
+}
+
+impl Default for KeyRotationPolicy {
+    fn default() -> Self {
+        Self {
+            rotate_after: Duration::from_secs(30 * 24 * 3600),
+            overlap: Duration::from_secs(24 * 3600),
+        }
+    }
+}
+
+/// On-disk form of a key (`{kid}.json`, mode 0600)
+#[derive(Serialize, Deserialize)]
+struct StoredKey {
+    kid: String,
+    alg: String,
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+    #[serde(alias = "pkcs8")]
+    secret: String, // base64 PKCS#8 DER, or the raw ML-DSA secret key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public: Option<String>, // ML-DSA only; its secret key can't give it back
+}
+
+enum KeyMaterial {
+    Jose {
+        alg: Algorithm,
+        pkcs8: Zeroizing<Vec<u8>>,
+        encoding: EncodingKey,
+        decoding: DecodingKey,
+    },
+    MlDsa {
+        public: Box<mldsa65::PublicKey>, // ~2 KB, keep the enum small
+        secret: Zeroizing<Vec<u8>>,
+    },
+}
+
+struct SigningKey {
+    kid: String,
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+    material: KeyMaterial,
+    jwk: Value,
+}
+
+/// Verified claims, and whether a post-quantum key signed them
+pub struct VerifiedToken<T> {
+    pub claims: T,
+    pub post_quantum: bool,
+}
+
+#[derive(Deserialize)]
+struct JwsHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+impl SigningKey {
+    fn generate(alg: Algorithm) -> Result<Self, String> {
+        let rng = SystemRandom::new();
+        let pkcs8 = match alg {
+            Algorithm::EdDSA => Ed25519KeyPair::generate_pkcs8(&rng).map(|doc| doc.as_ref().to_vec()),
+            Algorithm::ES256 => EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+                .map(|doc| doc.as_ref().to_vec()),
+            other => return Err(format!("Unsupported signing algorithm {:?}", other)),
+        }
+        .map_err(|_| "Key generation failed".to_string())?;
+
+        Self::from_pkcs8(Uuid::new_v4().to_string(), alg, Utc::now(), None, Zeroizing::new(pkcs8))
+    }
+
+    fn generate_ml_dsa() -> Result<Self, String> {
+        let (public, secret) = mldsa65::keypair();
+        Self::from_ml_dsa(
+            Uuid::new_v4().to_string(),
+            Utc::now(),
+            None,
+            public.as_bytes(),
+            Zeroizing::new(secret.as_bytes().to_vec()),
+        )
+    }
+
+    fn from_pkcs8(
+        kid: String,
+        alg: Algorithm,
+        created_at: DateTime<Utc>,
+        retired_at: Option<DateTime<Utc>>,
+        pkcs8: Zeroizing<Vec<u8>>,
+    ) -> Result<Self, String> {
+        let (encoding, decoding, jwk) = match alg {
+            Algorithm::EdDSA => {
+                let pair = Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| format!("Key {} is not Ed25519", kid))?;
+                let x = URL_SAFE_NO_PAD.encode(pair.public_key().as_ref());
+                (
+                    EncodingKey::from_ed_der(&pkcs8),
+                    DecodingKey::from_ed_components(&x).map_err(|e| e.to_string())?,
+                    json!({ "kty": "OKP", "crv": "Ed25519", "x": x }),
+                )
+            }
+            Algorithm::ES256 => {
+                let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, &SystemRandom::new())
+                    .map_err(|_| format!("Key {} is not P-256", kid))?;
+                // uncompressed point: 0x04 || x || y
+                let point = pair.public_key().as_ref();
+                let x = URL_SAFE_NO_PAD.encode(&point[1..33]);
+                let y = URL_SAFE_NO_PAD.encode(&point[33..65]);
+                (
+                    EncodingKey::from_ec_der(&pkcs8),
+                    DecodingKey::from_ec_components(&x, &y).map_err(|e| e.to_string())?,
+                    json!({ "kty": "EC", "crv": "P-256", "x": x, "y": y }),
+                )
+            }
+            other => return Err(format!("Unsupported signing algorithm {:?}", other)),
+        };
+
+        let mut jwk = jwk;
+        jwk["kid"] = json!(kid);
+        jwk["alg"] = json!(alg);
+        jwk["use"] = json!("sig");
+
+        Ok(Self {
+            kid,
+            created_at,
+            retired_at,
+            material: KeyMaterial::Jose {
+                alg,
+                pkcs8,
+                encoding,
+                decoding,
+            },
+            jwk,
+        })
+    }
+
+    fn from_ml_dsa(
+        kid: String,
+        created_at: DateTime<Utc>,
+        retired_at: Option<DateTime<Utc>>,
+        public: &[u8],
+        secret: Zeroizing<Vec<u8>>,
+    ) -> Result<Self, String> {
+        let public = mldsa65::PublicKey::from_bytes(public).map_err(|_| format!("Key {} is not ML-DSA-65", kid))?;
+        mldsa65::SecretKey::from_bytes(&secret).map_err(|_| format!("Key {} is not ML-DSA-65", kid))?;
+
+        // "AKP" (algorithm key pair) JWK, as in the JOSE ML-DSA draft
+        let jwk = json!({
+            "kty": "AKP",
+            "alg": ML_DSA_65,
+            "pub": URL_SAFE_NO_PAD.encode(public.as_bytes()),
+            "kid": kid,
+            "use": "sig",
+        });
+
+        Ok(Self {
+            kid,
+            created_at,
+            retired_at,
+            material: KeyMaterial::MlDsa {
+                public: Box::new(public),
+                secret,
+            },
+            jwk,
+        })
+    }
+
+    fn restore(stored: StoredKey) -> Result<Self, String> {
+        let secret = Zeroizing::new(STANDARD.decode(&stored.secret).map_err(|e| e.to_string())?);
+        if stored.alg == ML_DSA_65 {
+            let public = stored
+                .public
+                .as_deref()
+                .ok_or_else(|| format!("Key {} has no public key", stored.kid))
+                .and_then(|public| STANDARD.decode(public).map_err(|e| e.to_string()))?;
+            return Self::from_ml_dsa(stored.kid, stored.created_at, stored.retired_at, &public, secret);
+        }
+
+        let alg = stored
+            .alg
+            .parse::<Algorithm>()
+            .map_err(|_| format!("Key {} has unknown alg {}", stored.kid, stored.alg))?;
+        Self::from_pkcs8(stored.kid, alg, stored.created_at, stored.retired_at, secret)
+    }
+
+    fn stored(&self) -> StoredKey {
+        let (secret, public) = match &self.material {
+            KeyMaterial::Jose { pkcs8, .. } => (STANDARD.encode(&**pkcs8), None),
+            KeyMaterial::MlDsa { public, secret } => (STANDARD.encode(&**secret), Some(STANDARD.encode(public.as_bytes()))),
+        };
+        StoredKey {
+            kid: self.kid.clone(),
+            alg: self.alg_name().to_string(),
+            created_at: self.created_at,
+            retired_at: self.retired_at,
+            secret,
+            public,
+        }
+    }
+
+    /// Copy of this key that stopped signing at `at`
+    fn retire(&self, at: DateTime<Utc>) -> Result<Self, String> {
+        Self::restore(StoredKey {
+            retired_at: Some(at),
+            ..self.stored()
+        })
+    }
+
+    fn alg_name(&self) -> &'static str {
+        match &self.material {
+            KeyMaterial::Jose { alg: Algorithm::ES256, .. } => "ES256",
+            KeyMaterial::Jose { .. } => "EdDSA",
+            KeyMaterial::MlDsa { .. } => ML_DSA_65,
+        }
+    }
+
+    fn is_post_quantum(&self) -> bool {
+        matches!(self.material, KeyMaterial::MlDsa { .. })
+    }
+
+    /// Still accepted for verification at `now`
+    fn verifies_at(&self, now: DateTime<Utc>, overlap: Duration) -> bool {
+        let overlap = chrono::Duration::from_std(overlap).unwrap_or(chrono::Duration::MAX);
+        self.retired_at
+            .is_none_or(|retired| retired.checked_add_signed(overlap).is_none_or(|end| now < end))
+    }
+}
+
+struct KeyRing {
+    active: Arc<SigningKey>,
+    active_pq: Arc<SigningKey>, // ML-DSA-65, for callers that asked for PQ tokens
+    retired: Vec<Arc<SigningKey>>,
+}
+
+impl KeyRing {
+    fn all(&self) -> impl Iterator<Item = &Arc<SigningKey>> {
+        [&self.active, &self.active_pq].into_iter().chain(self.retired.iter())
+    }
+}
+
+/// Asymmetric keys our JWTs are signed with.
+///
+/// One active key signs classical tokens and one ML-DSA key signs
+/// post-quantum ones; keys they replaced keep verifying for `policy.overlap`.
+/// Classical keys are published at `/.well-known/jwks.json`, ML-DSA keys at
+/// `/.well-known/pq-jwks.json` (many JWKS parsers reject unknown key types).
+#[derive(Clone)]
+pub struct TokenKeys {
+    ring: Arc<RwLock<KeyRing>>,
+    alg: Algorithm,
+    policy: KeyRotationPolicy,
+    dir: Option<PathBuf>, // persisted so restarts and `gen_jwt` share keys
+}
+
+impl TokenKeys {
+    /// In-memory keys (tests, throwaway environments)
+    pub fn ephemeral(alg: Algorithm, policy: KeyRotationPolicy) -> Result<Self, String> {
+        Ok(Self {
+            ring: Arc::new(RwLock::new(KeyRing {
+                active: Arc::new(SigningKey::generate(alg)?),
+                active_pq: Arc::new(SigningKey::generate_ml_dsa()?),
+                retired: Vec::new(),
+            })),
+            alg,
+            policy,
+            dir: None,
+        })
+    }
+
+    /// Keys stored in `dir`, creating (or rotating to) fresh ones when needed
+    pub fn load_or_create(dir: impl Into<PathBuf>, alg: Algorithm, policy: KeyRotationPolicy) -> Result<Self, String> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+        let mut keys = Vec::new();
+        for entry in std::fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let raw = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let stored: StoredKey =
+                serde_json::from_str(&raw).map_err(|e| format!("Invalid key file {}: {}", path.display(), e))?;
+            keys.push(SigningKey::restore(stored)?);
+        }
+        keys.sort_by_key(|key| key.created_at);
+
+        // newest unretired key of each kind signs; the rest are retiring
+        let active = match take_newest(&mut keys, |key| matches!(key.material, KeyMaterial::Jose { alg: key_alg, .. } if key_alg == alg)) {
+            Some(key) => key,
+            None => {
+                let key = SigningKey::generate(alg)?;
+                write_key(&dir, &key)?;
+                key
+            }
+        };
+        let active_pq = match take_newest(&mut keys, SigningKey::is_post_quantum) {
+            Some(key) => key,
+            None => {
+                let key = SigningKey::generate_ml_dsa()?;
+                write_key(&dir, &key)?;
+                key
+            }
+        };
+
+        let now = Utc::now();
+        let mut retired = Vec::new();
+        for key in keys {
+            let key = match key.retired_at {
+                Some(_) => key,
+                None => {
+                    let key = key.retire(now)?;
+                    write_key(&dir, &key)?;
+                    key
+                }
+            };
+            retired.push(Arc::new(key));
+        }
+
+        let keys = Self {
+            ring: Arc::new(RwLock::new(KeyRing {
+                active: Arc::new(active),
+                active_pq: Arc::new(active_pq),
+                retired,
+            })),
+            alg,
+            policy,
+            dir: Some(dir),
+        };
+        keys.rotate_if_due()?;
+        Ok(keys)
+    }
+
+    /// `JWT_SIGNING_ALG` (EdDSA | ES256), `JWT_KEYS_DIR` (default `keys/jwt`,
+    /// empty for in-memory keys), `JWT_KEY_ROTATION_SECS` and `JWT_KEY_OVERLAP_SECS`
+    pub fn from_env() -> Result<Self, String> {
+        let alg = match std::env::var("JWT_SIGNING_ALG").as_deref() {
+            Ok("ES256") => Algorithm::ES256,
+            Ok("EdDSA") | Err(_) => Algorithm::EdDSA,
+            Ok(other) => return Err(format!("JWT_SIGNING_ALG must be EdDSA or ES256, got {}", other)),
+        };
+        let secs = |key: &str| std::env::var(key).ok().and_then(|v| v.parse().ok()).map(Duration::from_secs);
+
+        let defaults = KeyRotationPolicy::default();
+        let policy = KeyRotationPolicy {
+            rotate_after: secs("JWT_KEY_ROTATION_SECS").unwrap_or(defaults.rotate_after),
+            overlap: secs("JWT_KEY_OVERLAP_SECS").unwrap_or(defaults.overlap),
+        };
+        match std::env::var("JWT_KEYS_DIR") {
+            // explicitly empty: keep keys in memory (tokens die with the process)
+            Ok(dir) if dir.is_empty() => Self::ephemeral(alg, policy),
+            Ok(dir) => Self::load_or_create(dir, alg, policy),
+            Err(_) => Self::load_or_create(DEFAULT_KEYS_DIR, alg, policy),
+        }
+    }
+
+    pub fn active_kid(&self) -> String {
+        self.ring.read().unwrap().active.kid.clone()
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        let active = self.ring.read().unwrap().active.clone();
+        let KeyMaterial::Jose { alg, encoding, .. } = &active.material else {
+            unreachable!("active classical key is always a JOSE key");
+        };
+        let mut header = Header::new(*alg);
+        header.kid = Some(active.kid.clone());
+        encode(&header, claims, encoding)
+    }
+
+    /// Sign `claims` as a compact JWS with `alg: ML-DSA-65`.
+    ///
+    /// jsonwebtoken has no ML-DSA support, so the token is assembled here;
+    /// the layout is the usual `header.payload.signature`.
+    pub fn sign_post_quantum<T: Serialize>(&self, claims: &T) -> Result<String, String> {
+        let active = self.ring.read().unwrap().active_pq.clone();
+        let KeyMaterial::MlDsa { secret, .. } = &active.material else {
+            unreachable!("active PQ key is always an ML-DSA key");
+        };
+
+        let header = json!({ "alg": ML_DSA_65, "typ": "JWT", "kid": active.kid });
+        let payload = serde_json::to_vec(claims).map_err(|e| e.to_string())?;
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+
+        let secret = mldsa65::SecretKey::from_bytes(secret).map_err(|_| "Invalid ML-DSA secret key".to_string())?;
+        let signature = mldsa65::detached_sign(signing_input.as_bytes(), &secret);
+        Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature.as_bytes())))
+    }
+
+    /// Verify a token signed by any key that is still within its overlap
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<VerifiedToken<T>, String> {
+        let mut parts = token.split('.');
+        let (Some(header_b64), Some(payload_b64), Some(signature_b64), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err("Malformed token".to_string());
+        };
+        let header: JwsHeader = URL_SAFE_NO_PAD
+            .decode(header_b64)
+            .ok()
+            .and_then(|raw| serde_json::from_slice(&raw).ok())
+            .ok_or("Malformed token header")?;
+        let kid = header.kid.ok_or("Token has no kid")?;
+
+        let key = self
+            .ring
+            .read()
+            .unwrap()
+            .all()
+            .find(|key| key.kid == kid)
+            .filter(|key| key.verifies_at(Utc::now(), self.policy.overlap))
+            .cloned()
+            .ok_or("Unknown or retired signing key")?;
+
+        // the key decides the algorithm, never the token header
+        if header.alg != key.alg_name() {
+            return Err("Token alg does not match its key".to_string());
+        }
+
+        let claims = match &key.material {
+            KeyMaterial::Jose { alg, decoding, .. } => decode::<T>(token, decoding, &Validation::new(*alg))
+                .map(|data| data.claims)
+                .map_err(|e| format!("Invalid token: {}", e))?,
+            KeyMaterial::MlDsa { public, .. } => {
+                let signature = URL_SAFE_NO_PAD
+                    .decode(signature_b64)
+                    .ok()
+                    .and_then(|raw| mldsa65::DetachedSignature::from_bytes(&raw).ok())
+                    .ok_or("Malformed token signature")?;
+                let signing_input = &token[..header_b64.len() + 1 + payload_b64.len()];
+                mldsa65::verify_detached_signature(&signature, signing_input.as_bytes(), public)
+                    .map_err(|_| "Invalid token: bad signature".to_string())?;
+
+                let payload: Value = URL_SAFE_NO_PAD
+                    .decode(payload_b64)
+                    .ok()
+                    .and_then(|raw| serde_json::from_slice(&raw).ok())
+                    .ok_or("Malformed token payload")?;
+                let exp = payload["exp"].as_i64().ok_or("Invalid token: missing exp")?;
+                if exp + EXP_LEEWAY_SECS < Utc::now().timestamp() {
+                    return Err("Invalid token: expired".to_string());
+                }
+                serde_json::from_value(payload).map_err(|e| format!("Invalid token: {}", e))?
+            }
+        };
+
+        Ok(VerifiedToken {
+            claims,
+            post_quantum: key.is_post_quantum(),
+        })
+    }
+
+    /// Start signing with new keys; the old ones keep verifying for the overlap
+    pub fn rotate(&self) -> Result<String, String> {
+        let next = SigningKey::generate(self.alg)?;
+        let next_pq = SigningKey::generate_ml_dsa()?;
+        if let Some(dir) = &self.dir {
+            write_key(dir, &next)?;
+            write_key(dir, &next_pq)?;
+        }
+
+        let now = Utc::now();
+        let mut ring = self.ring.write().unwrap();
+        let replaced = [
+            std::mem::replace(&mut ring.active, Arc::new(next)),
+            std::mem::replace(&mut ring.active_pq, Arc::new(next_pq)),
+        ];
+        for key in replaced {
+            let previous = key.retire(now)?;
+            if let Some(dir) = &self.dir {
+                write_key(dir, &previous)?;
+            }
+            ring.retired.push(Arc::new(previous));
+        }
+
+        let kid = ring.active.kid.clone();
+        drop(ring);
+        self.prune()?;
+        println!("🔑 Rotated JWT signing keys, now {}", kid);
+        Ok(kid)
+    }
+
+    /// Rotate if the active key is older than the policy allows; drop expired keys
+    pub fn rotate_if_due(&self) -> Result<bool, String> {
+        let age = {
+            let ring = self.ring.read().unwrap();
+            Utc::now().signed_duration_since(ring.active.created_at.min(ring.active_pq.created_at))
+        };
+        let due = age.to_std().is_ok_and(|age| age >= self.policy.rotate_after);
+        if due {
+            self.rotate()?;
+        } else {
+            self.prune()?;
+        }
+        Ok(due)
+    }
+
+    fn prune(&self) -> Result<(), String> {
+        let now = Utc::now();
+        let mut ring = self.ring.write().unwrap();
+        let (keep, expired): (Vec<_>, Vec<_>) = ring
+            .retired
+            .drain(..)
+            .partition(|key| key.verifies_at(now, self.policy.overlap));
+        ring.retired = keep;
+
+        if let Some(dir) = &self.dir {
+            for key in expired {
+                let _ = std::fs::remove_file(dir.join(format!("{}.json", key.kid)));
+            }
+        }
+        Ok(())
+    }
+
+    fn published(&self, post_quantum: bool) -> Value {
+        let now = Utc::now();
+        let ring = self.ring.read().unwrap();
+        let keys: Vec<&Value> = ring
+            .all()
+            .filter(|key| key.is_post_quantum() == post_quantum && key.verifies_at(now, self.policy.overlap))
+            .map(|key| &key.jwk)
+            .collect();
+        json!({ "keys": keys })
+    }
+
+    /// Public classical keys for `/.well-known/jwks.json`
+    pub fn jwks(&self) -> Value {
+        self.published(false)
+    }
+
+    /// Public ML-DSA keys for `/.well-known/pq-jwks.json`
+    pub fn pq_jwks(&self) -> Value {
+        self.published(true)
+    }
+
+    /// How often to check for a due rotation: hourly, or every
+    /// `JWT_KEY_ROTATION_SECS` when that's shorter
+    pub fn rotation_check_interval(&self) -> Duration {
+        self.policy
+            .rotate_after
+            .clamp(Duration::from_secs(1), Duration::from_secs(3600))
+    }
+
+    /// Check for due rotations every `every`
+    pub fn spawn_rotation(self, every: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            loop {
+                ticker.tick().await;
+                if let Err(err) = self.rotate_if_due() {
+                    tracing::error!("JWT key rotation failed: {}", err);
+                }
+            }
+        })
+    }
+}
+
+/// Remove and return the newest unretired key matching `kind`
+fn take_newest(keys: &mut Vec<SigningKey>, kind: impl Fn(&SigningKey) -> bool) -> Option<SigningKey> {
+    let index = keys.iter().rposition(|key| key.retired_at.is_none() && kind(key))?;
+    Some(keys.remove(index))
+}
+
+fn write_key(dir: &std::path::Path, key: &SigningKey) -> Result<(), String> {
+    let path = dir.join(format!("{}.json", key.kid));
+    let body = Zeroizing::new(serde_json::to_string_pretty(&key.stored()).map_err(|e| e.to_string())?);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    use std::io::Write;
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(body.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
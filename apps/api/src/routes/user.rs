@@ -2,129 +2,164 @@ use axum::{
     routing::{get, post, put, delete},
     Router, Json, Extension,
     extract::Path,
-    http::StatusCode,
+    response::Response,
 };
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use sqlx::PgPool;
 use uuid::Uuid;
-use chrono::Utc;
+use validator::Validate;
 
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
-pub struct User {
-    pub id: Uuid,
-    pub username: String,
-    pub email: String,
-    pub nft_token_id: Option<String>,
-    pub identity_hash: Option<String>,
-    pub created_at: chrono::DateTime<Utc>,
-}
+use db::models::{User, UserFields};
+use db::queries;
 
-#[derive(Deserialize)]
+use crate::routes::audit::{Audit, AuditEntry};
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::orgs::{active_org, Tenant};
+use crate::routes::permissions::{RequirePermission, UsersRead, UsersWrite};
+use crate::routes::validation::{not_blank, ValidJson};
+use crate::routes::versioning::{MergePatch, Preconditions, Tagged};
+
+#[derive(Deserialize, Validate)]
 pub struct NewUser {
+    #[validate(length(max = 64, message = "must be at most 64 characters"), custom(function = "not_blank"))]
     pub username: String,
+    #[validate(email(message = "must be an email address"))]
     pub email: String,
-    pub nft_token_id: Option<String>,
+    #[validate(length(max = 128, message = "must be at most 128 characters"))]
     pub identity_hash: Option<String>,
 }
 
 pub fn user_routes() -> Router {
     Router::new()
         .route("/users", get(get_users).post(create_user))
-        .route("/users/:id", get(get_user).put(update_user).delete(delete_user))
+        .route("/users/:id", get(get_user).put(update_user).patch(patch_user).delete(delete_user))
 }
 
-pub async fn get_users(Extension(pool): Extension<PgPool>) -> Json<Vec<User>> {
-    let users = sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY created_at DESC")
-        .fetch_all(&pool)
-        .await
-        .unwrap_or_default();
-    Json(users)
+/// Emails and usernames are unique, ignoring case
+fn already_taken(err: sqlx::Error) -> ApiError {
+    match &err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => match e.constraint() {
+            Some("users_username_key") => ApiError::Conflict("Username is already in use".to_string()),
+            _ => ApiError::Conflict("Email is already in use".to_string()),
+        },
+        _ => err.into(),
+    }
+}
+
+pub async fn get_users(
+    RequirePermission(caller, _): RequirePermission<UsersRead>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<Json<Vec<User>>> {
+    let tenant = active_org(&pool, &caller).await?;
+    let users = queries::get_org_users(&pool, tenant.org_id).await?;
+    Ok(Json(users))
 }
 
 pub async fn get_user(
+    RequirePermission(caller, _): RequirePermission<UsersRead>,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<User>, StatusCode> {
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    preconditions: Preconditions,
+) -> ApiResult<Response> {
+    let tenant = active_org(&pool, &caller).await?;
+    let user = load_user(&pool, &tenant, id).await?;
+    Ok(preconditions.respond(user))
+}
+
+/// Users of other organizations are as missing as deleted ones
+async fn load_user(pool: &PgPool, tenant: &Tenant, id: Uuid) -> ApiResult<User> {
+    queries::get_org_user(pool, tenant.org_id, id)
+        .await?
+        .ok_or(ApiError::NotFound("User not found".to_string()))
+}
 
-    match user {
-        Some(u) => Ok(Json(u)),
-        None => Err(StatusCode::NOT_FOUND),
+fn fields(payload: &NewUser) -> UserFields<'_> {
+    UserFields {
+        username: &payload.username,
+        email: &payload.email,
+        identity_hash: payload.identity_hash.as_deref(),
     }
 }
 
+/// New users join the caller's organization
 pub async fn create_user(
+    RequirePermission(caller, _): RequirePermission<UsersWrite>,
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<NewUser>,
-) -> Result<Json<User>, StatusCode> {
-    let user = sqlx::query_as!(
-        User,
-        r#"
-        INSERT INTO users (id, username, email, nft_token_id, identity_hash, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6)
-        RETURNING id, username, email, nft_token_id, identity_hash, created_at
-        "#,
-        Uuid::new_v4(),
-        payload.username,
-        payload.email,
-        payload.nft_token_id,
-        payload.identity_hash,
-        Utc::now()
-    )
-    .fetch_one(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Json(user))
+    audit: Audit,
+    ValidJson(payload): ValidJson<NewUser>,
+) -> ApiResult<Tagged<User>> {
+    let tenant = active_org(&pool, &caller).await?;
+    let user = queries::create_org_user(&pool, tenant.org_id, fields(&payload))
+        .await
+        .map_err(already_taken)?;
+
+    audit.record(&caller.sub, AuditEntry::new("user.create", "user", user.id).in_org(tenant.org_id).created(&user)).await;
+    Ok(Tagged(user))
 }
 
+/// Replace every field; `If-Match` makes it conditional
 pub async fn update_user(
+    RequirePermission(caller, _): RequirePermission<UsersWrite>,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-    Json(payload): Json<NewUser>,
-) -> Result<Json<User>, StatusCode> {
-    let user = sqlx::query_as!(
-        User,
-        r#"
-        UPDATE users
-        SET username = $1, email = $2, nft_token_id = $3, identity_hash = $4
-        WHERE id = $5
-        RETURNING id, username, email, nft_token_id, identity_hash, created_at
-        "#,
-        payload.username,
-        payload.email,
-        payload.nft_token_id,
-        payload.identity_hash,
-        id,
-    )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    match user {
-        Some(u) => Ok(Json(u)),
-        None => Err(StatusCode::NOT_FOUND),
-    }
+    audit: Audit,
+    preconditions: Preconditions,
+    ValidJson(payload): ValidJson<NewUser>,
+) -> ApiResult<Tagged<User>> {
+    let tenant = active_org(&pool, &caller).await?;
+    let before = load_user(&pool, &tenant, id).await?;
+    preconditions.check(&before)?;
+
+    save_user(&pool, &audit, &caller.sub, &tenant, before, payload).await
 }
 
-pub async fn delete_user(
+/// Change only the fields in the merge patch; `null` clears `identity_hash`
+pub async fn patch_user(
+    RequirePermission(caller, _): RequirePermission<UsersWrite>,
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<&'static str>, StatusCode> {
-    let rows_affected = sqlx::query!("DELETE FROM users WHERE id = $1", id)
-        .execute(&pool)
+    audit: Audit,
+    preconditions: Preconditions,
+    patch: MergePatch,
+) -> ApiResult<Tagged<User>> {
+    let tenant = active_org(&pool, &caller).await?;
+    let before = load_user(&pool, &tenant, id).await?;
+    preconditions.check(&before)?;
+
+    let payload: NewUser = patch.apply(&before)?;
+    save_user(&pool, &audit, &caller.sub, &tenant, before, payload).await
+}
+
+/// Write over the version that was read, so a concurrent update makes this one fail
+async fn save_user(
+    pool: &PgPool,
+    audit: &Audit,
+    actor: &str,
+    tenant: &Tenant,
+    before: User,
+    payload: NewUser,
+) -> ApiResult<Tagged<User>> {
+    let user = queries::update_org_user(pool, tenant.org_id, before.id, before.version, fields(&payload))
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .rows_affected();
+        .map_err(already_taken)?
+        .ok_or(ApiError::PreconditionFailed("User was changed by another request".to_string()))?;
 
-    if rows_affected == 0 {
-        Err(StatusCode::NOT_FOUND)
-    } else {
-        Ok(Json("User deleted"))
-    }
+    audit.record(actor, AuditEntry::new("user.update", "user", user.id).in_org(tenant.org_id).updated(&before, &user)).await;
+    Ok(Tagged(user))
 }
 
+pub async fn delete_user(
+    RequirePermission(caller, _): RequirePermission<UsersWrite>,
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    audit: Audit,
+) -> ApiResult<Json<&'static str>> {
+    let tenant = active_org(&pool, &caller).await?;
+    match queries::delete_org_user(&pool, tenant.org_id, id).await? {
+        Some(user) => {
+            audit.record(&caller.sub, AuditEntry::new("user.delete", "user", id).in_org(tenant.org_id).deleted(&user)).await;
+            Ok(Json("User deleted"))
+        }
+        None => Err(ApiError::NotFound("User not found".to_string())),
+    }
+}
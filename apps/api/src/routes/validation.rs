@@ -0,0 +1,64 @@
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::StatusCode,
+    Json,
+};
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::routes::error::{ApiError, FieldError};
+
+/// Extractor: a JSON body that passed its `#[validate(...)]` rules.
+/// Broken rules are all reported at once, as a 422 listing each field.
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(payload) = Json::<T>::from_request(req, state).await.map_err(json_error)?;
+        payload.validate().map_err(field_errors)?;
+        Ok(ValidJson(payload))
+    }
+}
+
+pub(crate) fn json_error(rejection: JsonRejection) -> ApiError {
+    match rejection.status() {
+        // parsed, but a field is missing or has the wrong type
+        StatusCode::UNPROCESSABLE_ENTITY => ApiError::Unprocessable(rejection.body_text()),
+        _ => ApiError::BadRequest(rejection.body_text()),
+    }
+}
+
+pub(crate) fn field_errors(errors: ValidationErrors) -> ApiError {
+    let mut fields: Vec<FieldError> = errors
+        .field_errors()
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors.iter().map(move |error| FieldError {
+                field: field.to_string(),
+                code: error.code.to_string(),
+                message: error
+                    .message
+                    .as_ref()
+                    .map_or_else(|| format!("failed the {} rule", error.code), |message| message.to_string()),
+            })
+        })
+        .collect();
+    fields.sort_by(|a, b| a.field.cmp(&b.field));
+    ApiError::Validation(fields)
+}
+
+/// Rule for names and other required text: something besides whitespace
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("not_blank").with_message("must not be blank".into()));
+    }
+    Ok(())
+}
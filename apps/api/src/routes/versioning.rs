@@ -0,0 +1,158 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::headers::{ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch, LastModified};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::convert::Infallible;
+use std::time::SystemTime;
+use validator::Validate;
+
+use crate::routes::error::{ApiError, ApiResult};
+use crate::routes::validation::{field_errors, json_error};
+
+/// Rows whose `version` the database bumps on every update. The version is
+/// the resource's (strong) ETag, `updated_at` its Last-Modified.
+pub trait Versioned {
+    fn version(&self) -> i32;
+    fn updated_at(&self) -> DateTime<Utc>;
+
+    fn etag(&self) -> ETag {
+        format!("\"{}\"", self.version()).parse().expect("a quoted number is a valid ETag")
+    }
+}
+
+macro_rules! versioned {
+    ($($model:ty),*) => {$(
+        impl Versioned for $model {
+            fn version(&self) -> i32 {
+                self.version
+            }
+
+            fn updated_at(&self) -> DateTime<Utc> {
+                self.updated_at
+            }
+        }
+    )*};
+}
+
+versioned!(db::models::Inventory, db::models::Package, db::models::User);
+
+fn insert_validators(headers: &mut HeaderMap, resource: &impl Versioned) {
+    headers.typed_insert(resource.etag());
+    headers.typed_insert(LastModified::from(SystemTime::from(resource.updated_at())));
+}
+
+/// Response: the resource as JSON, with its `ETag` and `Last-Modified`
+pub struct Tagged<T>(pub T);
+
+impl<T: Versioned + Serialize> IntoResponse for Tagged<T> {
+    fn into_response(self) -> Response {
+        let mut response = Json(&self.0).into_response();
+        insert_validators(response.headers_mut(), &self.0);
+        response
+    }
+}
+
+/// Extractor: the request's conditional headers. Handlers check writes
+/// against `If-Match` and answer reads with [`Preconditions::respond`].
+#[derive(Debug, Default)]
+pub struct Preconditions {
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<IfModifiedSince>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Preconditions {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            if_match: parts.headers.typed_get(),
+            if_none_match: parts.headers.typed_get(),
+            if_modified_since: parts.headers.typed_get(),
+        })
+    }
+}
+
+impl Preconditions {
+    /// 412 unless `If-Match`, when sent, names the current version.
+    /// Tags that don't parse match nothing, so a garbled header fails too.
+    pub fn check(&self, current: &impl Versioned) -> ApiResult<()> {
+        match &self.if_match {
+            Some(if_match) if !if_match.precondition_passes(&current.etag()) => Err(ApiError::PreconditionFailed(
+                "The resource has changed; fetch it again".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Conditional GET: 304 when the client's copy is current, else the resource.
+    /// `If-None-Match` wins over `If-Modified-Since`, as RFC 9110 asks.
+    pub fn respond<T: Versioned + Serialize>(&self, resource: T) -> Response {
+        let modified = match (&self.if_none_match, &self.if_modified_since) {
+            (Some(if_none_match), _) => if_none_match.precondition_passes(&resource.etag()),
+            (None, Some(since)) => since.is_modified(resource.updated_at().into()),
+            (None, None) => true,
+        };
+        if modified {
+            return Tagged(resource).into_response();
+        }
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        insert_validators(response.headers_mut(), &resource);
+        response
+    }
+}
+
+/// Extractor: a JSON Merge Patch (RFC 7396) body, sent as
+/// `application/merge-patch+json` or plain `application/json`
+pub struct MergePatch(pub Value);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for MergePatch {
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(patch) = Json::<Value>::from_request(req, state).await.map_err(json_error)?;
+        if !patch.is_object() {
+            return Err(ApiError::BadRequest("A merge patch must be a JSON object".to_string()));
+        }
+        Ok(MergePatch(patch))
+    }
+}
+
+impl MergePatch {
+    /// Patch `current`'s fields into a payload, held to the same rules as a full update
+    pub fn apply<T: DeserializeOwned + Validate>(&self, current: &impl Serialize) -> ApiResult<T> {
+        let mut document = serde_json::to_value(current).map_err(|e| ApiError::Internal(e.to_string()))?;
+        merge(&mut document, &self.0);
+        let payload: T = serde_json::from_value(document).map_err(|e| ApiError::Unprocessable(e.to_string()))?;
+        payload.validate().map_err(field_errors)?;
+        Ok(payload)
+    }
+}
+
+/// Objects merge key by key, `null` removes a key, anything else replaces
+fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().expect("just made an object");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
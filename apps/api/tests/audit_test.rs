@@ -0,0 +1,212 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::routes::audit::{audit_routes, REQUEST_ID_HEADER};
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+use db::models::{AuditFilter, NewAuditEvent};
+
+async fn setup() -> (Router, TokenKeys, sqlx::PgPool) {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let audit = audit_routes()
+        .layer(Extension(keys.clone()))
+        .layer(Extension(pool.clone()));
+    (app_routes(pool.clone(), keys.clone()).merge(audit), keys, pool)
+}
+
+fn token(keys: &TokenKeys, sub: &str, perms: &[&str]) -> String {
+    let now = chrono::Utc::now().timestamp();
+    keys.sign(&json!({
+        "sub": sub,
+        "exp": now + 3600,
+        "provider": "test",
+        "perms": perms,
+        "amr": ["oauth", "otp", "mfa"],
+        "mfa_time": now
+    }))
+    .unwrap()
+}
+
+async fn call(app: &Router, method: Method, uri: &str, token: &str, request_id: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(id) = request_id {
+        request = request.header(REQUEST_ID_HEADER, id);
+    }
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 1 << 20).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_writes_are_audited_with_diffs() {
+    let (app, keys, pool) = setup().await;
+    let email = format!("audited-{}@tidasone.com", Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &format!("audited-{}", Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+    let caller = token(&keys, &user.id.to_string(), &[]);
+    let auditor = token(&keys, "auditor@tidasone.com", &["audit:read"]);
+
+    let request_id = format!("req-{}", Uuid::new_v4());
+    let (status, item) = call(&app, Method::POST, "/inventory", &caller, Some(&request_id), Some(json!({
+        "name": "Star tracker",
+        "quantity": 1
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let id = item["id"].as_str().unwrap().to_string();
+    let uri = format!("/inventory/{}", id);
+
+    call(&app, Method::PUT, &uri, &caller, None, Some(json!({ "name": "Star tracker", "quantity": 5 }))).await;
+    let (status, _) = call(&app, Method::DELETE, &uri, &caller, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // only holders of audit:read see the trail
+    let query = format!("/audit?target_type=inventory&target_id={}", id);
+    let (status, _) = call(&app, Method::GET, &query, &caller, None, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, events) = call(&app, Method::GET, &query, &auditor, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let events = events.as_array().unwrap();
+    let actions: Vec<&str> = events.iter().map(|e| e["action"].as_str().unwrap()).collect();
+    assert_eq!(actions, ["inventory.delete", "inventory.update", "inventory.create"]);
+    assert!(events.iter().all(|e| e["actor"] == user.id.to_string() && e["org_id"] == DEFAULT_ORG_ID.to_string()));
+
+    let (deleted, updated, created) = (&events[0], &events[1], &events[2]);
+    assert_eq!(created["request_id"], request_id.as_str());
+    assert_eq!(created["changes"]["after"]["name"], "Star tracker");
+    assert!(created["changes"].get("before").is_none());
+    // an update keeps only what changed
+    assert_eq!(updated["changes"]["before"]["quantity"], 1);
+    assert_eq!(updated["changes"]["after"]["quantity"], 5);
+    assert!(updated["changes"]["after"].get("name").is_none());
+    // a request without an id got one
+    assert!(Uuid::parse_str(updated["request_id"].as_str().unwrap()).is_ok());
+    assert_eq!(deleted["changes"]["before"]["quantity"], 5);
+
+    let (_, mine) = call(&app, Method::GET, &format!("/audit?actor={}&limit=1", user.id), &auditor, None, None).await;
+    assert_eq!(mine.as_array().unwrap().len(), 1);
+
+    let (status, report) = call(&app, Method::GET, "/audit/verify", &auditor, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["valid"], true);
+    assert!(report["events_checked"].as_u64().unwrap() >= 3);
+}
+
+#[tokio::test]
+async fn test_trail_is_append_only_and_chained() {
+    let pool = init_db_pool().await;
+    let target = Uuid::new_v4().to_string();
+    let event = |action| NewAuditEvent {
+        actor: "audit-test",
+        org_id: None,
+        action,
+        target_type: "test",
+        target_id: Some(target.clone()),
+        changes: json!({ "after": { "n": 1 } }),
+        ip: Some("127.0.0.1"),
+        request_id: None,
+    };
+    db::queries::append_audit_event(&pool, event("test.first")).await.unwrap();
+    let mut second = db::queries::append_audit_event(&pool, event("test.second")).await.unwrap();
+
+    // each event chains onto the one before it, whoever wrote that
+    let filter = AuditFilter { before_id: Some(second.id), limit: Some(1), ..AuditFilter::default() };
+    let previous = db::queries::get_audit_events(&pool, &filter).await.unwrap();
+    assert_eq!(second.prev_hash, previous[0].hash);
+    assert_eq!(second.expected_hash(), second.hash);
+
+    // stored rows can't be edited or removed
+    let updated = sqlx::query("UPDATE audit_events SET actor = 'mallory' WHERE id = $1")
+        .bind(second.id)
+        .execute(&pool)
+        .await;
+    assert!(updated.is_err());
+    let deleted = sqlx::query("DELETE FROM audit_events WHERE id = $1").bind(second.id).execute(&pool).await;
+    assert!(deleted.is_err());
+
+    // and an edit made around the trigger shows up as a hash mismatch
+    second.changes = json!({ "after": { "n": 2 } });
+    assert_ne!(second.expected_hash(), second.hash);
+}
+
+#[tokio::test]
+async fn test_concurrent_appends_keep_one_chain() {
+    let pool = init_db_pool().await;
+    let target = Uuid::new_v4().to_string();
+    let appends = (0..8).map(|n| {
+        let (pool, target) = (pool.clone(), target.clone());
+        tokio::spawn(async move {
+            let event = NewAuditEvent {
+                actor: "audit-test",
+                org_id: None,
+                action: "test.concurrent",
+                target_type: "test",
+                target_id: Some(target),
+                changes: json!({ "after": { "n": n } }),
+                ip: None,
+                request_id: None,
+            };
+            db::queries::append_audit_event(&pool, event).await.unwrap()
+        })
+    });
+    let mut events = Vec::new();
+    for append in appends {
+        events.push(append.await.unwrap());
+    }
+
+    // appended one at a time: each picks up where the one before it left off
+    events.sort_by_key(|event| event.id);
+    for pair in events.windows(2) {
+        let filter = AuditFilter { before_id: Some(pair[1].id), limit: Some(1), ..AuditFilter::default() };
+        let previous = db::queries::get_audit_events(&pool, &filter).await.unwrap();
+        assert_eq!(pair[1].prev_hash, previous[0].hash);
+    }
+}
+
+#[tokio::test]
+async fn test_failed_audit_write_does_not_fail_the_change() {
+    let (app, keys, pool) = setup().await;
+    let user = db::queries::create_user(&pool, &format!("unaudited-{}", Uuid::new_v4()), &format!("unaudited-{}@tidasone.com", Uuid::new_v4()))
+        .await
+        .unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+    let caller = token(&keys, &user.id.to_string(), &[]);
+
+    // the trail refuses events from requests tagged for this test
+    for statement in [
+        "CREATE OR REPLACE FUNCTION audit_test_outage() RETURNS trigger AS $$
+         BEGIN RAISE EXCEPTION 'audit trail unavailable'; END; $$ LANGUAGE plpgsql",
+        "CREATE OR REPLACE TRIGGER audit_test_outage BEFORE INSERT ON audit_events
+         FOR EACH ROW WHEN (NEW.request_id LIKE 'audit-outage-%') EXECUTE FUNCTION audit_test_outage()",
+    ] {
+        sqlx::query(statement).execute(&pool).await.unwrap();
+    }
+
+    let request_id = format!("audit-outage-{}", Uuid::new_v4());
+    let item = json!({ "name": "Unaudited valve", "quantity": 1 });
+    let (status, body) = call(&app, Method::POST, "/inventory", &caller, Some(&request_id), Some(item)).await;
+    sqlx::query("DROP TRIGGER audit_test_outage ON audit_events").execute(&pool).await.unwrap();
+
+    // the item was committed before the audit write, so the client hears it was:
+    // an error here would only get it created twice on retry
+    assert_eq!(status, StatusCode::OK);
+    let stored = db::queries::get_inventory_item(&pool, DEFAULT_ORG_ID, body["id"].as_str().unwrap().parse().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(stored.unwrap().name, "Unaudited valve");
+}
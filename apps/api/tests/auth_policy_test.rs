@@ -0,0 +1,173 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    middleware, Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::json;
+use std::collections::HashMap;
+use tower::ServiceExt;
+
+use api::routes::audit::audit_routes;
+use api::routes::auth::{auth_routes, AuthState};
+use api::routes::auth_middleware::require_auth;
+use api::routes::commsec::init_commsec_state;
+use api::routes::identities::identity_routes;
+use api::routes::oidc::JwksCache;
+use api::routes::orgs::org_routes;
+use api::routes::retention::{retention_routes, RetentionPolicy, RetentionState};
+use api::routes::roles::role_routes;
+use api::routes::service_accounts::service_account_routes;
+use api::routes::sessions::session_routes;
+use api::routes::solana::solana_routes;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, commsec_routes, init_db_pool};
+
+const ID: &str = "6f1c1f4e-2d0b-4c33-9a57-2f0e8f1d4c11";
+
+/// Everything main.rs serves, behind the same policy layer
+async fn full_app(keys: &TokenKeys) -> Router {
+    let pool = init_db_pool().await;
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: keys.clone(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: Vec::new(),
+    };
+    let retention = RetentionState {
+        policy: RetentionPolicy::default(),
+        commsec: init_commsec_state(),
+    };
+    let rest = auth_routes(state.clone())
+        .merge(session_routes(state.clone()))
+        .merge(solana_routes(state.clone()))
+        .merge(org_routes(state))
+        .merge(identity_routes())
+        .merge(role_routes())
+        .merge(service_account_routes())
+        .merge(commsec_routes(init_commsec_state()))
+        .merge(retention_routes(retention))
+        .merge(audit_routes())
+        .route_layer(middleware::from_fn(require_auth))
+        .layer(Extension(keys.clone()))
+        .layer(Extension(pool.clone()));
+    app_routes(pool, keys.clone()).merge(rest)
+}
+
+async fn status(app: &Router, method: Method, uri: &str, bearer: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().method(method).uri(uri).header("Content-Type", "application/json");
+    if let Some(token) = bearer {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    app.clone().oneshot(request.body(Body::from("{}")).unwrap()).await.unwrap().status()
+}
+
+fn protected_routes() -> Vec<(Method, String)> {
+    let routes = [
+        (Method::GET, "/users"),
+        (Method::POST, "/users"),
+        (Method::GET, "/users/{id}"),
+        (Method::PUT, "/users/{id}"),
+        (Method::DELETE, "/users/{id}"),
+        (Method::GET, "/inventory"),
+        (Method::POST, "/inventory"),
+        (Method::GET, "/inventory/{id}"),
+        (Method::PUT, "/inventory/{id}"),
+        (Method::DELETE, "/inventory/{id}"),
+        (Method::GET, "/inventory/{id}/shares"),
+        (Method::PUT, "/inventory/{id}/shares/{id}"),
+        (Method::DELETE, "/inventory/{id}/shares/{id}"),
+        (Method::GET, "/packages"),
+        (Method::POST, "/packages"),
+        (Method::GET, "/packages/{id}"),
+        (Method::PUT, "/packages/{id}"),
+        (Method::DELETE, "/packages/{id}"),
+        (Method::GET, "/packages/{id}/shares"),
+        (Method::PUT, "/packages/{id}/shares/{id}"),
+        (Method::DELETE, "/packages/{id}/shares/{id}"),
+        (Method::GET, "/missions"),
+        (Method::POST, "/missions"),
+        (Method::GET, "/missions/{id}"),
+        (Method::DELETE, "/missions/{id}"),
+        (Method::GET, "/missions/{id}/assignments"),
+        (Method::POST, "/missions/{id}/assignments"),
+        (Method::DELETE, "/missions/{id}/assignments/{id}"),
+        (Method::GET, "/missions/{id}/updates"),
+        (Method::POST, "/missions/{id}/updates"),
+        (Method::GET, "/missions/{id}/beacons"),
+        (Method::POST, "/missions/{id}/beacons"),
+        (Method::GET, "/auth/link/mock"),
+        (Method::POST, "/auth/logout"),
+        (Method::POST, "/auth/admin/users/{id}/revoke-sessions"),
+        (Method::GET, "/auth/identities"),
+        (Method::DELETE, "/auth/identities/{id}"),
+        (Method::POST, "/auth/solana/link"),
+        (Method::GET, "/orgs"),
+        (Method::POST, "/orgs"),
+        (Method::GET, "/orgs/{id}/members"),
+        (Method::PUT, "/orgs/{id}/members/{id}"),
+        (Method::DELETE, "/orgs/{id}/members/{id}"),
+        (Method::POST, "/orgs/{id}/switch"),
+        (Method::GET, "/auth/admin/roles"),
+        (Method::POST, "/auth/admin/roles"),
+        (Method::PUT, "/auth/admin/roles/viewer"),
+        (Method::DELETE, "/auth/admin/roles/viewer"),
+        (Method::GET, "/auth/admin/users/{id}/roles"),
+        (Method::PUT, "/auth/admin/users/{id}/roles/viewer"),
+        (Method::DELETE, "/auth/admin/users/{id}/roles/viewer"),
+        (Method::GET, "/auth/admin/service-accounts"),
+        (Method::POST, "/auth/admin/service-accounts"),
+        (Method::GET, "/auth/admin/service-accounts/{id}"),
+        (Method::DELETE, "/auth/admin/service-accounts/{id}"),
+        (Method::GET, "/auth/admin/service-accounts/{id}/keys"),
+        (Method::POST, "/auth/admin/service-accounts/{id}/keys"),
+        (Method::DELETE, "/auth/admin/service-accounts/{id}/keys/{id}"),
+        (Method::POST, "/commsec/keypair"),
+        (Method::POST, "/commsec/encapsulate"),
+        (Method::POST, "/commsec/decapsulate"),
+        (Method::POST, "/commsec/aead/encrypt"),
+        (Method::POST, "/commsec/aead/decrypt"),
+        (Method::GET, "/commsec/alerts"),
+        (Method::GET, "/retention/report"),
+        (Method::GET, "/audit"),
+        (Method::GET, "/audit/verify"),
+    ];
+    routes.into_iter().map(|(method, uri)| (method, uri.replace("{id}", ID))).collect()
+}
+
+#[tokio::test]
+async fn test_protected_routes_require_a_token() {
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = full_app(&keys).await;
+    let stranger = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let forged = stranger.sign(&json!({ "sub": ID, "exp": exp, "provider": "test" })).unwrap();
+    let made_up_key = "tdk_00000000_AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+
+    for (method, uri) in protected_routes() {
+        assert_eq!(status(&app, method.clone(), &uri, None).await, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+        assert_eq!(status(&app, method.clone(), &uri, Some(&forged)).await, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+        assert_eq!(status(&app, method.clone(), &uri, Some(made_up_key)).await, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+    }
+}
+
+#[tokio::test]
+async fn test_public_routes_stay_open() {
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = full_app(&keys).await;
+
+    assert_eq!(status(&app, Method::GET, "/health", None).await, StatusCode::OK);
+    assert_eq!(status(&app, Method::GET, "/.well-known/jwks.json", None).await, StatusCode::OK);
+    assert_eq!(status(&app, Method::GET, "/.well-known/pq-jwks.json", None).await, StatusCode::OK);
+    // reach their handlers (no such provider, empty bodies) instead of the policy layer
+    assert_eq!(status(&app, Method::GET, "/auth/login/nope", None).await, StatusCode::BAD_REQUEST);
+    assert_eq!(status(&app, Method::GET, "/auth/callback/nope", None).await, StatusCode::BAD_REQUEST);
+    assert_eq!(status(&app, Method::POST, "/auth/refresh", None).await, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(status(&app, Method::POST, "/auth/solana/challenge", None).await, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(status(&app, Method::POST, "/auth/solana/verify", None).await, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // unknown routes are still 404, not 401
+    assert_eq!(status(&app, Method::GET, "/nowhere", None).await, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,74 @@
+use commsec_client::{crypto, types::*, CommsecClient, Error};
+
+use api::commsec_routes;
+use api::routes::commsec::init_commsec_state;
+
+/// Serve the real CommSec router on an ephemeral port
+async fn spawn_server() -> CommsecClient {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, commsec_routes(init_commsec_state())).await.unwrap();
+    });
+    CommsecClient::new(format!("http://{}", addr)).with_client_id("sdk-test")
+}
+
+#[tokio::test]
+async fn test_kem_matches_local_crypto() {
+    let client = spawn_server().await;
+    let keys = client.keypair().await.unwrap();
+
+    // client-side encapsulation, server-side decapsulation
+    let pk = crypto::b64_decode(&keys.public_key).unwrap();
+    let (ct, local_ss) = crypto::encapsulate(&pk).unwrap();
+    let remote = client
+        .decapsulate(&DecapsulateRequest {
+            secret_key: keys.secret_key.clone(),
+            ciphertext: crypto::b64_encode(&ct),
+        })
+        .await
+        .unwrap();
+    assert_eq!(crypto::b64_decode(&remote.shared_secret).unwrap(), local_ss);
+
+    // server-side encapsulation, client-side decapsulation
+    let encap = client
+        .encapsulate(&EncapsulateRequest { public_key: keys.public_key.clone() })
+        .await
+        .unwrap();
+    let sk = crypto::b64_decode(&keys.secret_key).unwrap();
+    let ss = crypto::decapsulate(&sk, &crypto::b64_decode(&encap.ciphertext).unwrap()).unwrap();
+    assert_eq!(crypto::b64_encode(ss), encap.shared_secret);
+}
+
+#[tokio::test]
+async fn test_aead_matches_local_crypto() {
+    let client = spawn_server().await;
+    let key = [9u8; crypto::KEY_LEN];
+    let nonce = crypto::random_nonce();
+
+    let remote = client
+        .aead_encrypt(&AeadEncryptRequest {
+            key: crypto::b64_encode(key),
+            nonce: crypto::b64_encode(nonce),
+            plaintext: "hello_tidasonesec".to_string(),
+            associated_data: Some("metadata_test".to_string()),
+        })
+        .await
+        .unwrap();
+
+    let local = crypto::seal(&key, &nonce, b"hello_tidasonesec", Some(b"metadata_test")).unwrap();
+    assert_eq!(crypto::b64_decode(&remote.ciphertext).unwrap(), local);
+
+    let opened = crypto::open(&key, &nonce, &local, Some(b"metadata_test")).unwrap();
+    assert_eq!(opened, b"hello_tidasonesec");
+
+    let tampered = client
+        .aead_decrypt(&AeadDecryptRequest {
+            key: crypto::b64_encode(key),
+            nonce: crypto::b64_encode(nonce),
+            ciphertext: remote.ciphertext,
+            associated_data: Some("wrong_ad".to_string()),
+        })
+        .await;
+    assert!(matches!(tampered, Err(Error::Server { status: 500, .. })));
+}
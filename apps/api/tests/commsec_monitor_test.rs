@@ -0,0 +1,77 @@
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use tower::ServiceExt;
+use serde_json::{json, Value};
+
+use api::commsec_routes;
+use api::routes::commsec::init_commsec_state;
+
+async fn post_json(app: &Router, uri: &str, payload: Value) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Content-Type", "application/json")
+                .header("X-Client-Id", "field-unit-7")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_tampered_ad_raises_alert() {
+    let app = commsec_routes(init_commsec_state());
+
+    let key = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8="; // 32 bytes
+    let nonce = "AAECAwQFBgcICQoL"; // 12 bytes
+
+    let (status, encrypted) = post_json(
+        &app,
+        "/commsec/aead/encrypt",
+        json!({ "key": key, "nonce": nonce, "plaintext": "rendezvous at 0400", "associated_data": "mission-1" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let ciphertext = encrypted["ciphertext"].as_str().unwrap();
+
+    let (status, _) = post_json(
+        &app,
+        "/commsec/aead/decrypt",
+        json!({ "key": key, "nonce": nonce, "ciphertext": ciphertext, "associated_data": "mission-1" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = post_json(
+        &app,
+        "/commsec/aead/decrypt",
+        json!({ "key": key, "nonce": nonce, "ciphertext": ciphertext, "associated_data": "mission-2" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let response = app
+        .oneshot(Request::builder().uri("/commsec/alerts").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    let alerts: Value = serde_json::from_slice(&bytes).unwrap();
+    let alerts = alerts["alerts"].as_array().unwrap();
+
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0]["kind"], "ad_tampering");
+    assert_eq!(alerts[0]["client"], "field-unit-7");
+}
@@ -0,0 +1,123 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Request, StatusCode},
+    response::IntoResponse,
+    Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::routes::error::ApiError;
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+
+fn token(keys: &TokenKeys, sub: &str, perms: &[&str]) -> String {
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    keys.sign(&json!({ "sub": sub, "exp": exp, "provider": "test", "perms": perms })).unwrap()
+}
+
+async fn get(app: &Router, uri: &str, token: Option<&str>) -> (StatusCode, Option<String>, Value) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|v| v.to_str().unwrap().to_string());
+    let bytes = body::to_bytes(response.into_body(), 1 << 20).await.unwrap();
+    (status, content_type, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_errors_are_problem_documents() {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = app_routes(pool.clone(), keys.clone());
+
+    let email = format!("problems-{}@tidasone.com", Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &format!("problems-{}", Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+    let caller = token(&keys, &user.id.to_string(), &[]);
+
+    let (status, content_type, problem) = get(&app, &format!("/inventory/{}", Uuid::new_v4()), Some(&caller)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+    assert_eq!(problem, json!({
+        "type": "about:blank",
+        "title": "Not Found",
+        "status": 404,
+        "detail": "Item not found",
+        "code": "not_found"
+    }));
+
+    // extractor rejections use the same shape
+    let (status, content_type, problem) = get(&app, "/inventory", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+    assert_eq!(problem["code"], "unauthorized");
+
+    let (status, _, problem) = get(&app, "/users", Some(&caller)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["detail"], "Missing permission users:read");
+}
+
+#[tokio::test]
+async fn test_sqlx_errors_map_onto_statuses() {
+    let pool = init_db_pool().await;
+    let email = format!("constraints-{}@tidasone.com", Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &format!("constraints-{}", Uuid::new_v4()), &email).await.unwrap();
+
+    let duplicate = sqlx::query("INSERT INTO users (id, username, email) VALUES ($1, 'twin', $2)")
+        .bind(user.id)
+        .bind(&email)
+        .execute(&pool)
+        .await
+        .unwrap_err();
+    let err = ApiError::from(duplicate);
+    assert_eq!((err.status(), err.code()), (StatusCode::CONFLICT, "conflict"));
+
+    let dangling = sqlx::query("INSERT INTO user_roles (user_id, role) VALUES ($1, 'member')")
+        .bind(Uuid::new_v4())
+        .execute(&pool)
+        .await
+        .unwrap_err();
+    let err = ApiError::from(dangling);
+    assert_eq!((err.status(), err.code()), (StatusCode::UNPROCESSABLE_ENTITY, "unprocessable"));
+
+    let missing = sqlx::query_scalar::<_, i32>("SELECT 1 FROM users WHERE id = $1")
+        .bind(Uuid::new_v4())
+        .fetch_one(&pool)
+        .await
+        .unwrap_err();
+    assert_eq!(ApiError::from(missing).status(), StatusCode::NOT_FOUND);
+
+    // other database errors don't leak their details
+    let broken = sqlx::query("SELECT * FROM no_such_table").execute(&pool).await.unwrap_err();
+    let response = ApiError::from(broken).into_response();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let bytes = body::to_bytes(response.into_body(), 1 << 20).await.unwrap();
+    let problem: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((problem["code"].as_str(), problem["detail"].as_str()), (Some("database_error"), Some("Database error")));
+}
+
+#[tokio::test]
+async fn test_list_endpoints_surface_database_failures() {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = app_routes(pool.clone(), keys.clone());
+    let admin = token(&keys, &Uuid::new_v4().to_string(), &["users:read"]);
+
+    // a closed pool fails every query; lists must not pass that off as empty
+    pool.close().await;
+    for uri in ["/users", "/inventory", "/packages"] {
+        let (status, _, problem) = get(&app, uri, Some(&admin)).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR, "{}", uri);
+        assert_eq!(problem["code"], "database_error", "{}", uri);
+    }
+}
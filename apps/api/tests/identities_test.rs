@@ -0,0 +1,77 @@
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+use api::init_db_pool;
+use api::routes::identities::{resolve_login_user, ProviderProfile};
+
+fn profile(provider: &str, subject: &str, email: Option<&str>, verified: bool) -> ProviderProfile {
+    ProviderProfile {
+        provider: provider.to_string(),
+        subject: subject.to_string(),
+        email: email.map(str::to_string),
+        email_verified: verified,
+        username: None,
+    }
+}
+
+#[tokio::test]
+async fn test_first_login_provisions_and_repeat_login_reuses_user() {
+    let pool = init_db_pool().await;
+    let subject = Uuid::new_v4().to_string();
+    let email = format!("{}@tidasone.com", subject);
+
+    let first = resolve_login_user(&pool, &profile("google", &subject, Some(&email), true), None)
+        .await
+        .unwrap();
+    assert_eq!(first.email, email);
+
+    let again = resolve_login_user(&pool, &profile("google", &subject, Some(&email), true), None)
+        .await
+        .unwrap();
+    assert_eq!(again.id, first.id);
+
+    // same verified email from another provider joins the existing user
+    let github = resolve_login_user(&pool, &profile("github", &subject, Some(&email), true), None)
+        .await
+        .unwrap();
+    assert_eq!(github.id, first.id);
+
+    let identities = db::queries::get_user_identities(&pool, first.id).await.unwrap();
+    assert_eq!(identities.len(), 2);
+}
+
+#[tokio::test]
+async fn test_unverified_email_does_not_join_existing_user() {
+    let pool = init_db_pool().await;
+    let email = format!("{}@tidasone.com", Uuid::new_v4());
+    let existing = db::queries::create_user(&pool, &format!("victim-{}", Uuid::new_v4()), &email).await.unwrap();
+
+    let user = resolve_login_user(
+        &pool,
+        &profile("amazon", &Uuid::new_v4().to_string(), Some(&email), false),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_ne!(user.id, existing.id);
+}
+
+#[tokio::test]
+async fn test_explicit_link_and_conflict() {
+    let pool = init_db_pool().await;
+    let alice_name = format!("alice-{}", Uuid::new_v4());
+    let alice = db::queries::create_user(&pool, &alice_name, &format!("{}@tidasone.com", alice_name))
+        .await
+        .unwrap();
+    let bob_name = format!("bob-{}", Uuid::new_v4());
+    let bob = db::queries::create_user(&pool, &bob_name, &format!("{}@tidasone.com", bob_name))
+        .await
+        .unwrap();
+
+    let gh = profile("github", &Uuid::new_v4().to_string(), None, false);
+    let linked = resolve_login_user(&pool, &gh, Some(alice.id)).await.unwrap();
+    assert_eq!(linked.id, alice.id);
+
+    let err = resolve_login_user(&pool, &gh, Some(bob.id)).await.unwrap_err();
+    assert_eq!(err.status(), StatusCode::CONFLICT);
+}
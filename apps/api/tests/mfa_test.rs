@@ -0,0 +1,166 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::init_db_pool;
+use api::routes::auth::AuthState;
+use api::routes::inventory::inventory_routes;
+use api::routes::mfa::mfa_routes;
+use api::routes::oidc::JwksCache;
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::sessions::{issue_tokens, session_routes, Session};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::routes::totp;
+
+async fn setup() -> (Router, AuthState, sqlx::PgPool) {
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: Vec::new(),
+    };
+    let pool = init_db_pool().await;
+    let app = mfa_routes(state.clone())
+        .merge(session_routes(state.clone()))
+        .merge(inventory_routes())
+        .layer(Extension(state.keys.clone()))
+        .layer(Extension(pool.clone()));
+    (app, state, pool)
+}
+
+async fn call(app: &Router, method: Method, uri: &str, bearer: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", bearer))
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn code(secret: &str, steps_ahead: i64) -> String {
+    let step = totp::current_step(chrono::Utc::now().timestamp()) + steps_ahead;
+    totp::code_at(&totp::base32_decode(secret).unwrap(), step)
+}
+
+fn claims(state: &AuthState, token: &str) -> Value {
+    state.keys.verify::<Value>(token).unwrap().claims
+}
+
+#[test]
+fn test_totp_matches_rfc_6238() {
+    // RFC 6238 appendix B, SHA-1 secret, truncated to six digits
+    let secret = b"12345678901234567890";
+    assert_eq!(totp::code_at(secret, totp::current_step(59)), "287082");
+    assert_eq!(totp::code_at(secret, totp::current_step(1111111109)), "081804");
+    assert_eq!(totp::code_at(secret, totp::current_step(20000000000)), "353130");
+
+    let encoded = totp::base32_encode(secret);
+    assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    assert_eq!(totp::base32_decode(&encoded).unwrap(), secret);
+
+    assert_eq!(totp::verify(&encoded, "287082", 59), Some(1));
+    assert_eq!(totp::verify(&encoded, "287082", 59 + 30 * 5), None);
+}
+
+#[tokio::test]
+async fn test_enroll_step_up_and_recent_mfa_routes() {
+    let (app, state, pool) = setup().await;
+    let email = format!("mfa-{}@tidasone.com", Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &format!("mfa-{}", Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+    let login = issue_tokens(&state, &pool, &user, "mock", Session::new(false)).await.unwrap();
+    assert_eq!(claims(&state, &login.token)["amr"], json!(["oauth"]));
+
+    let (status, item) = call(&app, Method::POST, "/inventory", &login.token, Some(json!({ "name": "Crypto card", "quantity": 1 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let item_uri = format!("/inventory/{}", item["id"].as_str().unwrap());
+
+    // no step-up yet
+    let (status, _) = call(&app, Method::DELETE, &item_uri, &login.token, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, Method::POST, "/auth/mfa/verify", &login.token, Some(json!({ "code": "000000" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, enrollment) = call(&app, Method::POST, "/auth/mfa/totp", &login.token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let secret = enrollment["secret"].as_str().unwrap();
+    let uri = enrollment["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/TIDasONE:mfa-"));
+    assert!(uri.contains(&format!("secret={}", secret)));
+    assert_eq!(enrollment["recovery_codes"].as_array().unwrap().len(), 10);
+
+    let (status, _) = call(&app, Method::POST, "/auth/mfa/totp/confirm", &login.token, Some(json!({ "code": "000000" }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, confirmed) = call(&app, Method::POST, "/auth/mfa/totp/confirm", &login.token, Some(json!({ "code": code(secret, 0) }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let stepped_up = claims(&state, confirmed["token"].as_str().unwrap());
+    assert_eq!(stepped_up["amr"], json!(["oauth", "otp", "mfa"]));
+    assert_eq!(stepped_up["sid"], claims(&state, &login.token)["sid"]);
+
+    let (status, _) = call(&app, Method::POST, "/auth/mfa/totp", &login.token, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // a code works once
+    let (status, _) = call(&app, Method::POST, "/auth/mfa/verify", &login.token, Some(json!({ "code": code(secret, 0) }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // refreshing keeps the MFA time
+    let (status, refreshed) = call(&app, Method::POST, "/auth/refresh", &login.token, Some(json!({
+        "refresh_token": confirmed["refresh_token"]
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(claims(&state, refreshed["token"].as_str().unwrap())["mfa_time"], stepped_up["mfa_time"]);
+
+    let (status, _) = call(&app, Method::DELETE, &item_uri, refreshed["token"].as_str().unwrap(), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // recovery codes: single use, formatting doesn't matter
+    let recovery = enrollment["recovery_codes"][0].as_str().unwrap().to_uppercase().replace('-', " ");
+    let (status, _) = call(&app, Method::POST, "/auth/mfa/verify", &login.token, Some(json!({ "recovery_code": recovery }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::POST, "/auth/mfa/verify", &login.token, Some(json!({ "recovery_code": recovery }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, mfa) = call(&app, Method::GET, "/auth/mfa", &login.token, None).await;
+    assert_eq!(mfa, json!({ "totp_enabled": true, "totp_pending": false, "recovery_codes_left": 9 }));
+
+    // turning MFA off needs a step-up too
+    let (status, _) = call(&app, Method::DELETE, "/auth/mfa/totp", &login.token, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, verified) = call(&app, Method::POST, "/auth/mfa/verify", &login.token, Some(json!({ "code": code(secret, 1) }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::DELETE, "/auth/mfa/totp", verified["token"].as_str().unwrap(), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_stale_mfa_is_not_recent() {
+    let (app, state, _pool) = setup().await;
+    let now = chrono::Utc::now().timestamp();
+    let stale = state
+        .keys
+        .sign(&json!({
+            "sub": Uuid::new_v4().to_string(),
+            "exp": now + 3600,
+            "provider": "test",
+            "amr": ["oauth", "otp", "mfa"],
+            "mfa_time": now - 3600
+        }))
+        .unwrap();
+
+    let (status, _) = call(&app, Method::DELETE, &format!("/inventory/{}", Uuid::new_v4()), &stale, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
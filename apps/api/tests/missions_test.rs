@@ -0,0 +1,148 @@
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose, Engine as _};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+
+fn token_for(keys: &TokenKeys, sub: &str) -> String {
+    token_with(keys, sub, &["missions:read", "missions:write"])
+}
+
+fn token_with(keys: &TokenKeys, sub: &str, perms: &[&str]) -> String {
+    let exp = chrono::Utc::now().timestamp() as usize + 3600;
+    keys.sign(&json!({ "sub": sub, "exp": exp, "provider": "test", "perms": perms })).unwrap()
+}
+
+async fn send(app: &Router, method: &str, uri: &str, token: &str, payload: Option<Value>) -> (StatusCode, Value) {
+    let body = payload.map(|p| Body::from(p.to_string())).unwrap_or_else(Body::empty);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(body)
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_mission_updates_and_beacons() {
+    let pool = init_db_pool().await;
+    let email = format!("op-{}@tidasone.com", uuid::Uuid::new_v4());
+    let operator = db::queries::create_user(&pool, &format!("operator-{}", uuid::Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, operator.id, "member").await.unwrap();
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = app_routes(pool.clone(), keys.clone());
+    let token = token_for(&keys, &email);
+
+    let (status, mission) = send(&app, "POST", "/missions", &token, Some(json!({
+        "name": "Night Owl",
+        "checkin_interval_secs": 600
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let mission_id = mission["id"].as_str().unwrap().to_string();
+
+    let sealed = json!({
+        "nonce": general_purpose::STANDARD.encode([7u8; 12]),
+        "ciphertext": general_purpose::STANDARD.encode([1u8; 32]),
+        "associated_data": "night-owl"
+    });
+
+    // not assigned yet
+    let uri = format!("/missions/{}/updates", mission_id);
+    let (status, _) = send(&app, "POST", &uri, &token, Some(sealed.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, "POST", &format!("/missions/{}/assignments", mission_id), &token, Some(json!({
+        "user_id": operator.id
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, update) = send(&app, "POST", &uri, &token, Some(sealed)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(update["author_id"], json!(operator.id));
+
+    let (status, updates) = send(&app, "GET", &uri, &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updates.as_array().unwrap().len(), 1);
+
+    let beacons_uri = format!("/missions/{}/beacons", mission_id);
+    let (status, _) = send(&app, "POST", &beacons_uri, &token, Some(json!({ "location": "Grid 7" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, beacon) = send(&app, "POST", &beacons_uri, &token, Some(json!({ "location": "Grid 8" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(beacon["checkins"], 2);
+
+    let (status, beacons) = send(&app, "GET", &beacons_uri, &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(beacons[0]["location"], "Grid 8");
+    assert_eq!(beacons[0]["missed"], false);
+}
+
+#[tokio::test]
+async fn test_mission_status_and_writes_are_gated() {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = app_routes(pool.clone(), keys.clone());
+    let mut users = Vec::new();
+    for name in ["lead", "observer"] {
+        let email = format!("{}-{}@tidasone.com", name, uuid::Uuid::new_v4());
+        let user = db::queries::create_user(&pool, &format!("{}-{}", name, uuid::Uuid::new_v4()), &email).await.unwrap();
+        db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+        users.push((email, user.id));
+    }
+    let lead = token_for(&keys, &users[0].0);
+    let observer = token_with(&keys, &users[1].0, &["missions:read"]);
+
+    let (status, mission) = send(&app, "POST", "/missions", &lead, Some(json!({ "name": "Kestrel" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(mission["status"], "Planned");
+    let id = mission["id"].as_str().unwrap().to_string();
+    let status_uri = format!("/missions/{}/status", id);
+
+    let (status, mission) = send(&app, "PUT", &status_uri, &lead, Some(json!({ "status": "Active" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(mission["status"], "Active");
+
+    // only the known statuses, spelled the way they're listed
+    for unknown in ["active", "Exploded", ""] {
+        let (status, body) = send(&app, "PUT", &status_uri, &lead, Some(json!({ "status": unknown }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", unknown);
+        assert_eq!(body["errors"][0]["field"], "status");
+    }
+    let bypass = sqlx::query("UPDATE missions SET status = 'Exploded' WHERE id = $1")
+        .bind(uuid::Uuid::parse_str(&id).unwrap())
+        .execute(&pool)
+        .await;
+    assert!(bypass.is_err());
+
+    // readers can look, but not create, assign or change status
+    let (status, _) = send(&app, "GET", &format!("/missions/{}", id), &observer, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, "POST", "/missions", &observer, Some(json!({ "name": "Rogue" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let assignment = json!({ "user_id": users[1].1 });
+    let (status, _) = send(&app, "POST", &format!("/missions/{}/assignments", id), &observer, Some(assignment)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, "PUT", &status_uri, &observer, Some(json!({ "status": "Aborted" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, mission) = send(&app, "GET", &format!("/missions/{}", id), &observer, None).await;
+    assert_eq!(mission["status"], "Active");
+}
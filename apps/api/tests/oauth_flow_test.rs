@@ -0,0 +1,308 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Request, StatusCode},
+    Extension, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use jsonwebtoken::Algorithm;
+use oauth2::url::Url;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use api::init_db_pool;
+use api::mock_oauth::{IdTokenFaults, MockProvider, MOCK_CLIENT_ID, MOCK_CLIENT_SECRET};
+use api::routes::auth::{auth_routes, AuthState};
+use api::routes::oidc::JwksCache;
+use api::routes::providers::{load_providers, ProvidersConfig};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys, ML_DSA_65};
+
+/// Auth router for a mock provider registered with `config` (a `[providers.mock]` body)
+async fn router_for(config: &str) -> (Router, sqlx::PgPool) {
+    // registered the same way main.rs does
+    let config = ProvidersConfig::from_toml(&format!(
+        r#"
+        [providers.mock]
+        client_id = "{}"
+        client_secret = "{}"
+        required = true
+        {}
+        "#,
+        MOCK_CLIENT_ID, MOCK_CLIENT_SECRET, config
+    ))
+    .unwrap();
+
+    let state = AuthState {
+        providers: load_providers(&config).await.unwrap(),
+        keys: TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        // refetch on every unknown kid so key rotation is observable
+        jwks: JwksCache::new(Duration::from_secs(3600), Duration::ZERO),
+        admin_emails: Vec::new(),
+    };
+
+    let pool = init_db_pool().await;
+    (auth_routes(state).layer(Extension(pool.clone())), pool)
+}
+
+/// Mock registered as an OpenID Connect provider (discovery + ID tokens)
+async fn setup() -> (Router, sqlx::PgPool, MockProvider) {
+    let mock = MockProvider::spawn("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let (app, pool) = router_for(&format!(r#"issuer = "{}""#, mock.issuer())).await;
+    (app, pool, mock)
+}
+
+/// Mock registered as a plain OAuth 2.0 provider (userinfo only)
+async fn setup_oauth2() -> (Router, sqlx::PgPool, MockProvider) {
+    let mock = MockProvider::spawn("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let endpoints = format!(
+        r#"
+        auth_url = "{0}/authorize"
+        token_url = "{0}/token"
+        userinfo_url = "{0}/userinfo"
+        "#,
+        mock.issuer()
+    );
+    let (app, pool) = router_for(&endpoints).await;
+    (app, pool, mock)
+}
+
+/// Start a login and follow the provider redirect; returns (callback path, cookie, state)
+async fn start_login(app: &Router) -> (String, String, String) {
+    start_login_at(app, "/auth/login/mock").await
+}
+
+async fn start_login_at(app: &Router, login_uri: &str) -> (String, String, String) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(login_uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    let location = response.headers()[header::LOCATION].to_str().unwrap().to_string();
+    let cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+    let cookie = cookie.split(';').next().unwrap().to_string();
+
+    let params: HashMap<String, String> = Url::parse(&location).unwrap().query_pairs().into_owned().collect();
+    assert_eq!(params["code_challenge_method"], "S256");
+    assert_eq!(cookie, format!("oauth_state={}", params["state"]));
+
+    let provider = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let redirect = provider.get(&location).send().await.unwrap();
+    let callback = Url::parse(redirect.headers()["location"].to_str().unwrap()).unwrap();
+
+    (
+        format!("{}?{}", callback.path(), callback.query().unwrap()),
+        cookie,
+        params["state"].clone(),
+    )
+}
+
+async fn callback(app: &Router, uri: &str, cookie: Option<&str>) -> (StatusCode, Value) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(cookie) = cookie {
+        request = request.header(header::COOKIE, cookie);
+    }
+    let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_full_login_flow_and_state_is_single_use() {
+    let (app, _pool, _) = setup().await;
+    let (uri, cookie, _) = start_login(&app).await;
+
+    let (status, body) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["token"].as_str().is_some());
+    assert!(body["refresh_token"].as_str().is_some());
+
+    // replaying the same callback must not mint a second token
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_callback_rejects_missing_or_mismatched_state() {
+    let (app, _pool, _) = setup().await;
+
+    // login CSRF: callback delivered to a browser that never started the flow
+    let (uri, _, _) = start_login(&app).await;
+    let (status, _) = callback(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (uri, _, _) = start_login(&app).await;
+    let (status, _) = callback(&app, &uri, Some("oauth_state=forged")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, cookie, _) = start_login(&app).await;
+    let (status, _) = callback(&app, "/auth/callback/mock?code=x&state=forged", Some(&cookie)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_callback_rejects_expired_state_and_wrong_verifier() {
+    let (app, pool, _) = setup().await;
+
+    let (uri, cookie, state) = start_login(&app).await;
+    sqlx::query("UPDATE oauth_states SET expires_at = NOW() - INTERVAL '1 second' WHERE state = $1")
+        .bind(&state)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // the provider only accepts the verifier matching the challenge it saw
+    let (uri, cookie, state) = start_login(&app).await;
+    sqlx::query("UPDATE oauth_states SET pkce_verifier = 'not-the-verifier-that-was-challenged-xxxxxxxxx' WHERE state = $1")
+        .bind(&state)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_login_provisions_the_mock_account() {
+    let (app, pool, mock) = setup().await;
+    let subject = uuid::Uuid::new_v4().to_string();
+    mock.configure(|b| {
+        b.account.sub = subject.clone();
+        b.account.email = Some(format!("{}@mock.tidasone", subject));
+    });
+
+    let (uri, cookie, _) = start_login(&app).await;
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let identity = db::queries::find_identity(&pool, "mock", &subject).await.unwrap().unwrap();
+    let user = db::queries::get_user(&pool, identity.user_id).await.unwrap().unwrap();
+    assert_eq!(user.email, format!("{}@mock.tidasone", subject));
+}
+
+#[tokio::test]
+async fn test_denied_consent_is_unauthorized() {
+    let (app, _pool, mock) = setup().await;
+    mock.configure(|b| b.deny_consent = true);
+
+    let (uri, cookie, _) = start_login(&app).await;
+    assert!(uri.contains("error=access_denied"));
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_bad_code_is_rejected() {
+    let (app, _pool, _) = setup().await;
+
+    let (_, cookie, state) = start_login(&app).await;
+    let uri = format!("/auth/callback/mock?code=not-a-real-code&state={}", state);
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_userinfo_failure_is_bad_gateway() {
+    let (app, _pool, mock) = setup_oauth2().await;
+    mock.configure(|b| b.fail_userinfo = true);
+
+    let (uri, cookie, _) = start_login(&app).await;
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn test_oauth2_provider_identity_comes_from_userinfo() {
+    let (app, pool, mock) = setup_oauth2().await;
+    let subject = uuid::Uuid::new_v4().to_string();
+    mock.configure(|b| b.account.sub = subject.clone());
+
+    let (uri, cookie, _) = start_login(&app).await;
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(db::queries::find_identity(&pool, "mock", &subject).await.unwrap().is_some());
+    assert_eq!(mock.jwks_requests(), 0);
+}
+
+#[tokio::test]
+async fn test_oidc_login_ignores_userinfo() {
+    let (app, _pool, mock) = setup().await;
+    mock.configure(|b| b.fail_userinfo = true);
+
+    let (uri, cookie, _) = start_login(&app).await;
+    assert!(uri.contains("code="));
+    let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_invalid_id_tokens_are_rejected() {
+    let faults: [fn(&mut IdTokenFaults); 6] = [
+        |f| f.omit = true,
+        |f| f.audience = Some("someone-else".to_string()),
+        |f| f.issuer = Some("https://evil.example".to_string()),
+        |f| f.nonce = Some("replayed-nonce".to_string()),
+        |f| f.expired = true,
+        |f| f.foreign_key = true,
+    ];
+
+    let (app, _pool, mock) = setup().await;
+    for (i, fault) in faults.iter().enumerate() {
+        mock.configure(|b| {
+            b.id_token = IdTokenFaults::default();
+            fault(&mut b.id_token);
+        });
+        let (uri, cookie, _) = start_login(&app).await;
+        let (status, _) = callback(&app, &uri, Some(&cookie)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "fault #{}", i);
+    }
+}
+
+#[tokio::test]
+async fn test_jwks_is_cached_and_refetched_after_rotation() {
+    let (app, _pool, mock) = setup().await;
+
+    for _ in 0..2 {
+        let (uri, cookie, _) = start_login(&app).await;
+        assert_eq!(callback(&app, &uri, Some(&cookie)).await.0, StatusCode::OK);
+    }
+    assert_eq!(mock.jwks_requests(), 1);
+
+    // new kid is unknown to the cache, so the set is fetched again
+    mock.rotate_key();
+    let (uri, cookie, _) = start_login(&app).await;
+    assert_eq!(callback(&app, &uri, Some(&cookie)).await.0, StatusCode::OK);
+    assert_eq!(mock.jwks_requests(), 2);
+}
+
+/// `alg` from a compact JWS header
+fn token_alg(token: &str) -> String {
+    let header = token.split('.').next().unwrap();
+    let header: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).unwrap()).unwrap();
+    header["alg"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_login_can_ask_for_post_quantum_tokens() {
+    let (app, _pool, _) = setup().await;
+
+    let (uri, cookie, _) = start_login(&app).await;
+    let (status, body) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(token_alg(body["token"].as_str().unwrap()), "EdDSA");
+
+    let (uri, cookie, _) = start_login_at(&app, "/auth/login/mock?pq=true").await;
+    let (status, body) = callback(&app, &uri, Some(&cookie)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(token_alg(body["token"].as_str().unwrap()), ML_DSA_65);
+}
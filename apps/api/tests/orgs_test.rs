@@ -0,0 +1,177 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::routes::auth::AuthState;
+use api::routes::oidc::JwksCache;
+use api::routes::orgs::{org_routes, DEFAULT_ORG_ID};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+
+struct Caller {
+    id: Uuid,
+    token: String,
+}
+
+async fn setup() -> (Router, TokenKeys, sqlx::PgPool) {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: keys.clone(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: Vec::new(),
+    };
+    let orgs = org_routes(state)
+        .layer(Extension(keys.clone()))
+        .layer(Extension(pool.clone()));
+    (app_routes(pool.clone(), keys.clone()).merge(orgs), keys, pool)
+}
+
+fn token_for(keys: &TokenKeys, user_id: Uuid, org: Option<Uuid>) -> String {
+    let now = chrono::Utc::now().timestamp();
+    keys.sign(&json!({
+        "sub": user_id.to_string(),
+        "exp": now + 3600,
+        "provider": "test",
+        "org": org,
+    }))
+    .unwrap()
+}
+
+/// A user in the default organization, with a token that doesn't name one
+async fn new_caller(keys: &TokenKeys, pool: &sqlx::PgPool, name: &str) -> Caller {
+    let email = format!("{}-{}@tidasone.com", name, Uuid::new_v4());
+    let user = db::queries::create_user(pool, &format!("{}-{}", name, Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+    Caller { id: user.id, token: token_for(keys, user.id, None) }
+}
+
+async fn new_org(pool: &sqlx::PgPool, owner: &Caller) -> Uuid {
+    let slug = format!("org-{}", Uuid::new_v4());
+    db::queries::create_organization(pool, &slug, "Test org", owner.id).await.unwrap().id
+}
+
+async fn call(app: &Router, method: Method, uri: &str, token: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_data_stays_inside_its_organization() {
+    let (app, keys, pool) = setup().await;
+    let alice = new_caller(&keys, &pool, "alice").await;
+    let bob = new_caller(&keys, &pool, "bob").await;
+    let acme = new_org(&pool, &alice).await;
+
+    // no org claim: alice works in her oldest organization, the default one
+    let (status, item) = call(&app, Method::POST, "/inventory", &alice.token, Some(json!({
+        "name": "Field radio",
+        "quantity": 1
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(item["org_id"], DEFAULT_ORG_ID.to_string());
+    let item_uri = format!("/inventory/{}", item["id"].as_str().unwrap());
+
+    let (status, tokens) = call(&app, Method::POST, &format!("/orgs/{}/switch", acme), &alice.token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let acme_token = tokens["token"].as_str().unwrap().to_string();
+    let claims = keys.verify::<Value>(&acme_token).unwrap().claims;
+    assert_eq!(claims["org"], acme.to_string());
+    assert_eq!(claims["org_role"], "owner");
+
+    // the default org's item doesn't exist from inside acme
+    let (status, _) = call(&app, Method::GET, &item_uri, &acme_token, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, list) = call(&app, Method::GET, "/inventory", &acme_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(list.as_array().unwrap().is_empty());
+    let (status, _) = call(&app, Method::GET, &item_uri, &alice.token, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // outsiders can't switch in, and a token naming the org gets them nowhere
+    let (status, _) = call(&app, Method::POST, &format!("/orgs/{}/switch", acme), &bob.token, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let forged = token_for(&keys, bob.id, Some(acme));
+    let (status, _) = call(&app, Method::GET, "/inventory", &forged, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (_, orgs) = call(&app, Method::GET, "/orgs", &alice.token, None).await;
+    let roles: HashMap<String, String> = orgs
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|org| (org["id"].as_str().unwrap().to_string(), org["role"].as_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(roles.get(&acme.to_string()).map(String::as_str), Some("owner"));
+    assert_eq!(roles.get(&DEFAULT_ORG_ID.to_string()).map(String::as_str), Some("member"));
+}
+
+#[tokio::test]
+async fn test_member_management_roles_and_last_owner() {
+    let (app, keys, pool) = setup().await;
+    let alice = new_caller(&keys, &pool, "alice").await;
+    let bob = new_caller(&keys, &pool, "bob").await;
+    let carol = new_caller(&keys, &pool, "carol").await;
+    let acme = new_org(&pool, &alice).await;
+    let member_uri = |caller: &Caller| format!("/orgs/{}/members/{}", acme, caller.id);
+
+    // outsiders can't see or change the member list
+    let (status, _) = call(&app, Method::GET, &format!("/orgs/{}/members", acme), &bob.token, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(&app, Method::PUT, &member_uri(&bob), &bob.token, Some(json!({ "role": "member" }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = call(&app, Method::PUT, &member_uri(&bob), &alice.token, Some(json!({ "role": "member" }))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // plain members don't manage anyone
+    let (status, _) = call(&app, Method::PUT, &member_uri(&carol), &bob.token, Some(json!({ "role": "member" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // admins manage members, but not other admins
+    let (status, _) = call(&app, Method::PUT, &member_uri(&bob), &alice.token, Some(json!({ "role": "admin" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::PUT, &member_uri(&carol), &bob.token, Some(json!({ "role": "member" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::PUT, &member_uri(&carol), &bob.token, Some(json!({ "role": "admin" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, Method::DELETE, &member_uri(&alice), &bob.token, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, Method::DELETE, &member_uri(&carol), &bob.token, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, members) = call(&app, Method::GET, &format!("/orgs/{}/members", acme), &bob.token, None).await;
+    assert_eq!(members.as_array().unwrap().len(), 2);
+
+    // the only owner can neither step down nor leave
+    let (status, _) = call(&app, Method::PUT, &member_uri(&alice), &alice.token, Some(json!({ "role": "admin" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = call(&app, Method::DELETE, &member_uri(&alice), &alice.token, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // once there's a second owner, she can
+    let (status, _) = call(&app, Method::PUT, &member_uri(&bob), &alice.token, Some(json!({ "role": "owner" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::DELETE, &member_uri(&alice), &alice.token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::POST, &format!("/orgs/{}/switch", acme), &alice.token, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,161 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+
+struct Caller {
+    id: Uuid,
+    token: String,
+}
+
+async fn setup() -> (Router, TokenKeys, sqlx::PgPool) {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    (app_routes(pool.clone(), keys.clone()), keys, pool)
+}
+
+async fn new_caller(keys: &TokenKeys, pool: &sqlx::PgPool, name: &str) -> Caller {
+    let email = format!("{}-{}@tidasone.com", name, Uuid::new_v4());
+    let user = db::queries::create_user(pool, &format!("{}-{}", name, Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+    let now = chrono::Utc::now().timestamp();
+    // freshly MFA-verified, so deletes get as far as the ownership checks
+    let token = keys
+        .sign(&json!({
+            "sub": user.id.to_string(),
+            "exp": now + 3600,
+            "provider": "test",
+            "amr": ["oauth", "otp", "mfa"],
+            "mfa_time": now
+        }))
+        .unwrap();
+    Caller { id: user.id, token }
+}
+
+async fn call(app: &Router, method: Method, uri: &str, caller: &Caller, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", caller.token))
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn ids(list: &Value) -> Vec<&str> {
+    list.as_array().unwrap().iter().map(|row| row["id"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn test_owner_comes_from_token_and_strangers_see_nothing() {
+    let (app, keys, pool) = setup().await;
+    let alice = new_caller(&keys, &pool, "alice").await;
+    let mallory = new_caller(&keys, &pool, "mallory").await;
+
+    // a body owner_id is ignored
+    let (status, item) = call(&app, Method::POST, "/inventory", &mallory, Some(json!({
+        "owner_id": alice.id,
+        "name": "Decoy",
+        "quantity": 1
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(item["owner_id"], mallory.id.to_string());
+
+    let (_, item) = call(&app, Method::POST, "/inventory", &alice, Some(json!({
+        "name": "Sat phone",
+        "quantity": 2
+    }))).await;
+    let uri = format!("/inventory/{}", item["id"].as_str().unwrap());
+
+    // same answer as for an id that doesn't exist
+    let missing = format!("/inventory/{}", Uuid::new_v4());
+    let (status, _) = call(&app, Method::GET, &missing, &mallory, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let update = json!({ "name": "Mine now", "quantity": 0 });
+    for (method, body) in [(Method::GET, None), (Method::PUT, Some(update)), (Method::DELETE, None)] {
+        let (status, _) = call(&app, method, &uri, &mallory, body).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+    let (status, _) = call(&app, Method::PUT, &format!("{}/shares/{}", uri, mallory.id), &mallory, Some(json!({}))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, list) = call(&app, Method::GET, "/inventory", &mallory, None).await;
+    assert!(!ids(&list).contains(&item["id"].as_str().unwrap()));
+    let (_, list) = call(&app, Method::GET, "/inventory", &alice, None).await;
+    assert_eq!(ids(&list), vec![item["id"].as_str().unwrap()]);
+
+    // packages can't point at inventory the caller can't see
+    let (status, _) = call(&app, Method::POST, "/packages", &mallory, Some(json!({
+        "inventory_item_id": item["id"],
+        "destination": "Dock 9"
+    }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_shares_grant_read_then_write() {
+    let (app, keys, pool) = setup().await;
+    let alice = new_caller(&keys, &pool, "alice").await;
+    let bob = new_caller(&keys, &pool, "bob").await;
+
+    let (status, pkg) = call(&app, Method::POST, "/packages", &alice, Some(json!({
+        "destination": "Forward base"
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/packages/{}", pkg["id"].as_str().unwrap());
+    let share_uri = format!("{}/shares/{}", uri, bob.id);
+    let update = json!({ "status": "Shipped", "destination": "Forward base" });
+
+    let (status, share) = call(&app, Method::PUT, &share_uri, &alice, Some(json!({}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(share["can_write"], false);
+
+    // read-only: visible, but changes are refused
+    let (status, _) = call(&app, Method::GET, &uri, &bob, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, list) = call(&app, Method::GET, "/packages", &bob, None).await;
+    assert_eq!(ids(&list), vec![pkg["id"].as_str().unwrap()]);
+    let (status, _) = call(&app, Method::PUT, &uri, &bob, Some(update.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // write: may edit, not delete or reshare
+    call(&app, Method::PUT, &share_uri, &alice, Some(json!({ "can_write": true }))).await;
+    let (status, updated) = call(&app, Method::PUT, &uri, &bob, Some(update)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["status"], "Shipped");
+    assert_eq!(updated["owner_id"], alice.id.to_string());
+    let (status, _) = call(&app, Method::DELETE, &uri, &bob, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, Method::GET, &format!("{}/shares", uri), &bob, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, shares) = call(&app, Method::GET, &format!("{}/shares", uri), &alice, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(shares[0]["user_id"], bob.id.to_string());
+
+    let (status, _) = call(&app, Method::PUT, &format!("{}/shares/{}", uri, alice.id), &alice, Some(json!({}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = call(&app, Method::PUT, &format!("{}/shares/{}", uri, Uuid::new_v4()), &alice, Some(json!({}))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // revoked: back to not existing
+    let (status, _) = call(&app, Method::DELETE, &share_uri, &alice, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::GET, &uri, &bob, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = call(&app, Method::DELETE, &uri, &alice, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
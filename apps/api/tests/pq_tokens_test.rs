@@ -0,0 +1,116 @@
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    middleware, Extension, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use api::commsec_routes;
+use api::routes::auth_middleware::require_post_quantum;
+use api::routes::commsec::init_commsec_state;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys, ML_DSA_65};
+
+fn keys() -> TokenKeys {
+    TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap()
+}
+
+fn claims(exp_in_secs: i64) -> Value {
+    let exp = chrono::Utc::now().timestamp() + exp_in_secs;
+    json!({ "sub": uuid::Uuid::new_v4().to_string(), "exp": exp, "provider": "test" })
+}
+
+fn header_of(token: &str) -> Value {
+    let header = token.split('.').next().unwrap();
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).unwrap()).unwrap()
+}
+
+/// CommSec wired the way main.rs does it
+fn commsec_app(keys: &TokenKeys) -> Router {
+    commsec_routes(init_commsec_state())
+        .route_layer(middleware::from_fn(require_post_quantum))
+        .layer(Extension(keys.clone()))
+}
+
+async fn alerts(app: &Router, bearer: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().uri("/commsec/alerts");
+    if let Some(token) = bearer {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_ml_dsa_tokens_round_trip() {
+    let keys = keys();
+    let token = keys.sign_post_quantum(&claims(3600)).unwrap();
+
+    let header = header_of(&token);
+    assert_eq!(header["alg"], ML_DSA_65);
+    let verified = keys.verify::<Value>(&token).unwrap();
+    assert!(verified.post_quantum);
+    assert_eq!(verified.claims["provider"], "test");
+
+    let classical = keys.sign(&claims(3600)).unwrap();
+    assert!(!keys.verify::<Value>(&classical).unwrap().post_quantum);
+
+    // ML-DSA keys have their own key set, so classical JWKS parsers keep working
+    let pq_jwks = keys.pq_jwks();
+    assert_eq!(pq_jwks["keys"][0]["kty"], "AKP");
+    assert_eq!(pq_jwks["keys"][0]["kid"], header["kid"]);
+    assert!(keys.jwks()["keys"].as_array().unwrap().iter().all(|key| key["kty"] != "AKP"));
+}
+
+#[tokio::test]
+async fn test_ml_dsa_tokens_reject_tampering_and_expiry() {
+    let keys = keys();
+    let token = keys.sign_post_quantum(&claims(3600)).unwrap();
+    let parts: Vec<&str> = token.split('.').collect();
+
+    // swapped payload
+    let forged_payload = URL_SAFE_NO_PAD.encode(claims(7200).to_string());
+    let forged = format!("{}.{}.{}", parts[0], forged_payload, parts[2]);
+    assert!(keys.verify::<Value>(&forged).is_err());
+
+    // ML-DSA header pointing at the classical key
+    let classical = keys.sign(&claims(3600)).unwrap();
+    let classical_kid = header_of(&classical)["kid"].clone();
+    let header = URL_SAFE_NO_PAD.encode(json!({ "alg": ML_DSA_65, "kid": classical_kid }).to_string());
+    let confused = format!("{}.{}.{}", header, parts[1], parts[2]);
+    assert!(keys.verify::<Value>(&confused).is_err());
+
+    let expired = keys.sign_post_quantum(&claims(-3600)).unwrap();
+    assert!(keys.verify::<Value>(&expired).is_err());
+
+    let stranger = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let foreign = stranger.sign_post_quantum(&claims(3600)).unwrap();
+    assert!(keys.verify::<Value>(&foreign).is_err());
+}
+
+#[tokio::test]
+async fn test_ml_dsa_keys_rotate_with_overlap() {
+    let keys = keys();
+    let before = keys.sign_post_quantum(&claims(3600)).unwrap();
+    keys.rotate().unwrap();
+    let after = keys.sign_post_quantum(&claims(3600)).unwrap();
+
+    assert_ne!(header_of(&before)["kid"], header_of(&after)["kid"]);
+    assert!(keys.verify::<Value>(&before).unwrap().post_quantum);
+    assert_eq!(keys.pq_jwks()["keys"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_commsec_requires_post_quantum_callers() {
+    let keys = keys();
+    let app = commsec_app(&keys);
+
+    assert_eq!(alerts(&app, None).await, StatusCode::UNAUTHORIZED);
+
+    let classical = keys.sign(&claims(3600)).unwrap();
+    assert_eq!(alerts(&app, Some(&classical)).await, StatusCode::FORBIDDEN);
+
+    let pq = keys.sign_post_quantum(&claims(3600)).unwrap();
+    assert_eq!(alerts(&app, Some(&pq)).await, StatusCode::OK);
+}
@@ -0,0 +1,79 @@
+use oauth2::url::Url;
+
+use api::mock_oauth::{MockProvider, MOCK_CLIENT_ID, MOCK_CLIENT_SECRET};
+use api::routes::providers::{load_providers, ProvidersConfig};
+
+#[tokio::test]
+async fn test_oidc_discovery_and_redirect_base() {
+    let mock = MockProvider::spawn("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let config = ProvidersConfig::from_toml(&format!(
+        r#"
+        redirect_base_url = "https://api.tidasone.example/"
+
+        [providers.corp]
+        issuer = "{}"
+        client_id = "{}"
+        client_secret = "{}"
+        "#,
+        mock.issuer(),
+        MOCK_CLIENT_ID,
+        MOCK_CLIENT_SECRET
+    ))
+    .unwrap();
+
+    let providers = load_providers(&config).await.unwrap();
+    let corp = &providers["corp"];
+
+    let oidc = corp.oidc.as_ref().unwrap();
+    assert_eq!(oidc.issuer, mock.issuer());
+    assert_eq!(oidc.jwks_uri, format!("{}/jwks", mock.issuer()));
+    assert_eq!(corp.userinfo_url.as_deref(), Some(mock.userinfo_url().as_str()));
+    assert_eq!(corp.scopes, ["openid", "email", "profile"]);
+
+    assert_eq!(corp.client.auth_url().url().as_str(), format!("{}/authorize", mock.issuer()));
+    let redirect = corp.client.redirect_url().unwrap().url();
+    assert_eq!(redirect, &Url::parse("https://api.tidasone.example/auth/callback/corp").unwrap());
+}
+
+#[tokio::test]
+async fn test_optional_providers_are_skipped_and_required_ones_fail() {
+    let config = ProvidersConfig::from_toml(
+        r#"
+        [providers.off]
+        enabled = false
+        auth_url = "https://off.example/authorize"
+        token_url = "https://off.example/token"
+        client_id = "id"
+        client_secret = "secret"
+
+        [providers.nocreds]
+        auth_url = "https://nocreds.example/authorize"
+        token_url = "https://nocreds.example/token"
+        client_id_env = "PROVIDERS_TEST_UNSET_CLIENT_ID"
+
+        [providers.unreachable]
+        issuer = "http://127.0.0.1:9"
+        client_id = "id"
+        client_secret = "secret"
+
+        [providers.plain]
+        auth_url = "https://plain.example/authorize"
+        token_url = "https://plain.example/token"
+        userinfo_url = "https://plain.example/user"
+        scopes = ["read:user"]
+        client_id = "id"
+        client_secret = "secret"
+        "#,
+    )
+    .unwrap();
+
+    let providers = load_providers(&config).await.unwrap();
+    assert_eq!(providers.keys().collect::<Vec<_>>(), ["plain"]);
+    assert!(providers["plain"].oidc.is_none());
+    assert_eq!(providers["plain"].scopes, ["read:user"]);
+
+    let mut config = config;
+    config.providers.get_mut("nocreds").unwrap().required = true;
+    let err = load_providers(&config).await.err().unwrap();
+    assert!(err.contains("PROVIDERS_TEST_UNSET_CLIENT_ID"));
+}
@@ -0,0 +1,200 @@
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Method, Request, StatusCode},
+    middleware, Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use api::routes::auth::AuthState;
+use api::routes::auth_middleware::require_auth;
+use api::routes::commsec::init_commsec_state;
+use api::routes::oidc::JwksCache;
+use api::routes::rate_limit::{
+    rate_limit_by_caller, rate_limit_by_ip, Budget, Decision, InMemoryStore, RateLimitPolicy, RateLimitStore, RateLimiter,
+};
+use api::routes::sessions::session_routes;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{commsec_routes, init_db_pool};
+
+/// Sign-in, CommSec and inventory routes behind the limiter, layered as in main.rs
+async fn setup(policy: RateLimitPolicy) -> (Router, TokenKeys) {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: keys.clone(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: Vec::new(),
+    };
+    let limiter = RateLimiter::in_memory(policy);
+    let app = session_routes(state)
+        .merge(commsec_routes(init_commsec_state()))
+        .merge(api::routes::inventory::inventory_routes())
+        .route_layer(middleware::from_fn_with_state(limiter.clone(), rate_limit_by_caller))
+        .route_layer(middleware::from_fn(require_auth))
+        .route_layer(middleware::from_fn_with_state(limiter, rate_limit_by_ip))
+        .layer(Extension(keys.clone()))
+        .layer(Extension(pool));
+    (app, keys)
+}
+
+fn token(keys: &TokenKeys, sub: &str) -> String {
+    let now = chrono::Utc::now().timestamp();
+    keys.sign(&json!({ "sub": sub, "exp": now + 3600, "provider": "test" })).unwrap()
+}
+
+async fn call(app: &Router, method: Method, uri: &str, ip: [u8; 4], bearer: Option<&str>) -> (StatusCode, Option<u64>) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .extension(ConnectInfo(SocketAddr::from((ip, 40000))));
+    if let Some(token) = bearer {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let body = Body::from(json!({ "refresh_token": "not-a-refresh-token" }).to_string());
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .map(|v| v.to_str().unwrap().parse().unwrap());
+    (response.status(), retry_after)
+}
+
+#[tokio::test]
+async fn test_failed_sign_ins_lock_out_the_caller() {
+    let (app, _) = setup(RateLimitPolicy {
+        login_failures: Budget::new(3, 1),
+        ..RateLimitPolicy::default()
+    })
+    .await;
+    let attacker = [10, 0, 0, 1];
+
+    for _ in 0..3 {
+        let (status, _) = call(&app, Method::POST, "/auth/refresh", attacker, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    // locked out: the next guess never reaches the handler
+    let (status, retry_after) = call(&app, Method::POST, "/auth/refresh", attacker, None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!((1..=60).contains(&retry_after.unwrap()));
+
+    // somebody else isn't
+    let (status, _) = call(&app, Method::POST, "/auth/refresh", [10, 0, 0, 2], None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_budgets_are_per_caller_and_per_class() {
+    let (app, keys) = setup(RateLimitPolicy {
+        api: Budget::new(2, 1),
+        crypto: Budget::new(1, 1),
+        ..RateLimitPolicy::default()
+    })
+    .await;
+    let (alice, bob) = (token(&keys, "alice@tidasone.com"), token(&keys, "bob@tidasone.com"));
+    let (home, cafe) = ([10, 0, 1, 1], [10, 0, 1, 2]);
+
+    for _ in 0..2 {
+        let (status, _) = call(&app, Method::GET, "/inventory", home, Some(&alice)).await;
+        assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+    // a user's budget follows them to another address
+    let (status, retry_after) = call(&app, Method::GET, "/inventory", cafe, Some(&alice)).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(retry_after.is_some());
+
+    // other users are charged separately, but an address's budget is shared by everyone behind it
+    let (status, _) = call(&app, Method::GET, "/inventory", cafe, Some(&bob)).await;
+    assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+    let (status, _) = call(&app, Method::GET, "/inventory", cafe, Some(&bob)).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // and KEM calls come out of their own budget
+    let (status, _) = call(&app, Method::POST, "/commsec/encapsulate", home, Some(&alice)).await;
+    assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+    let (status, _) = call(&app, Method::POST, "/commsec/encapsulate", home, Some(&alice)).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_unverified_callers_are_charged_by_address() {
+    let (app, keys) = setup(RateLimitPolicy {
+        api: Budget::new(2, 1),
+        ..RateLimitPolicy::default()
+    })
+    .await;
+    let ip = [10, 0, 2, 1];
+
+    // a token per request doesn't buy a fresh budget
+    for user in ["a", "b"] {
+        let (status, _) = call(&app, Method::GET, "/inventory", ip, Some(&token(&keys, user))).await;
+        assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+    let (status, _) = call(&app, Method::GET, "/inventory", ip, Some(&token(&keys, "c"))).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // rejected tokens cost as much as good ones
+    for _ in 0..2 {
+        let (status, _) = call(&app, Method::GET, "/inventory", [10, 0, 2, 2], Some("forged")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = call(&app, Method::GET, "/inventory", [10, 0, 2, 2], Some("forged")).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // callers without an address share one bucket instead of going unlimited
+    let mut statuses = Vec::new();
+    for _ in 0..3 {
+        let request = Request::builder().uri("/inventory").body(Body::empty()).unwrap();
+        statuses.push(app.clone().oneshot(request).await.unwrap().status());
+    }
+    assert_eq!(statuses, [StatusCode::UNAUTHORIZED, StatusCode::UNAUTHORIZED, StatusCode::TOO_MANY_REQUESTS]);
+}
+
+#[tokio::test]
+async fn test_buckets_refill_over_time() {
+    let store = InMemoryStore::default();
+    let budget = Budget::new(2, 600); // a token every 100ms
+
+    assert_eq!(store.take("k", budget, 2).await, Decision::Allowed);
+    let Decision::Limited { retry_after } = store.take("k", budget, 1).await else {
+        panic!("bucket should be empty");
+    };
+    assert!(retry_after <= Duration::from_millis(100));
+
+    // a cost of 0 only checks, and never drains
+    assert!(matches!(store.take("k", budget, 0).await, Decision::Limited { .. }));
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(store.take("k", budget, 0).await, Decision::Allowed);
+    assert_eq!(store.take("k", budget, 1).await, Decision::Allowed);
+    assert!(matches!(store.take("k", budget, 1).await, Decision::Limited { .. }));
+}
+
+#[tokio::test]
+async fn test_zero_budgets_are_refused_or_capped() {
+    // from the environment, zero falls back to the default...
+    std::env::set_var("RATE_LIMIT_CRYPTO_BURST", "0");
+    std::env::set_var("RATE_LIMIT_CRYPTO_PER_MIN", "0");
+    let policy = RateLimitPolicy::from_env();
+    std::env::remove_var("RATE_LIMIT_CRYPTO_BURST");
+    std::env::remove_var("RATE_LIMIT_CRYPTO_PER_MIN");
+    assert_eq!(policy.crypto, RateLimitPolicy::default().crypto);
+
+    // ...and one built in code that never refills still gets a finite Retry-After
+    let (app, keys) = setup(RateLimitPolicy {
+        api: Budget::new(0, 0),
+        ..RateLimitPolicy::default()
+    })
+    .await;
+    let (status, retry_after) = call(&app, Method::GET, "/inventory", [10, 0, 3, 1], Some(&token(&keys, "carol@tidasone.com"))).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(retry_after, Some(24 * 3600));
+}
@@ -0,0 +1,104 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Request, StatusCode},
+    Extension,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tower::ServiceExt;
+
+use api::init_db_pool;
+use api::routes::commsec::{init_commsec_state, CommsecState};
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::retention::{retention_routes, sweep_once, RetentionPolicy, RetentionState};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+
+#[tokio::test]
+async fn test_sweep_erases_expired_ciphertexts() {
+    let pool = init_db_pool().await;
+    let email = format!("ret-{}@tidasone.com", uuid::Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &format!("retention-{}", uuid::Uuid::new_v4()), &email).await.unwrap();
+    let mission = db::queries::create_mission(&pool, DEFAULT_ORG_ID, "Retention", None, None, 600).await.unwrap();
+
+    let stale = db::queries::create_task_update(&pool, mission.id, user.id, None, "bm9uY2U=", "c3RhbGU=", None)
+        .await
+        .unwrap();
+    let fresh = db::queries::create_task_update(&pool, mission.id, user.id, None, "bm9uY2U=", "ZnJlc2g=", None)
+        .await
+        .unwrap();
+
+    sqlx::query("UPDATE task_updates SET created_at = NOW() - INTERVAL '2 days' WHERE id = $1")
+        .bind(stale.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let state = RetentionState {
+        policy: RetentionPolicy {
+            ciphertext_ttl: Duration::from_secs(24 * 3600),
+            ..RetentionPolicy::default()
+        },
+        commsec: init_commsec_state(),
+    };
+
+    let report = sweep_once(&pool, &state).await.unwrap();
+    assert!(report.ciphertexts_erased >= 1);
+    assert!(!report.kem_key_rotated);
+
+    let remaining = db::queries::get_task_updates(&pool, mission.id).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, fresh.id);
+
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = retention_routes(state)
+        .layer(Extension(keys.clone()))
+        .layer(Extension(pool.clone()));
+    let get_report = |perms: &[&str]| {
+        let exp = chrono::Utc::now().timestamp() + 3600;
+        let token = keys.sign(&json!({ "sub": user.id.to_string(), "exp": exp, "provider": "test", "perms": perms })).unwrap();
+        app.clone().oneshot(
+            Request::builder()
+                .uri("/retention/report")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    // instance-wide numbers are for admins only
+    let anonymous = app
+        .clone()
+        .oneshot(Request::builder().uri("/retention/report").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(get_report(&["missions:read"]).await.unwrap().status(), StatusCode::FORBIDDEN);
+    let response = get_report(&["retention:read"]).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    let report: Value = serde_json::from_slice(&bytes).unwrap();
+    let classes: Vec<&str> = report["classes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["class"].as_str().unwrap())
+        .collect();
+    // and name no rows: expiry times only
+    let ciphertexts = &report["classes"][1]["next_expiries"];
+    assert!(ciphertexts.as_array().unwrap().iter().all(Value::is_string));
+    assert_eq!(classes, ["server_kem_key", "ciphertext", "issued_token"]);
+}
+
+#[tokio::test]
+async fn test_expired_kem_key_rotates() {
+    let state = CommsecState {
+        kem_ttl: chrono::Duration::zero(),
+        ..init_commsec_state()
+    };
+
+    let before = state.kem.read().unwrap().public_key.clone();
+    assert!(state.rotate_kem_if_expired());
+    assert_ne!(state.kem.read().unwrap().public_key, before);
+}
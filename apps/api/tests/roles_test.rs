@@ -0,0 +1,146 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::init_db_pool;
+use api::routes::auth::AuthState;
+use api::routes::missions::mission_routes;
+use api::routes::oidc::JwksCache;
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::roles::role_routes;
+use api::routes::sessions::{issue_tokens, session_routes, Session};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+
+/// Each test bootstraps its own admin, so tests running at once don't race to create one
+async fn setup() -> (Router, AuthState, sqlx::PgPool, String) {
+    let admin_email = format!("roles-admin-{}@tidasone.com", Uuid::new_v4());
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: vec![admin_email.clone()],
+    };
+    let pool = init_db_pool().await;
+    let app = role_routes()
+        .merge(session_routes(state.clone()))
+        .merge(mission_routes())
+        .layer(Extension(state.keys.clone()))
+        .layer(Extension(pool.clone()));
+    (app, state, pool, admin_email)
+}
+
+/// Log `email` in (creating the user if needed); returns (user id, access token)
+async fn login(state: &AuthState, pool: &sqlx::PgPool, email: &str) -> (Uuid, String) {
+    let user = match db::queries::get_user_by_email(pool, email).await.unwrap() {
+        Some(user) => user,
+        None => {
+            let user = db::queries::create_user(pool, &format!("roles-test-{}", Uuid::new_v4()), email).await.unwrap();
+            db::queries::set_org_member(pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+            user
+        }
+    };
+    let tokens = issue_tokens(state, pool, &user, "mock", Session::new(false)).await.unwrap();
+    (user.id, tokens.token)
+}
+
+async fn call(app: &Router, method: Method, uri: &str, bearer: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", bearer))
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn perms(state: &AuthState, token: &str) -> Vec<String> {
+    let claims = state.keys.verify::<Value>(token).unwrap().claims;
+    serde_json::from_value(claims["perms"].clone()).unwrap()
+}
+
+#[tokio::test]
+async fn test_bootstrap_admin_gets_permission_claims() {
+    let (app, state, pool, admin_email) = setup().await;
+    let (_, admin) = login(&state, &pool, &admin_email).await;
+    assert!(perms(&state, &admin).contains(&"roles:manage".to_string()));
+
+    let (status, roles) = call(&app, Method::GET, "/auth/admin/roles", &admin, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let names: Vec<&str> = roles.as_array().unwrap().iter().map(|r| r["name"].as_str().unwrap()).collect();
+    for builtin in ["admin", "operator", "vendor", "viewer"] {
+        assert!(names.contains(&builtin));
+    }
+
+    // no roles, no permissions
+    let (_, nobody) = login(&state, &pool, &format!("roles-{}@tidasone.com", Uuid::new_v4())).await;
+    assert!(perms(&state, &nobody).is_empty());
+    let (status, _) = call(&app, Method::GET, "/auth/admin/roles", &nobody, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, Method::GET, "/missions", &nobody, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_custom_role_grants_permissions_on_next_token() {
+    let (app, state, pool, admin_email) = setup().await;
+    let (_, admin) = login(&state, &pool, &admin_email).await;
+    let email = format!("roles-{}@tidasone.com", Uuid::new_v4());
+    let (user_id, before) = login(&state, &pool, &email).await;
+
+    let role = format!("planner-{}", &Uuid::new_v4().simple().to_string()[..8]);
+    let (status, _) = call(&app, Method::POST, "/auth/admin/roles", &admin, Some(json!({
+        "name": role,
+        "permissions": ["missions:read", "launch:nukes"]
+    }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, created) = call(&app, Method::POST, "/auth/admin/roles", &admin, Some(json!({
+        "name": role,
+        "description": "Plans missions",
+        "permissions": ["missions:read", "missions:write"]
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["builtin"], false);
+
+    let (status, _) = call(&app, Method::PUT, "/auth/admin/roles/viewer", &admin, Some(json!({
+        "permissions": ["missions:write"]
+    }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let uri = format!("/auth/admin/users/{}/roles/{}", user_id, role);
+    let (status, grants) = call(&app, Method::PUT, &uri, &admin, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(grants[0]["role"], role);
+
+    // the old token predates the grant
+    let mission = json!({ "name": "Quiet Harbor" });
+    let (status, _) = call(&app, Method::POST, "/missions", &before, Some(mission.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (_, after) = login(&state, &pool, &email).await;
+    let (status, _) = call(&app, Method::POST, "/missions", &after, Some(mission)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, grants) = call(&app, Method::DELETE, &uri, &admin, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(grants.as_array().unwrap().is_empty());
+    let (status, _) = call(&app, Method::DELETE, &uri, &admin, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = call(&app, Method::DELETE, &format!("/auth/admin/roles/{}", role), &admin, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::DELETE, "/auth/admin/roles/admin", &admin, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
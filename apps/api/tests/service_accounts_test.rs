@@ -0,0 +1,207 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::service_accounts::{generate_api_key, service_account_routes};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+use db::models::AuditFilter;
+
+async fn setup() -> (Router, TokenKeys, sqlx::PgPool) {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = app_routes(pool.clone(), keys.clone()).merge(
+        service_account_routes()
+            .layer(Extension(keys.clone()))
+            .layer(Extension(pool.clone())),
+    );
+    (app, keys, pool)
+}
+
+/// An admin-ish human whose token carries `perms`
+async fn admin_token(keys: &TokenKeys, pool: &sqlx::PgPool, perms: &[&str]) -> String {
+    let email = format!("svc-admin-{}@tidasone.com", Uuid::new_v4());
+    let user = db::queries::create_user(pool, &format!("svc-admin-{}", Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(pool, DEFAULT_ORG_ID, user.id, "admin").await.unwrap();
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    keys.sign(&json!({ "sub": user.id.to_string(), "exp": exp, "provider": "test", "perms": perms })).unwrap()
+}
+
+async fn call(app: &Router, method: Method, uri: &str, bearer: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", bearer))
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn account_name() -> String {
+    format!("scanner-{}", &Uuid::new_v4().simple().to_string()[..8])
+}
+
+#[tokio::test]
+async fn test_api_key_authenticates_with_its_scopes() {
+    let (app, keys, pool) = setup().await;
+    let admin = admin_token(&keys, &pool, &["service_accounts:manage", "inventory:read", "inventory:write"]).await;
+
+    let (status, account) = call(&app, Method::POST, "/auth/admin/service-accounts", &admin, Some(json!({
+        "name": account_name(),
+        "description": "Dock 3 barcode scanner"
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let keys_uri = format!("/auth/admin/service-accounts/{}/keys", account["id"].as_str().unwrap());
+
+    // can't hand out what the admin doesn't hold
+    let (status, _) = call(&app, Method::POST, &keys_uri, &admin, Some(json!({ "scopes": ["missions:write"] }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, Method::POST, &keys_uri, &admin, Some(json!({ "scopes": ["dock:open"] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, issued) = call(&app, Method::POST, &keys_uri, &admin, Some(json!({
+        "scopes": ["inventory:read", "inventory:write"],
+        "expires_in_days": 30
+    }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let api_key = issued["key"].as_str().unwrap();
+    assert!(api_key.starts_with(issued["prefix"].as_str().unwrap()));
+    assert!(issued.get("key_hash").is_none());
+
+    // the account owns what it creates
+    let (status, item) = call(&app, Method::POST, "/inventory", api_key, Some(json!({ "name": "Pallet", "quantity": 4 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(item["owner_id"], account["id"]);
+
+    // scopes are permissions
+    let (status, _) = call(&app, Method::GET, "/missions", api_key, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (_, listed) = call(&app, Method::GET, &keys_uri, &admin, None).await;
+    assert!(listed[0]["last_used_at"].is_string());
+    assert!(listed[0].get("key").is_none());
+
+    let revoke_uri = format!("{}/{}", keys_uri, issued["id"].as_str().unwrap());
+    let (status, _) = call(&app, Method::DELETE, &revoke_uri, &admin, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::GET, "/inventory", api_key, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_expired_keys_and_disabled_accounts_are_rejected() {
+    let (app, keys, pool) = setup().await;
+    let admin = admin_token(&keys, &pool, &["service_accounts:manage"]).await;
+    let account = db::queries::create_service_account(&pool, DEFAULT_ORG_ID, &account_name(), "", None).await.unwrap();
+    let scopes = vec!["inventory:read".to_string()];
+
+    let expired = generate_api_key();
+    let yesterday = chrono::Utc::now() - chrono::Duration::days(1);
+    db::queries::create_api_key(&pool, account.id, &expired.prefix, &expired.hash, &scopes, Some(yesterday))
+        .await
+        .unwrap();
+    let (status, _) = call(&app, Method::GET, "/inventory", &expired.key, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let live = generate_api_key();
+    db::queries::create_api_key(&pool, account.id, &live.prefix, &live.hash, &scopes, None).await.unwrap();
+    let (status, _) = call(&app, Method::GET, "/inventory", &live.key, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // a tampered secret with a real prefix
+    let tampered = format!("{}_{}", live.prefix, "x".repeat(43));
+    let (status, _) = call(&app, Method::GET, "/inventory", &tampered, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, disabled) = call(&app, Method::DELETE, &format!("/auth/admin/service-accounts/{}", account.id), &admin, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(disabled["disabled_at"].is_string());
+    let (status, _) = call(&app, Method::GET, "/inventory", &live.key, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_accounts_of_other_organizations_are_not_found() {
+    let (app, keys, pool) = setup().await;
+    let account = db::queries::create_service_account(&pool, DEFAULT_ORG_ID, &account_name(), "", None).await.unwrap();
+    let key = generate_api_key();
+    let scopes = vec!["inventory:read".to_string()];
+    let api_key = db::queries::create_api_key(&pool, account.id, &key.prefix, &key.hash, &scopes, None).await.unwrap();
+
+    // an admin of some other organization
+    let owner = db::queries::create_user(&pool, &account_name(), &format!("{}@tidasone.com", account_name())).await.unwrap();
+    let other_org = db::queries::create_organization(&pool, &account_name(), "Other org", owner.id).await.unwrap().id;
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let outsider = keys
+        .sign(&json!({ "sub": owner.id.to_string(), "exp": exp, "provider": "test", "perms": ["service_accounts:manage", "inventory:read"], "org": other_org }))
+        .unwrap();
+
+    let (status, listed) = call(&app, Method::GET, "/auth/admin/service-accounts", &outsider, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!listed.as_array().unwrap().iter().any(|a| a["id"] == account.id.to_string()));
+
+    let uri = format!("/auth/admin/service-accounts/{}", account.id);
+    for (method, uri, body) in [
+        (Method::GET, uri.clone(), None),
+        (Method::GET, format!("{}/keys", uri), None),
+        (Method::POST, format!("{}/keys", uri), Some(json!({ "scopes": ["inventory:read"] }))),
+        (Method::DELETE, format!("{}/keys/{}", uri, api_key.id), None),
+        (Method::DELETE, uri.clone(), None),
+    ] {
+        let (status, _) = call(&app, method.clone(), &uri, &outsider, body).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{} {}", method, uri);
+    }
+
+    // untouched: still enabled, its key still works
+    let (status, _) = call(&app, Method::GET, "/inventory", &key.key, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(db::queries::get_api_keys(&pool, account.id).await.unwrap().len(), 1);
+}
+
+/// Run the service_account CLI as OS user `ops`
+fn cli(args: &[&str]) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_service_account"))
+        .args(args)
+        .env("USER", "ops")
+        .output()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cli_checks_expiry_and_audits_as_its_user() {
+    let pool = init_db_pool().await;
+    let name = account_name();
+    assert!(cli(&["create", &name]).status.success());
+    let account = db::queries::get_service_account_by_name(&pool, &name).await.unwrap().unwrap();
+
+    // same rule as expires_in_days over HTTP
+    for days in ["0", "-3"] {
+        let output = cli(&["issue-key", &name, "inventory:read", "--expires-days", days]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("expires_in_days must be positive"));
+    }
+    assert!(cli(&["issue-key", &name, "inventory:read", "--expires-days", "7"]).status.success());
+    let key = &db::queries::get_api_keys(&pool, account.id).await.unwrap()[0];
+    assert!(cli(&["revoke-key", &key.prefix]).status.success());
+    assert!(cli(&["disable", &name]).status.success());
+
+    let filter = AuditFilter { target_id: Some(account.id.to_string()), ..AuditFilter::default() };
+    let events = db::queries::get_audit_events(&pool, &filter).await.unwrap();
+    let trail: Vec<(&str, &str)> = events.iter().rev().map(|e| (e.actor.as_str(), e.action.as_str())).collect();
+    assert_eq!(trail, [
+        ("cli:ops", "service_account.create"),
+        ("cli:ops", "api_key.create"),
+        ("cli:ops", "api_key.revoke"),
+        ("cli:ops", "service_account.disable"),
+    ]);
+}
@@ -0,0 +1,181 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::init_db_pool;
+use api::routes::auth::AuthState;
+use api::routes::identities::identity_routes;
+use api::routes::oidc::JwksCache;
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::sessions::{issue_tokens, session_routes, AuthResponse, Session};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+
+const ADMIN_EMAIL: &str = "ops-admin@tidasone.com";
+
+async fn setup() -> (Router, AuthState, sqlx::PgPool) {
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: vec![ADMIN_EMAIL.to_string()],
+    };
+    let pool = init_db_pool().await;
+    let app = session_routes(state.clone())
+        .merge(identity_routes())
+        .layer(Extension(state.keys.clone()))
+        .layer(Extension(pool.clone()));
+    (app, state, pool)
+}
+
+async fn login(state: &AuthState, pool: &sqlx::PgPool, email: &str) -> AuthResponse {
+    let user = match db::queries::get_user_by_email(pool, email).await.unwrap() {
+        Some(user) => user,
+        None => db::queries::create_user(pool, &format!("session-test-{}", Uuid::new_v4()), email).await.unwrap(),
+    };
+    issue_tokens(state, pool, &user, "mock", Session::new(false)).await.unwrap()
+}
+
+fn fresh_email() -> String {
+    format!("session-{}@tidasone.com", Uuid::new_v4())
+}
+
+async fn call(app: &Router, method: Method, uri: &str, bearer: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = bearer {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let body = match body {
+        Some(json) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+async fn refresh(app: &Router, refresh_token: &str) -> (StatusCode, Value) {
+    call(app, Method::POST, "/auth/refresh", None, Some(json!({ "refresh_token": refresh_token }))).await
+}
+
+#[tokio::test]
+async fn test_refresh_rotates_and_reuse_revokes_session() {
+    let (app, state, pool) = setup().await;
+    let first = login(&state, &pool, &fresh_email()).await;
+
+    let (status, second) = refresh(&app, &first.refresh_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let second_refresh = second["refresh_token"].as_str().unwrap();
+    let second_token = second["token"].as_str().unwrap();
+    assert_ne!(second_refresh, first.refresh_token);
+
+    let (status, _) = call(&app, Method::GET, "/auth/identities", Some(second_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // replaying the rotated token looks like theft: the whole session dies
+    let (status, _) = refresh(&app, &first.refresh_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&app, second_refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&app, Method::GET, "/auth/identities", Some(second_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = refresh(&app, "not-a-refresh-token").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_me_reports_what_the_token_grants() {
+    let (app, state, pool) = setup().await;
+    let email = fresh_email();
+    let user = db::queries::create_user(&pool, &format!("session-test-{}", Uuid::new_v4()), &email).await.unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+    let session = login(&state, &pool, &email).await;
+
+    let (status, me) = call(&app, Method::GET, "/auth/me", Some(&session.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["sub"], user.id.to_string());
+    assert_eq!((me["email"].as_str(), me["provider"].as_str()), (Some(email.as_str()), Some("mock")));
+    assert!(me["roles"].is_array() && me["perms"].is_array());
+    assert_eq!(me["org_role"], "member");
+    assert_eq!(me["org"], DEFAULT_ORG_ID.to_string());
+    assert_eq!(me["post_quantum"], false);
+    let expires_at: chrono::DateTime<chrono::Utc> = me["expires_at"].as_str().unwrap().parse().unwrap();
+    assert!(expires_at > chrono::Utc::now());
+
+    let (status, _) = call(&app, Method::GET, "/auth/me", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_logout_revokes_access_and_refresh_tokens() {
+    let (app, state, pool) = setup().await;
+    let session = login(&state, &pool, &fresh_email()).await;
+
+    let (status, _) = call(&app, Method::POST, "/auth/logout", Some(&session.token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = call(&app, Method::GET, "/auth/identities", Some(&session.token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&app, &session.refresh_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_admin_revokes_all_sessions_of_a_user() {
+    let (app, state, pool) = setup().await;
+    let admin = login(&state, &pool, ADMIN_EMAIL).await;
+
+    let email = fresh_email();
+    let laptop = login(&state, &pool, &email).await;
+    let user = db::queries::get_user_by_email(&pool, &email).await.unwrap().unwrap();
+    let phone = issue_tokens(&state, &pool, &user, "mock", Session::new(false)).await.unwrap();
+
+    let uri = format!("/auth/admin/users/{}/revoke-sessions", user.id);
+    let (status, _) = call(&app, Method::POST, &uri, Some(&laptop.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, report) = call(&app, Method::POST, &uri, Some(&admin.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["refresh_tokens_revoked"], 2);
+    assert_eq!(report["access_tokens_revoked"], 2);
+
+    for session in [&laptop, &phone] {
+        let (status, _) = call(&app, Method::GET, "/auth/identities", Some(&session.token), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = refresh(&app, &session.refresh_token).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn test_post_quantum_sessions_stay_post_quantum() {
+    let (app, state, pool) = setup().await;
+    let username = format!("session-test-{}", Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &username, &fresh_email()).await.unwrap();
+    let session = issue_tokens(&state, &pool, &user, "mock", Session::new(true)).await.unwrap();
+    assert!(state.keys.verify::<Value>(&session.token).unwrap().post_quantum);
+
+    let (status, refreshed) = refresh(&app, &session.refresh_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let token = refreshed["token"].as_str().unwrap();
+    assert!(state.keys.verify::<Value>(token).unwrap().post_quantum);
+
+    // tracked and revocable like any other access token
+    let (status, _) = call(&app, Method::POST, "/auth/logout", Some(token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = call(&app, Method::GET, "/auth/identities", Some(token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,160 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::Algorithm;
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tower::ServiceExt;
+
+use api::init_db_pool;
+use api::routes::auth::AuthState;
+use api::routes::identities::identity_routes;
+use api::routes::oidc::JwksCache;
+use api::routes::sessions::{issue_tokens, Session};
+use api::routes::solana::{base58_decode, base58_encode, solana_routes, verify_signature};
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+
+async fn setup() -> (Router, AuthState, sqlx::PgPool) {
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: Vec::new(),
+    };
+    let pool = init_db_pool().await;
+    let app = solana_routes(state.clone())
+        .merge(identity_routes())
+        .layer(Extension(state.keys.clone()))
+        .layer(Extension(pool.clone()));
+    (app, state, pool)
+}
+
+async fn call(app: &Router, method: Method, uri: &str, bearer: Option<&str>, body: Value) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(token) = bearer {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let response = app.clone().oneshot(request.body(Body::from(body.to_string())).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// A throwaway wallet and its base58 address
+fn wallet() -> (Ed25519KeyPair, String) {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let address = base58_encode(pair.public_key().as_ref());
+    (pair, address)
+}
+
+fn sign(pair: &Ed25519KeyPair, challenge: &Value) -> Value {
+    let message = challenge["message"].as_str().unwrap();
+    json!({
+        "nonce": challenge["nonce"],
+        "signature": base58_encode(pair.sign(message.as_bytes()).as_ref()),
+    })
+}
+
+#[test]
+fn test_base58_round_trip_and_signatures() {
+    // leading zero bytes become leading '1's
+    assert_eq!(base58_encode(&[0, 0, 1]), "112");
+    assert_eq!(base58_encode(b"hello world"), "StV1DL6CwTryKyV");
+    assert_eq!(base58_decode("StV1DL6CwTryKyV").unwrap(), b"hello world");
+    assert_eq!(base58_decode("1111").unwrap(), vec![0; 4]);
+    assert!(base58_decode("0OIl").is_none());
+
+    let (pair, address) = wallet();
+    let signature = base58_encode(pair.sign(b"gm").as_ref());
+    assert!(verify_signature(&address, b"gm", &signature));
+    assert!(!verify_signature(&address, b"gn", &signature));
+    assert!(!verify_signature(&wallet().1, b"gm", &signature));
+    assert!(!verify_signature(&address, b"gm", "not-base58"));
+}
+
+#[tokio::test]
+async fn test_wallet_login_is_single_use_and_reuses_user() {
+    let (app, state, _pool) = setup().await;
+    let (pair, address) = wallet();
+
+    let (status, _) = call(&app, Method::POST, "/auth/solana/challenge", None, json!({ "address": "0xnope" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, challenge) = call(&app, Method::POST, "/auth/solana/challenge", None, json!({ "address": address })).await;
+    assert_eq!(status, StatusCode::OK);
+    let message = challenge["message"].as_str().unwrap();
+    assert!(message.contains(&address));
+    assert!(message.contains(&format!("Nonce: {}", challenge["nonce"].as_str().unwrap())));
+
+    let signed = sign(&pair, &challenge);
+    let (status, tokens) = call(&app, Method::POST, "/auth/solana/verify", None, signed.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let claims = state.keys.verify::<Value>(tokens["token"].as_str().unwrap()).unwrap().claims;
+    assert_eq!(claims["provider"], "solana");
+    assert_eq!(claims["amr"], json!(["wallet"]));
+    let user_id = claims["sub"].as_str().unwrap().to_string();
+
+    // the nonce was consumed
+    let (status, _) = call(&app, Method::POST, "/auth/solana/verify", None, signed).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // a signature from another wallet doesn't count, and burns the challenge
+    let (_, challenge) = call(&app, Method::POST, "/auth/solana/challenge", None, json!({ "address": address })).await;
+    let (impostor, _) = wallet();
+    let (status, _) = call(&app, Method::POST, "/auth/solana/verify", None, sign(&impostor, &challenge)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&app, Method::POST, "/auth/solana/verify", None, sign(&pair, &challenge)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // the same wallet logs into the same user
+    let (_, challenge) = call(&app, Method::POST, "/auth/solana/challenge", None, json!({ "address": address })).await;
+    let (status, tokens) = call(&app, Method::POST, "/auth/solana/verify", None, sign(&pair, &challenge)).await;
+    assert_eq!(status, StatusCode::OK);
+    let claims = state.keys.verify::<Value>(tokens["token"].as_str().unwrap()).unwrap().claims;
+    assert_eq!(claims["sub"], user_id);
+}
+
+#[tokio::test]
+async fn test_link_wallet_to_existing_user() {
+    let (app, state, pool) = setup().await;
+    let email = format!("{}@tidasone.com", uuid::Uuid::new_v4());
+    let username = format!("wallet-owner-{}", uuid::Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &username, &email).await.unwrap();
+    let session = issue_tokens(&state, &pool, &user, "test", Session::new(false)).await.unwrap();
+    let (pair, address) = wallet();
+
+    let (status, _) = call(&app, Method::POST, "/auth/solana/link", None, json!({ "address": address })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, challenge) = call(&app, Method::POST, "/auth/solana/link", Some(&session.token), json!({ "address": address })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, tokens) = call(&app, Method::POST, "/auth/solana/verify", None, sign(&pair, &challenge)).await;
+    assert_eq!(status, StatusCode::OK);
+    let claims = state.keys.verify::<Value>(tokens["token"].as_str().unwrap()).unwrap().claims;
+    assert_eq!(claims["sub"], user.id.to_string());
+
+    let (_, identities) = call(&app, Method::GET, "/auth/identities", Some(&session.token), Value::Null).await;
+    let wallets: Vec<&Value> = identities.as_array().unwrap().iter().filter(|i| i["provider"] == "solana").collect();
+    assert_eq!(wallets.len(), 1);
+    assert_eq!(wallets[0]["provider_subject"], address);
+
+    // a wallet belongs to one user
+    let other_name = format!("someone-else-{}", uuid::Uuid::new_v4());
+    let other = db::queries::create_user(&pool, &other_name, &format!("{}@tidasone.com", other_name))
+        .await
+        .unwrap();
+    let other_session = issue_tokens(&state, &pool, &other, "test", Session::new(false)).await.unwrap();
+    let (_, challenge) = call(&app, Method::POST, "/auth/solana/link", Some(&other_session.token), json!({ "address": address })).await;
+    let (status, _) = call(&app, Method::POST, "/auth/solana/verify", None, sign(&pair, &challenge)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
@@ -0,0 +1,150 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Request, StatusCode},
+    Extension, Router,
+};
+use jsonwebtoken::{decode, decode_header, encode, jwk::JwkSet, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use api::init_db_pool;
+use api::routes::auth::{auth_routes, AuthState};
+use api::routes::identities::identity_routes;
+use api::routes::oidc::JwksCache;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+
+fn claims() -> Value {
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    json!({ "sub": uuid::Uuid::new_v4().to_string(), "exp": exp, "provider": "test" })
+}
+
+async fn app(keys: &TokenKeys) -> Router {
+    let state = AuthState {
+        providers: HashMap::new(),
+        keys: keys.clone(),
+        token_ttl_secs: 3600,
+        refresh_ttl_secs: 3600,
+        jwks: JwksCache::default(),
+        admin_emails: Vec::new(),
+    };
+    auth_routes(state)
+        .merge(identity_routes())
+        .layer(Extension(keys.clone()))
+        .layer(Extension(init_db_pool().await))
+}
+
+async fn get(app: &Router, uri: &str, bearer: Option<&str>) -> (StatusCode, Value) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = bearer {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_jwks_verifies_tokens_without_secret() {
+    for alg in [Algorithm::EdDSA, Algorithm::ES256] {
+        let keys = TokenKeys::ephemeral(alg, KeyRotationPolicy::default()).unwrap();
+        let app = app(&keys).await;
+        let token = keys.sign(&claims()).unwrap();
+
+        // another service: only the published key set
+        let (status, body) = get(&app, "/.well-known/jwks.json", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let jwks: JwkSet = serde_json::from_value(body).unwrap();
+
+        let kid = decode_header(&token).unwrap().kid.unwrap();
+        assert_eq!(kid, keys.active_kid());
+        let jwk = jwks.find(&kid).expect("signing key is published");
+        let decoded = decode::<Value>(&token, &DecodingKey::from_jwk(jwk).unwrap(), &Validation::new(alg)).unwrap();
+        assert_eq!(decoded.claims["provider"], "test");
+
+        // and our own extractor
+        let (status, _) = get(&app, "/auth/identities", Some(&token)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn test_rotation_keeps_old_key_during_overlap() {
+    let keys = TokenKeys::ephemeral(
+        Algorithm::EdDSA,
+        KeyRotationPolicy {
+            rotate_after: Duration::from_secs(3600),
+            overlap: Duration::from_secs(1),
+        },
+    )
+    .unwrap();
+    let old_kid = keys.active_kid();
+    let old_token = keys.sign(&claims()).unwrap();
+
+    let new_kid = keys.rotate().unwrap();
+    assert_ne!(old_kid, new_kid);
+    let new_token = keys.sign(&claims()).unwrap();
+    assert_eq!(decode_header(&new_token).unwrap().kid.unwrap(), new_kid);
+
+    // both keys verify and are published while the overlap lasts
+    assert!(keys.verify::<Value>(&old_token).is_ok());
+    assert!(keys.verify::<Value>(&new_token).is_ok());
+    assert_eq!(keys.jwks()["keys"].as_array().unwrap().len(), 2);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(!keys.rotate_if_due().unwrap());
+
+    assert!(keys.verify::<Value>(&old_token).is_err());
+    assert!(keys.verify::<Value>(&new_token).is_ok());
+    assert_eq!(keys.jwks()["keys"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_rotation_is_checked_at_least_as_often_as_it_is_due() {
+    let every = |secs| {
+        let policy = KeyRotationPolicy { rotate_after: Duration::from_secs(secs), overlap: Duration::from_secs(1) };
+        TokenKeys::ephemeral(Algorithm::EdDSA, policy).unwrap().rotation_check_interval()
+    };
+    assert_eq!(every(600), Duration::from_secs(600));
+    assert_eq!(every(30 * 24 * 3600), Duration::from_secs(3600));
+    assert_eq!(every(0), Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn test_rejects_hs256_and_unknown_keys() {
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = app(&keys).await;
+
+    // the old shared-secret tokens no longer work, even claiming our kid
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some(keys.active_kid());
+    let forged = encode(&header, &claims(), &EncodingKey::from_secret(b"old-shared-secret")).unwrap();
+    let (status, _) = get(&app, "/auth/identities", Some(&forged)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // signed by a key we never issued
+    let stranger = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let foreign = stranger.sign(&claims()).unwrap();
+    let (status, _) = get(&app, "/auth/identities", Some(&foreign)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_keys_persist_across_restarts() {
+    let dir = std::env::temp_dir().join(format!("jwt-keys-{}", uuid::Uuid::new_v4()));
+    let keys = TokenKeys::load_or_create(&dir, Algorithm::ES256, KeyRotationPolicy::default()).unwrap();
+    let token = keys.sign(&claims()).unwrap();
+
+    let restarted = TokenKeys::load_or_create(&dir, Algorithm::ES256, KeyRotationPolicy::default()).unwrap();
+    assert_eq!(restarted.active_kid(), keys.active_kid());
+    assert!(restarted.verify::<Value>(&token).is_ok());
+
+    // switching algorithm retires the old key instead of dropping it
+    let switched = TokenKeys::load_or_create(&dir, Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    assert_ne!(switched.active_kid(), keys.active_kid());
+    assert!(switched.verify::<Value>(&token).is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,57 +1,95 @@
 use axum::{
     body::{self, Body},
-    http::{Request, StatusCode},
+    http::{Method, Request, StatusCode},
+    Router,
 };
+use serde_json::{json, Value};
 use tower::ServiceExt;
-use sqlx::Executor;
+use uuid::Uuid;
 
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
 use api::{app_routes, init_db_pool}; // 👈 reuse helpers from lib.rs
 
-async fn setup_test_db() -> sqlx::PgPool {
-    let pool = init_db_pool().await;
-    // clean state before running the test
-    pool.execute("TRUNCATE users CASCADE").await.unwrap();
-    pool
+fn unique(prefix: &str) -> String {
+    format!("{}-{}", prefix, Uuid::new_v4())
+}
+
+/// A member of `org_id` holding users:read and users:write there
+async fn member_token(pool: &sqlx::PgPool, keys: &TokenKeys, org_id: Uuid) -> String {
+    let username = unique("user-admin");
+    let user = db::queries::create_user(pool, &username, &format!("{}@tidasone.com", username)).await.unwrap();
+    db::queries::set_org_member(pool, org_id, user.id, "admin").await.unwrap();
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let perms = ["users:read", "users:write"];
+    keys.sign(&json!({ "sub": user.id.to_string(), "exp": exp, "provider": "test", "perms": perms, "org": org_id }))
+        .unwrap()
+}
+
+async fn send(app: &Router, method: Method, uri: &str, token: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {}", token));
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 1 << 20).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
 }
 
 #[tokio::test]
 async fn test_create_and_list_users() {
-    let pool = setup_test_db().await;
-    let app = app_routes(pool.clone());
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(jsonwebtoken::Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let token = member_token(&pool, &keys, DEFAULT_ORG_ID).await;
+    let app = app_routes(pool.clone(), keys);
 
     // create a user
-    let response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/users")
-                .header("Content-Type", "application/json")
-                .body(Body::from(r#"{"username":"bob","email":"bob@tidasone.com"}"#))
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
+    let email = format!("{}@tidasone.com", unique("bob"));
+    let (status, _) = send(&app, Method::POST, "/users", &token, Some(json!({ "username": unique("bob"), "email": email }))).await;
+    assert_eq!(status, StatusCode::OK);
 
     // list users
-    let response = app
-        .oneshot(
-            Request::builder()
-                .method("GET")
-                .uri("/users")
-                .body(Body::empty())
-                .unwrap(),
-        )
+    let (status, users) = send(&app, Method::GET, "/users", &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(users.as_array().unwrap().iter().any(|user| user["email"] == email));
+}
+
+#[tokio::test]
+async fn test_users_of_other_organizations_are_not_found() {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(jsonwebtoken::Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let app = app_routes(pool.clone(), keys.clone());
+
+    let founder = db::queries::create_user(&pool, &unique("founder"), &format!("{}@tidasone.com", unique("founder")))
         .await
         .unwrap();
+    let other_org = db::queries::create_organization(&pool, &unique("other"), "Other org", founder.id).await.unwrap().id;
+    let (ours, theirs) = (member_token(&pool, &keys, DEFAULT_ORG_ID).await, member_token(&pool, &keys, other_org).await);
 
-    assert_eq!(response.status(), StatusCode::OK);
+    let email = format!("{}@tidasone.com", unique("carol"));
+    let (status, carol) = send(&app, Method::POST, "/users", &theirs, Some(json!({ "username": unique("carol"), "email": email }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/users/{}", carol["id"].as_str().unwrap());
 
-    let body_bytes = body::to_bytes(response.into_body(), 65_536).await.unwrap();
-    let body_str = String::from_utf8(body_bytes.to_vec()).unwrap();
+    // a member of the default org can't list, read, change or delete her
+    let (_, listed) = send(&app, Method::GET, "/users", &ours, None).await;
+    assert!(!listed.as_array().unwrap().iter().any(|user| user["email"] == email));
+    let replacement = json!({ "username": unique("mallory"), "email": format!("{}@tidasone.com", unique("mallory")) });
+    for (method, body) in [
+        (Method::GET, None),
+        (Method::PUT, Some(replacement.clone())),
+        (Method::PATCH, Some(json!({ "identity_hash": "x" }))),
+        (Method::DELETE, None),
+    ] {
+        let (status, _) = send(&app, method.clone(), &uri, &ours, body).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{} {}", method, uri);
+    }
 
-    assert!(body_str.contains("bob@tidasone.com"));
+    // her own organization still has her, unchanged
+    let (status, found) = send(&app, Method::GET, &uri, &theirs, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["email"], email);
 }
-
@@ -0,0 +1,149 @@
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::routes::error::ApiError;
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+use db::models::{InventoryFields, PackageFields};
+
+async fn setup() -> (Router, String, sqlx::PgPool) {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let username = format!("validator-{}", Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &username, &format!("{}@tidasone.com", username))
+        .await
+        .unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let perms = ["users:read", "users:write"];
+    let token = keys
+        .sign(&json!({ "sub": user.id.to_string(), "exp": exp, "provider": "test", "perms": perms }))
+        .unwrap();
+    (app_routes(pool.clone(), keys), token, pool)
+}
+
+async fn send(app: &Router, method: Method, uri: &str, token: &str, body: &str) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), 1 << 20).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// POST `payload` and return the fields reported as invalid
+async fn rejected_fields(app: &Router, uri: &str, token: &str, payload: Value) -> Vec<String> {
+    let (status, problem) = send(app, Method::POST, uri, token, &payload.to_string()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{} {}", uri, payload);
+    assert_eq!(problem["code"], "validation_failed");
+    problem["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_user_rules() {
+    let (app, token, _) = setup().await;
+    let email = format!("valid-{}@tidasone.com", Uuid::new_v4());
+
+    assert_eq!(rejected_fields(&app, "/users", &token, json!({ "username": "  ", "email": email })).await, ["username"]);
+    assert_eq!(rejected_fields(&app, "/users", &token, json!({ "username": "x".repeat(65), "email": email })).await, ["username"]);
+    assert_eq!(rejected_fields(&app, "/users", &token, json!({ "username": "ok", "email": "not-an-email" })).await, ["email"]);
+    // every broken rule is reported, not just the first
+    assert_eq!(rejected_fields(&app, "/users", &token, json!({ "username": "", "email": "" })).await, ["email", "username"]);
+
+    let username = format!("valid-{}", Uuid::new_v4());
+    let (status, _) = send(&app, Method::POST, "/users", &token, &json!({ "username": username, "email": email }).to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    // the same address or username in other capitals is still taken
+    let shouted = json!({ "username": format!("copy-{}", Uuid::new_v4()), "email": email.to_uppercase() }).to_string();
+    let (status, problem) = send(&app, Method::POST, "/users", &token, &shouted).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["detail"], "Email is already in use");
+    let shouted = json!({ "username": username.to_uppercase(), "email": format!("copy-{}@tidasone.com", Uuid::new_v4()) });
+    let (status, problem) = send(&app, Method::POST, "/users", &token, &shouted.to_string()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["detail"], "Username is already in use");
+}
+
+#[tokio::test]
+async fn test_inventory_rules() {
+    let (app, token, _) = setup().await;
+
+    assert_eq!(rejected_fields(&app, "/inventory", &token, json!({ "name": "Valve", "quantity": -1 })).await, ["quantity"]);
+    assert_eq!(rejected_fields(&app, "/inventory", &token, json!({ "name": "", "quantity": 1 })).await, ["name"]);
+    let long = json!({ "name": "Valve", "quantity": 1, "description": "x".repeat(2001), "location": "y".repeat(201) });
+    assert_eq!(rejected_fields(&app, "/inventory", &token, long).await, ["description", "location"]);
+
+    // updates are held to the same rules
+    let (status, item) = send(&app, Method::POST, "/inventory", &token, r#"{"name": "Valve", "quantity": 0}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/inventory/{}", item["id"].as_str().unwrap());
+    let (status, problem) = send(&app, Method::PUT, &uri, &token, r#"{"name": "Valve", "quantity": -5}"#).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(problem["errors"][0]["message"], "must not be negative");
+
+    // bodies that aren't the payload at all never reach the rules
+    let (status, problem) = send(&app, Method::POST, "/inventory", &token, "{not json").await;
+    assert_eq!((status, problem["code"].as_str()), (StatusCode::BAD_REQUEST, Some("bad_request")));
+    let (status, _) = send(&app, Method::POST, "/inventory", &token, r#"{"name": "Valve"}"#).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_package_rules() {
+    let (app, token, _) = setup().await;
+
+    let fields = rejected_fields(&app, "/packages", &token, json!({ "status": "Lost in space", "destination": "Mars" })).await;
+    assert_eq!(fields, ["status"]);
+    assert_eq!(rejected_fields(&app, "/packages", &token, json!({ "destination": " " })).await, ["destination"]);
+
+    for status in ["Pending", "In Transit", "Delivered"] {
+        let payload = json!({ "status": status, "destination": "Mars" }).to_string();
+        let (code, package) = send(&app, Method::POST, "/packages", &token, &payload).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(package["status"], status);
+    }
+}
+
+#[tokio::test]
+async fn test_database_enforces_the_same_rules() {
+    let (_, _, pool) = setup().await;
+    let owner_name = format!("db-rules-{}", Uuid::new_v4());
+    let owner = db::queries::create_user(&pool, &owner_name, &format!("{}@tidasone.com", owner_name))
+        .await
+        .unwrap();
+    let status = |result: Result<_, sqlx::Error>| ApiError::from(result.err().unwrap()).status();
+
+    let negative = InventoryFields { name: "Valve", description: None, quantity: -1, location: None, token_id: None };
+    let result = db::queries::create_inventory(&pool, DEFAULT_ORG_ID, owner.id, negative).await;
+    assert_eq!(status(result.map(|_| ())), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let unknown = PackageFields { inventory_item_id: None, status: "Lost", destination: "Mars", nft_token: None };
+    let result = db::queries::create_package(&pool, DEFAULT_ORG_ID, owner.id, unknown).await;
+    assert_eq!(status(result.map(|_| ())), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let result = db::queries::create_user(&pool, "", &format!("blank-{}@tidasone.com", Uuid::new_v4())).await;
+    assert_eq!(status(result.map(|_| ())), StatusCode::UNPROCESSABLE_ENTITY);
+    let result = db::queries::create_user(&pool, &format!("dup-{}", Uuid::new_v4()), &owner.email.to_uppercase()).await;
+    assert_eq!(status(result.map(|_| ())), StatusCode::CONFLICT);
+    let result = db::queries::create_user(&pool, &owner.username.to_uppercase(), &format!("dup-{}@tidasone.com", Uuid::new_v4())).await;
+    assert_eq!(status(result.map(|_| ())), StatusCode::CONFLICT);
+}
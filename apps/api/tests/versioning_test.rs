@@ -0,0 +1,173 @@
+use axum::{
+    body::{self, Body},
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    Router,
+};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use api::routes::orgs::DEFAULT_ORG_ID;
+use api::routes::token_keys::{KeyRotationPolicy, TokenKeys};
+use api::{app_routes, init_db_pool};
+
+async fn setup() -> (Router, String, sqlx::PgPool) {
+    let pool = init_db_pool().await;
+    let keys = TokenKeys::ephemeral(Algorithm::EdDSA, KeyRotationPolicy::default()).unwrap();
+    let username = format!("versioner-{}", Uuid::new_v4());
+    let user = db::queries::create_user(&pool, &username, &format!("{}@tidasone.com", username))
+        .await
+        .unwrap();
+    db::queries::set_org_member(&pool, DEFAULT_ORG_ID, user.id, "member").await.unwrap();
+
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let perms = ["users:read", "users:write"];
+    let token = keys
+        .sign(&json!({ "sub": user.id.to_string(), "exp": exp, "provider": "test", "perms": perms }))
+        .unwrap();
+    (app_routes(pool.clone(), keys), token, pool)
+}
+
+struct Reply {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Value,
+}
+
+impl Reply {
+    fn etag(&self) -> &str {
+        self.headers.get(header::ETAG).unwrap().to_str().unwrap()
+    }
+}
+
+async fn send(app: &Router, method: Method, uri: &str, token: &str, headers: &[(&str, &str)], body: Option<Value>) -> Reply {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/merge-patch+json");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let body = body.map(|json| Body::from(json.to_string())).unwrap_or_else(Body::empty);
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = body::to_bytes(response.into_body(), 1 << 20).await.unwrap();
+    Reply { status, headers, body: serde_json::from_slice(&bytes).unwrap_or(Value::Null) }
+}
+
+#[tokio::test]
+async fn test_patch_only_touches_named_fields() {
+    let (app, token, _) = setup().await;
+    let item = json!({ "name": "Valve", "description": "Brass", "quantity": 3, "location": "Bay 4" });
+    let created = send(&app, Method::POST, "/inventory", &token, &[], Some(item)).await;
+    assert_eq!(created.status, StatusCode::OK);
+    assert_eq!(created.body["version"], 1);
+    let uri = format!("/inventory/{}", created.body["id"].as_str().unwrap());
+
+    let patched = send(&app, Method::PATCH, &uri, &token, &[], Some(json!({ "quantity": 7, "location": null }))).await;
+    assert_eq!(patched.status, StatusCode::OK);
+    assert_eq!(patched.body["quantity"], 7);
+    assert_eq!(patched.body["location"], Value::Null);
+    // everything the patch didn't mention is kept
+    assert_eq!((patched.body["name"].as_str(), patched.body["description"].as_str()), (Some("Valve"), Some("Brass")));
+    assert_eq!(patched.body["version"], 2);
+    assert_eq!(patched.etag(), "\"2\"");
+
+    // the patched result is held to the same rules as a full update
+    let invalid = send(&app, Method::PATCH, &uri, &token, &[], Some(json!({ "quantity": -1 }))).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(invalid.body["errors"][0]["field"], "quantity");
+    let not_object = send(&app, Method::PATCH, &uri, &token, &[], Some(json!([1, 2]))).await;
+    assert_eq!(not_object.status, StatusCode::BAD_REQUEST);
+
+    let package = json!({ "destination": "Mars", "nft_token": "nft-1" });
+    let created = send(&app, Method::POST, "/packages", &token, &[], Some(package)).await;
+    let uri = format!("/packages/{}", created.body["id"].as_str().unwrap());
+    let patched = send(&app, Method::PATCH, &uri, &token, &[], Some(json!({ "status": "Shipped" }))).await;
+    assert_eq!(patched.status, StatusCode::OK);
+    assert_eq!((patched.body["status"].as_str(), patched.body["nft_token"].as_str()), (Some("Shipped"), Some("nft-1")));
+}
+
+#[tokio::test]
+async fn test_if_match_rejects_stale_writes() {
+    let (app, token, _) = setup().await;
+    let email = format!("stale-{}@tidasone.com", Uuid::new_v4());
+    let (renamed, clobber) = (format!("renamed-{}", Uuid::new_v4()), format!("clobber-{}", Uuid::new_v4()));
+    let created = send(&app, Method::POST, "/users", &token, &[], Some(json!({ "username": format!("stale-{}", Uuid::new_v4()), "email": email }))).await;
+    assert_eq!(created.status, StatusCode::OK);
+    let uri = format!("/users/{}", created.body["id"].as_str().unwrap());
+    let first = created.etag().to_string();
+
+    // two clients read version 1; the first write wins
+    let rename = Some(json!({ "username": renamed }));
+    let won = send(&app, Method::PATCH, &uri, &token, &[("if-match", &first)], rename).await;
+    assert_eq!(won.status, StatusCode::OK);
+    assert_ne!(won.etag(), first);
+
+    let full = json!({ "username": clobber, "email": email });
+    let lost = send(&app, Method::PUT, &uri, &token, &[("if-match", &first)], Some(full.clone())).await;
+    assert_eq!(lost.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(lost.body["code"], "precondition_failed");
+    let current = send(&app, Method::GET, &uri, &token, &[], None).await;
+    assert_eq!(current.body["username"], renamed);
+
+    // retried against the version it now knows about, it goes through
+    let retried = send(&app, Method::PUT, &uri, &token, &[("if-match", won.etag())], Some(full)).await;
+    assert_eq!(retried.status, StatusCode::OK);
+    assert_eq!(retried.body["username"], clobber);
+
+    // a garbled tag matches nothing rather than making the write unconditional
+    let garbled = send(&app, Method::PATCH, &uri, &token, &[("if-match", "no quotes")], Some(json!({}))).await;
+    assert_eq!(garbled.status, StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn test_writes_that_lose_a_race_fail() {
+    let (app, token, pool) = setup().await;
+    let created = send(&app, Method::POST, "/inventory", &token, &[], Some(json!({ "name": "Valve", "quantity": 1 }))).await;
+    let id: Uuid = created.body["id"].as_str().unwrap().parse().unwrap();
+
+    // a write that doesn't go through the API still bumps the version...
+    let bumped = db::queries::update_inventory_quantity(&pool, DEFAULT_ORG_ID, id, 5).await.unwrap().unwrap();
+    assert_eq!(bumped.version, 2);
+    assert!(bumped.updated_at > bumped.created_at);
+
+    // ...so an update computed from the version before it goes nowhere
+    let fields = db::models::InventoryFields { name: "Valve", description: None, quantity: 9, location: None, token_id: None };
+    let stale = db::queries::update_inventory(&pool, DEFAULT_ORG_ID, id, 1, fields).await.unwrap();
+    assert!(stale.is_none());
+
+    let uri = format!("/inventory/{}", id);
+    let reply = send(&app, Method::PATCH, &uri, &token, &[("if-match", created.etag())], Some(json!({ "quantity": 9 }))).await;
+    assert_eq!(reply.status, StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn test_conditional_get() {
+    let (app, token, _) = setup().await;
+    let created = send(&app, Method::POST, "/packages", &token, &[], Some(json!({ "destination": "Mars" }))).await;
+    let uri = format!("/packages/{}", created.body["id"].as_str().unwrap());
+
+    let fetched = send(&app, Method::GET, &uri, &token, &[], None).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    let etag = fetched.etag().to_string();
+    let last_modified = fetched.headers.get(header::LAST_MODIFIED).unwrap().to_str().unwrap().to_string();
+
+    let cached = send(&app, Method::GET, &uri, &token, &[("if-none-match", &etag)], None).await;
+    assert_eq!(cached.status, StatusCode::NOT_MODIFIED);
+    assert_eq!(cached.etag(), etag);
+    assert_eq!(cached.body, Value::Null);
+    let cached = send(&app, Method::GET, &uri, &token, &[("if-modified-since", &last_modified)], None).await;
+    assert_eq!(cached.status, StatusCode::NOT_MODIFIED);
+
+    send(&app, Method::PATCH, &uri, &token, &[], Some(json!({ "status": "Delivered" }))).await;
+    let changed = send(&app, Method::GET, &uri, &token, &[("if-none-match", &etag)], None).await;
+    assert_eq!(changed.status, StatusCode::OK);
+    assert_eq!(changed.body["status"], "Delivered");
+    // If-None-Match decides when both are sent, even within the same second
+    let changed = send(&app, Method::GET, &uri, &token, &[("if-none-match", &etag), ("if-modified-since", &last_modified)], None).await;
+    assert_eq!(changed.status, StatusCode::OK);
+}
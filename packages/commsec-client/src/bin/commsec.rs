@@ -0,0 +1,392 @@
+//! `commsec` — operator CLI for CommSec keys, envelopes and signatures.
+//!
+//! Every command prints one JSON object on stdout. Failures print
+//! `{"error": "..."}` on stderr and exit with status 2; `verify` exits
+//! with status 1 when the signature doesn't check out.
+
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use commsec_client::{crypto, types::*, CommsecClient};
+
+#[derive(Parser)]
+#[command(name = "commsec", version, about = "TIDasONE CommSec operator tool")]
+struct Cli {
+    /// Pretty-print JSON output
+    #[arg(long, global = true)]
+    pretty: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate an ML-KEM-1024 keypair (base64 files)
+    KemKeygen {
+        #[arg(long)]
+        public_out: PathBuf,
+        #[arg(long)]
+        secret_out: PathBuf,
+    },
+    /// Generate an ML-DSA-65 signing keypair (base64 files)
+    DsaKeygen {
+        #[arg(long)]
+        public_out: PathBuf,
+        #[arg(long)]
+        secret_out: PathBuf,
+    },
+    /// Encapsulate a fresh shared secret to a KEM public key
+    Encapsulate {
+        #[arg(long)]
+        public_key: PathBuf,
+        #[arg(long)]
+        ciphertext_out: Option<PathBuf>,
+        /// Also write the shared secret here (usable as `--key` for seal/open)
+        #[arg(long)]
+        shared_secret_out: Option<PathBuf>,
+    },
+    /// Recover the shared secret from a KEM ciphertext
+    Decapsulate {
+        #[arg(long)]
+        secret_key: PathBuf,
+        #[arg(long)]
+        ciphertext: PathBuf,
+        #[arg(long)]
+        shared_secret_out: Option<PathBuf>,
+    },
+    /// AES-256-GCM encrypt a file; output is nonce || ciphertext
+    Seal {
+        #[arg(long)]
+        key: PathBuf,
+        #[arg(long = "in")]
+        input: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        ad: Option<String>,
+    },
+    /// Decrypt a file produced by `seal`
+    Open {
+        #[arg(long)]
+        key: PathBuf,
+        #[arg(long = "in")]
+        input: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        ad: Option<String>,
+    },
+    /// Detached ML-DSA-65 signature over a file
+    Sign {
+        #[arg(long)]
+        secret_key: PathBuf,
+        #[arg(long = "in")]
+        input: PathBuf,
+        #[arg(long)]
+        signature_out: Option<PathBuf>,
+    },
+    /// Check a detached signature
+    Verify {
+        #[arg(long)]
+        public_key: PathBuf,
+        #[arg(long = "in")]
+        input: PathBuf,
+        #[arg(long)]
+        signature: PathBuf,
+    },
+    /// Call a running CommSec API
+    Api {
+        #[arg(long, env = "COMMSEC_API_URL", default_value = "http://127.0.0.1:3000")]
+        url: String,
+        #[arg(long)]
+        client_id: Option<String>,
+        #[arg(long, env = "COMMSEC_TOKEN")]
+        token: Option<String>,
+
+        #[command(subcommand)]
+        op: ApiCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApiCommand {
+    /// Fetch the server's KEM keypair
+    Keypair,
+    Encapsulate {
+        #[arg(long)]
+        public_key: PathBuf,
+    },
+    Decapsulate {
+        #[arg(long)]
+        secret_key: PathBuf,
+        #[arg(long)]
+        ciphertext: PathBuf,
+    },
+    Encrypt {
+        #[arg(long)]
+        key: PathBuf,
+        /// Base64 nonce; random if omitted
+        #[arg(long)]
+        nonce: Option<String>,
+        #[arg(long)]
+        plaintext: String,
+        #[arg(long)]
+        ad: Option<String>,
+    },
+    Decrypt {
+        #[arg(long)]
+        key: PathBuf,
+        #[arg(long)]
+        nonce: String,
+        #[arg(long)]
+        ciphertext: String,
+        #[arg(long)]
+        ad: Option<String>,
+    },
+    /// List traffic monitor alerts
+    Alerts {
+        #[arg(long)]
+        since: Option<u64>,
+    },
+}
+
+type CliResult = Result<Value, String>;
+
+/// Read a base64 text file (e.g. `kem_pubkey.txt`) into bytes
+fn read_b64(path: &Path) -> Result<Vec<u8>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    crypto::b64_decode(text.trim()).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn read_b64_string(path: &Path) -> Result<String, String> {
+    read_b64(path).map(crypto::b64_encode)
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_bytes(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Secret material is written owner-read/write only. The mode is set when
+/// the file is opened, and again before writing in case it already existed,
+/// so the secret is never readable by anyone else.
+fn write_secret_b64(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    use std::io::Write;
+    options
+        .open(path)
+        .and_then(|mut file| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+            }
+            file.write_all(crypto::b64_encode(bytes).as_bytes())
+        })
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_public_b64(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    write_bytes(path, crypto::b64_encode(bytes).as_bytes())
+}
+
+/// A shared secret sent to a file stays out of stdout (and the shell's
+/// scrollback); without `--shared-secret-out` stdout is where it goes
+fn report_shared_secret(output: &mut Value, path: Option<&Path>, secret: &[u8]) -> Result<(), String> {
+    match path {
+        Some(path) => {
+            write_secret_b64(path, secret)?;
+            output["shared_secret_file"] = json!(path);
+        }
+        None => output["shared_secret"] = json!(crypto::b64_encode(secret)),
+    }
+    Ok(())
+}
+
+fn run_local(command: Command) -> CliResult {
+    match command {
+        Command::KemKeygen { public_out, secret_out } => {
+            let (pk, sk) = crypto::generate_keypair();
+            write_public_b64(&public_out, &pk)?;
+            write_secret_b64(&secret_out, &sk)?;
+            Ok(json!({
+                "algorithm": "ML-KEM-1024",
+                "public_key_file": public_out,
+                "secret_key_file": secret_out,
+            }))
+        }
+        Command::DsaKeygen { public_out, secret_out } => {
+            let (pk, sk) = crypto::generate_signing_keypair();
+            write_public_b64(&public_out, &pk)?;
+            write_secret_b64(&secret_out, &sk)?;
+            Ok(json!({
+                "algorithm": "ML-DSA-65",
+                "public_key_file": public_out,
+                "secret_key_file": secret_out,
+            }))
+        }
+        Command::Encapsulate { public_key, ciphertext_out, shared_secret_out } => {
+            let pk = read_b64(&public_key)?;
+            let (ct, ss) = crypto::encapsulate(&pk).map_err(|e| e.to_string())?;
+            if let Some(path) = &ciphertext_out {
+                write_public_b64(path, &ct)?;
+            }
+            let mut output = json!({ "ciphertext": crypto::b64_encode(&ct) });
+            report_shared_secret(&mut output, shared_secret_out.as_deref(), &ss)?;
+            Ok(output)
+        }
+        Command::Decapsulate { secret_key, ciphertext, shared_secret_out } => {
+            let sk = read_b64(&secret_key)?;
+            let ct = read_b64(&ciphertext)?;
+            let ss = crypto::decapsulate(&sk, &ct).map_err(|e| e.to_string())?;
+            let mut output = json!({});
+            report_shared_secret(&mut output, shared_secret_out.as_deref(), &ss)?;
+            Ok(output)
+        }
+        Command::Seal { key, input, out, ad } => {
+            let key = read_b64(&key)?;
+            let plaintext = read_bytes(&input)?;
+            let nonce = crypto::random_nonce();
+            let ct = crypto::seal(&key, &nonce, &plaintext, ad.as_deref().map(str::as_bytes))
+                .map_err(|e| e.to_string())?;
+
+            let mut sealed = nonce.to_vec();
+            sealed.extend_from_slice(&ct);
+            write_bytes(&out, &sealed)?;
+
+            Ok(json!({
+                "out": out,
+                "nonce": crypto::b64_encode(nonce),
+                "bytes": sealed.len(),
+            }))
+        }
+        Command::Open { key, input, out, ad } => {
+            let key = read_b64(&key)?;
+            let sealed = read_bytes(&input)?;
+            if sealed.len() < crypto::NONCE_LEN {
+                return Err("sealed file too short".to_string());
+            }
+            let (nonce, ct) = sealed.split_at(crypto::NONCE_LEN);
+            let plaintext = crypto::open(&key, nonce, ct, ad.as_deref().map(str::as_bytes))
+                .map_err(|e| e.to_string())?;
+            write_bytes(&out, &plaintext)?;
+
+            Ok(json!({ "out": out, "bytes": plaintext.len() }))
+        }
+        Command::Sign { secret_key, input, signature_out } => {
+            let sk = read_b64(&secret_key)?;
+            let message = read_bytes(&input)?;
+            let sig = crypto::sign(&sk, &message).map_err(|e| e.to_string())?;
+            if let Some(path) = &signature_out {
+                write_public_b64(path, &sig)?;
+            }
+            Ok(json!({ "algorithm": "ML-DSA-65", "signature": crypto::b64_encode(&sig) }))
+        }
+        Command::Verify { public_key, input, signature } => {
+            let pk = read_b64(&public_key)?;
+            let message = read_bytes(&input)?;
+            let sig = read_b64(&signature)?;
+            let valid = crypto::verify(&pk, &message, &sig).map_err(|e| e.to_string())?;
+            Ok(json!({ "valid": valid }))
+        }
+        Command::Api { .. } => unreachable!("handled by run_api"),
+    }
+}
+
+async fn run_api(client: CommsecClient, op: ApiCommand) -> CliResult {
+    fn to_value<T: serde::Serialize>(res: commsec_client::error::Result<T>) -> CliResult {
+        res.map_err(|e| e.to_string())
+            .and_then(|v| serde_json::to_value(v).map_err(|e| e.to_string()))
+    }
+
+    match op {
+        ApiCommand::Keypair => to_value(client.keypair().await),
+        ApiCommand::Encapsulate { public_key } => {
+            let req = EncapsulateRequest { public_key: read_b64_string(&public_key)? };
+            to_value(client.encapsulate(&req).await)
+        }
+        ApiCommand::Decapsulate { secret_key, ciphertext } => {
+            let req = DecapsulateRequest {
+                secret_key: read_b64_string(&secret_key)?,
+                ciphertext: read_b64_string(&ciphertext)?,
+            };
+            to_value(client.decapsulate(&req).await)
+        }
+        ApiCommand::Encrypt { key, nonce, plaintext, ad } => {
+            let nonce = nonce.unwrap_or_else(|| crypto::b64_encode(crypto::random_nonce()));
+            let req = AeadEncryptRequest {
+                key: read_b64_string(&key)?,
+                nonce: nonce.clone(),
+                plaintext,
+                associated_data: ad,
+            };
+            let res = client.aead_encrypt(&req).await.map_err(|e| e.to_string())?;
+            Ok(json!({ "nonce": nonce, "ciphertext": res.ciphertext }))
+        }
+        ApiCommand::Decrypt { key, nonce, ciphertext, ad } => {
+            let req = AeadDecryptRequest {
+                key: read_b64_string(&key)?,
+                nonce,
+                ciphertext,
+                associated_data: ad,
+            };
+            to_value(client.aead_decrypt(&req).await)
+        }
+        ApiCommand::Alerts { since } => to_value(client.alerts(since).await),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let is_verify = matches!(cli.command, Command::Verify { .. });
+
+    let result = match cli.command {
+        Command::Api { url, client_id, token, op } => {
+            let mut client = CommsecClient::new(url);
+            if let Some(id) = client_id {
+                client = client.with_client_id(id);
+            }
+            if let Some(token) = token {
+                client = client.with_bearer_token(token);
+            }
+            run_api(client, op).await
+        }
+        command => run_local(command),
+    };
+
+    let render = |v: &Value| {
+        if cli.pretty {
+            serde_json::to_string_pretty(v).unwrap()
+        } else {
+            v.to_string()
+        }
+    };
+
+    match result {
+        Ok(value) => {
+            println!("{}", render(&value));
+            if is_verify && value["valid"] == false {
+                ExitCode::from(1)
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", render(&json!({ "error": err })));
+            ExitCode::from(2)
+        }
+    }
+}
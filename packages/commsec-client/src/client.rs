@@ -0,0 +1,98 @@
+use reqwest::header::AUTHORIZATION;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{Error, Result};
+use crate::types::*;
+
+/// Header the server's traffic monitor uses to tell clients apart
+pub const CLIENT_ID_HEADER: &str = "x-client-id";
+
+/// Async client for a running CommSec API
+#[derive(Clone)]
+pub struct CommsecClient {
+    base_url: String,
+    http: reqwest::Client,
+    client_id: Option<String>,
+    bearer_token: Option<String>,
+}
+
+impl CommsecClient {
+    /// `base_url` is the API root, e.g. `http://127.0.0.1:3000`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            client_id: None,
+            bearer_token: None,
+        }
+    }
+
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut req = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(id) = &self.client_id {
+            req = req.header(CLIENT_ID_HEADER, id);
+        }
+        if let Some(token) = &self.bearer_token {
+            req = req.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        req
+    }
+
+    async fn send<T: DeserializeOwned>(&self, req: reqwest::RequestBuilder) -> Result<T> {
+        let res = req.send().await?;
+        let status = res.status();
+        if !status.is_success() {
+            let message = res.text().await.unwrap_or_default();
+            return Err(Error::Server { status: status.as_u16(), message });
+        }
+        Ok(res.json().await?)
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        self.send(self.request(reqwest::Method::POST, path).json(body)).await
+    }
+
+    /// The server's ML-KEM keypair
+    pub async fn keypair(&self) -> Result<KeypairResponse> {
+        self.send(self.request(reqwest::Method::POST, "/commsec/keypair")).await
+    }
+
+    pub async fn encapsulate(&self, req: &EncapsulateRequest) -> Result<EncapsulateResponse> {
+        self.post("/commsec/encapsulate", req).await
+    }
+
+    pub async fn decapsulate(&self, req: &DecapsulateRequest) -> Result<DecapsulateResponse> {
+        self.post("/commsec/decapsulate", req).await
+    }
+
+    pub async fn aead_encrypt(&self, req: &AeadEncryptRequest) -> Result<AeadEncryptResponse> {
+        self.post("/commsec/aead/encrypt", req).await
+    }
+
+    pub async fn aead_decrypt(&self, req: &AeadDecryptRequest) -> Result<AeadDecryptResponse> {
+        self.post("/commsec/aead/decrypt", req).await
+    }
+
+    /// Monitor alerts newer than `since`
+    pub async fn alerts(&self, since: Option<u64>) -> Result<AlertsResponse> {
+        let mut req = self.request(reqwest::Method::GET, "/commsec/alerts");
+        if let Some(since) = since {
+            req = req.query(&[("since", since)]);
+        }
+        self.send(req).await
+    }
+}
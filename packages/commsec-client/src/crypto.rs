@@ -0,0 +1,108 @@
+//! Local equivalents of the server's CommSec operations, for clients that
+//! want to keep secrets off the wire or check the server's answers.
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+
+use pqcrypto_mlkem::mlkem1024::{
+    keypair as kem_keypair, encapsulate as pq_encapsulate, decapsulate as pq_decapsulate,
+    PublicKey, SecretKey, Ciphertext,
+};
+use pqcrypto_traits::kem::{
+    PublicKey as PKTrait, SecretKey as SKTrait, Ciphertext as CTTrait, SharedSecret as SSTrait,
+};
+
+use pqcrypto_mldsa::mldsa65;
+use pqcrypto_traits::sign::{
+    PublicKey as SignPKTrait, SecretKey as SignSKTrait, DetachedSignature as SigTrait,
+};
+
+use crate::error::{Error, Result};
+
+pub const NONCE_LEN: usize = 12;
+pub const KEY_LEN: usize = 32;
+
+pub fn b64_encode(bytes: impl AsRef<[u8]>) -> String {
+    general_purpose::STANDARD.encode(bytes)
+}
+
+pub fn b64_decode(value: &str) -> Result<Vec<u8>> {
+    Ok(general_purpose::STANDARD.decode(value)?)
+}
+
+/// Fresh ML-KEM-1024 keypair as `(public_key, secret_key)` bytes
+pub fn generate_keypair() -> (Vec<u8>, Vec<u8>) {
+    let (pk, sk) = kem_keypair();
+    (pk.as_bytes().to_vec(), sk.as_bytes().to_vec())
+}
+
+/// Encapsulate to `public_key`, returning `(ciphertext, shared_secret)`
+pub fn encapsulate(public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let pk = PublicKey::from_bytes(public_key).map_err(|_| Error::Crypto("invalid public key"))?;
+    let (ss, ct) = pq_encapsulate(&pk);
+    Ok((ct.as_bytes().to_vec(), ss.as_bytes().to_vec()))
+}
+
+pub fn decapsulate(secret_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let sk = SecretKey::from_bytes(secret_key).map_err(|_| Error::Crypto("invalid secret key"))?;
+    let ct = Ciphertext::from_bytes(ciphertext).map_err(|_| Error::Crypto("invalid ciphertext"))?;
+    Ok(pq_decapsulate(&ct, &sk).as_bytes().to_vec())
+}
+
+/// Fresh ML-DSA-65 signing keypair as `(public_key, secret_key)` bytes
+pub fn generate_signing_keypair() -> (Vec<u8>, Vec<u8>) {
+    let (pk, sk) = mldsa65::keypair();
+    (pk.as_bytes().to_vec(), sk.as_bytes().to_vec())
+}
+
+/// Detached ML-DSA-65 signature over `message`
+pub fn sign(secret_key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+    let sk = mldsa65::SecretKey::from_bytes(secret_key)
+        .map_err(|_| Error::Crypto("invalid signing key"))?;
+    Ok(mldsa65::detached_sign(message, &sk).as_bytes().to_vec())
+}
+
+/// `Ok(false)` for a well-formed signature that doesn't verify
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+    let pk = mldsa65::PublicKey::from_bytes(public_key)
+        .map_err(|_| Error::Crypto("invalid verifying key"))?;
+    let sig = mldsa65::DetachedSignature::from_bytes(signature)
+        .map_err(|_| Error::Crypto("invalid signature"))?;
+    Ok(mldsa65::verify_detached_signature(&sig, message, &pk).is_ok())
+}
+
+pub fn random_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+fn cipher(key: &[u8], nonce: &[u8]) -> Result<Aes256Gcm> {
+    if key.len() != KEY_LEN {
+        return Err(Error::Crypto("key must be 32 bytes"));
+    }
+    if nonce.len() != NONCE_LEN {
+        return Err(Error::Crypto("nonce must be 12 bytes"));
+    }
+    Ok(Aes256Gcm::new(aes_gcm::Key::<Aes256Gcm>::from_slice(key)))
+}
+
+/// AES-256-GCM encrypt, same construction as `/commsec/aead/encrypt`
+pub fn seal(key: &[u8], nonce: &[u8], plaintext: &[u8], associated_data: Option<&[u8]>) -> Result<Vec<u8>> {
+    let payload = Payload { msg: plaintext, aad: associated_data.unwrap_or_default() };
+    cipher(key, nonce)?
+        .encrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| Error::Crypto("encryption failed"))
+}
+
+/// AES-256-GCM decrypt, same construction as `/commsec/aead/decrypt`
+pub fn open(key: &[u8], nonce: &[u8], ciphertext: &[u8], associated_data: Option<&[u8]>) -> Result<Vec<u8>> {
+    let payload = Payload { msg: ciphertext, aad: associated_data.unwrap_or_default() };
+    cipher(key, nonce)?
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| Error::Crypto("decryption failed"))
+}
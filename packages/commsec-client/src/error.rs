@@ -0,0 +1,40 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Transport failure talking to the API
+    Http(reqwest::Error),
+    /// Non-2xx response; `message` is the server's body
+    Server { status: u16, message: String },
+    /// A base64 field failed to decode
+    Base64(base64::DecodeError),
+    /// Local crypto rejected its input (bad key size, failed decryption, ...)
+    Crypto(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(err) => write!(f, "http error: {}", err),
+            Error::Server { status, message } => write!(f, "server returned {}: {}", status, message),
+            Error::Base64(err) => write!(f, "invalid base64: {}", err),
+            Error::Crypto(msg) => write!(f, "crypto error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(err: base64::DecodeError) -> Self {
+        Error::Base64(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
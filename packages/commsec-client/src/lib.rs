@@ -0,0 +1,13 @@
+//! Typed client for the TIDasONE CommSec API.
+//!
+//! `types` holds the wire format shared with the server, `crypto` mirrors the
+//! server's ML-KEM-1024 / AES-256-GCM operations locally, and `client` talks
+//! to a running API over HTTP.
+
+pub mod types;
+pub mod crypto;
+pub mod client;
+pub mod error;
+
+pub use client::CommsecClient;
+pub use error::Error;
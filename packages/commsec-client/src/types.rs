@@ -0,0 +1,84 @@
+//! Request/response bodies for `/commsec/*`. All binary fields are standard base64.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeypairResponse {
+    pub public_key: String,
+    pub secret_key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncapsulateRequest {
+    pub public_key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncapsulateResponse {
+    pub ciphertext: String,
+    pub shared_secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecapsulateRequest {
+    pub secret_key: String,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecapsulateResponse {
+    pub shared_secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AeadEncryptRequest {
+    pub key: String,
+    pub nonce: String,
+    pub plaintext: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub associated_data: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AeadEncryptResponse {
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AeadDecryptRequest {
+    pub key: String,
+    pub nonce: String,
+    pub ciphertext: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub associated_data: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AeadDecryptResponse {
+    pub plaintext: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    CallRate,
+    DecryptFailures,
+    PayloadSize,
+    AdTampering,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+    pub client: String,
+    pub kind: AlertKind,
+    pub score: f64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertsResponse {
+    pub alerts: Vec<Alert>,
+}
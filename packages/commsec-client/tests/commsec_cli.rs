@@ -0,0 +1,140 @@
+use rand::RngCore;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A scratch directory of files for one test
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("commsec-cli-{:016x}", rand::thread_rng().next_u64()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Scratch(dir)
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.0.join(name).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run `commsec`, returning its exit code and the JSON it printed
+/// (errors, exit code 2, go to stderr)
+fn commsec(args: &[&str]) -> (i32, Value) {
+    let output = Command::new(env!("CARGO_BIN_EXE_commsec")).args(args).output().unwrap();
+    let code = output.status.code().unwrap();
+    let printed = if code == 2 { &output.stderr } else { &output.stdout };
+    (code, serde_json::from_slice(printed).unwrap())
+}
+
+fn ok(args: &[&str]) -> Value {
+    let (code, json) = commsec(args);
+    assert_eq!(code, 0, "{:?} failed: {}", args, json);
+    json
+}
+
+#[cfg(unix)]
+fn assert_owner_only(path: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600, "{} is {:o}", path, mode & 0o777);
+}
+
+#[cfg(not(unix))]
+fn assert_owner_only(_path: &str) {}
+
+fn flip_byte(path: &Path, at: usize) {
+    let mut bytes = std::fs::read(path).unwrap();
+    bytes[at] ^= 0x01;
+    std::fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn test_kem_keygen_and_shared_secret_round_trip() {
+    let dir = Scratch::new();
+    let (pk, sk, ct) = (dir.path("kem.pub"), dir.path("kem.key"), dir.path("kem.ct"));
+    let keygen = ok(&["kem-keygen", "--public-out", &pk, "--secret-out", &sk]);
+    assert_eq!(keygen["algorithm"], "ML-KEM-1024");
+    assert_owner_only(&sk);
+
+    // printed when there's nowhere else for it to go...
+    let sent = ok(&["encapsulate", "--public-key", &pk, "--ciphertext-out", &ct]);
+    let received = ok(&["decapsulate", "--secret-key", &sk, "--ciphertext", &ct]);
+    assert!(sent["shared_secret"].is_string());
+    assert_eq!(sent["shared_secret"], received["shared_secret"]);
+
+    // ...but kept off stdout when it's written to a file
+    let (sent_ss, received_ss) = (dir.path("sent.ss"), dir.path("received.ss"));
+    let sent = ok(&["encapsulate", "--public-key", &pk, "--ciphertext-out", &ct, "--shared-secret-out", &sent_ss]);
+    let received = ok(&["decapsulate", "--secret-key", &sk, "--ciphertext", &ct, "--shared-secret-out", &received_ss]);
+    assert!(sent.get("shared_secret").is_none() && received.get("shared_secret").is_none());
+    assert_eq!(received["shared_secret_file"], received_ss);
+    assert_eq!(std::fs::read(&sent_ss).unwrap(), std::fs::read(&received_ss).unwrap());
+    assert_owner_only(&sent_ss);
+    assert_owner_only(&received_ss);
+}
+
+#[test]
+fn test_secret_files_are_tightened_when_overwritten() {
+    let dir = Scratch::new();
+    let (pk, sk) = (dir.path("kem.pub"), dir.path("kem.key"));
+    std::fs::write(&sk, "stale").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&sk, std::fs::Permissions::from_mode(0o644)).unwrap();
+    }
+    ok(&["kem-keygen", "--public-out", &pk, "--secret-out", &sk]);
+    assert_owner_only(&sk);
+    assert_ne!(std::fs::read_to_string(&sk).unwrap(), "stale");
+}
+
+#[test]
+fn test_seal_open_round_trip() {
+    let dir = Scratch::new();
+    let (pk, sk, ct, key) = (dir.path("kem.pub"), dir.path("kem.key"), dir.path("kem.ct"), dir.path("aead.key"));
+    ok(&["kem-keygen", "--public-out", &pk, "--secret-out", &sk]);
+    ok(&["encapsulate", "--public-key", &pk, "--ciphertext-out", &ct, "--shared-secret-out", &key]);
+
+    let (plain, sealed, opened) = (dir.path("plain.txt"), dir.path("plain.sealed"), dir.path("plain.opened"));
+    std::fs::write(&plain, b"manifest for crate 7").unwrap();
+    ok(&["seal", "--key", &key, "--in", &plain, "--out", &sealed, "--ad", "crate-7"]);
+    assert_ne!(std::fs::read(&sealed).unwrap(), std::fs::read(&plain).unwrap());
+    ok(&["open", "--key", &key, "--in", &sealed, "--out", &opened, "--ad", "crate-7"]);
+    assert_eq!(std::fs::read(&opened).unwrap(), b"manifest for crate 7");
+
+    // the wrong associated data, or a flipped ciphertext bit, doesn't open
+    let (code, error) = commsec(&["open", "--key", &key, "--in", &sealed, "--out", &opened, "--ad", "crate-8"]);
+    assert_eq!(code, 2);
+    assert!(error["error"].is_string());
+    flip_byte(Path::new(&sealed), 20);
+    let (code, _) = commsec(&["open", "--key", &key, "--in", &sealed, "--out", &opened, "--ad", "crate-7"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_sign_verify_round_trip() {
+    let dir = Scratch::new();
+    let (pk, sk, sig) = (dir.path("dsa.pub"), dir.path("dsa.key"), dir.path("message.sig"));
+    let keygen = ok(&["dsa-keygen", "--public-out", &pk, "--secret-out", &sk]);
+    assert_eq!(keygen["algorithm"], "ML-DSA-65");
+    assert_owner_only(&sk);
+
+    let message = dir.path("message.txt");
+    std::fs::write(&message, b"release 1.4.0").unwrap();
+    ok(&["sign", "--secret-key", &sk, "--in", &message, "--signature-out", &sig]);
+    let verified = ok(&["verify", "--public-key", &pk, "--in", &message, "--signature", &sig]);
+    assert_eq!(verified["valid"], true);
+
+    // a tampered message fails verification with status 1
+    std::fs::write(&message, b"release 1.4.1").unwrap();
+    let (code, rejected) = commsec(&["verify", "--public-key", &pk, "--in", &message, "--signature", &sig]);
+    assert_eq!(code, 1);
+    assert_eq!(rejected["valid"], false);
+}
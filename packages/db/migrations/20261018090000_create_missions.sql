@@ -0,0 +1,44 @@
+-- Missions
+CREATE TABLE missions (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    status TEXT NOT NULL DEFAULT 'Planned',
+    kem_public_key TEXT,                       -- ML-KEM-1024 key operators seal updates to
+    checkin_interval_secs INT NOT NULL DEFAULT 3600,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Users assigned to a mission
+CREATE TABLE mission_assignments (
+    mission_id UUID NOT NULL REFERENCES missions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL DEFAULT 'operator',
+    assigned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (mission_id, user_id)
+);
+
+-- Task updates, sealed client-side with CommSec (ML-KEM + AES-256-GCM)
+CREATE TABLE task_updates (
+    id UUID PRIMARY KEY,
+    mission_id UUID NOT NULL REFERENCES missions(id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kem_ciphertext TEXT,
+    nonce TEXT NOT NULL,
+    ciphertext TEXT NOT NULL,
+    associated_data TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX task_updates_mission_idx ON task_updates (mission_id, created_at);
+
+-- Last beacon check-in per operator and mission
+CREATE TABLE beacons (
+    mission_id UUID NOT NULL REFERENCES missions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    location TEXT,
+    checkins BIGINT NOT NULL DEFAULT 1,
+    missed_alerted_at TIMESTAMPTZ,
+    PRIMARY KEY (mission_id, user_id)
+);
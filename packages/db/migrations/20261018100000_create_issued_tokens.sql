@@ -0,0 +1,10 @@
+-- JWTs minted by the API, tracked so they can be expired and reported on
+CREATE TABLE issued_tokens (
+    jti UUID PRIMARY KEY,
+    subject TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    issued_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX issued_tokens_expires_idx ON issued_tokens (expires_at);
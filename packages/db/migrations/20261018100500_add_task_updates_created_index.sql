@@ -0,0 +1,2 @@
+-- The retention sweeper finds expired task updates by age
+CREATE INDEX task_updates_created_idx ON task_updates (created_at);
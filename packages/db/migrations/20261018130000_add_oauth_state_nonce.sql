@@ -0,0 +1,2 @@
+-- OpenID Connect nonce echoed back in the ID token
+ALTER TABLE oauth_states ADD COLUMN nonce TEXT;
@@ -0,0 +1,24 @@
+-- Opaque refresh tokens (stored as SHA-256 hashes), rotated on every use.
+-- All tokens descending from one login share a session_id.
+CREATE TABLE refresh_tokens (
+    id UUID PRIMARY KEY,
+    session_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    issued_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX refresh_tokens_session_idx ON refresh_tokens (session_id);
+CREATE INDEX refresh_tokens_user_idx ON refresh_tokens (user_id);
+CREATE INDEX refresh_tokens_expires_idx ON refresh_tokens (expires_at);
+
+-- Access tokens: which session minted them, and the jti denylist
+ALTER TABLE issued_tokens ADD COLUMN session_id UUID;
+ALTER TABLE issued_tokens ADD COLUMN revoked_at TIMESTAMPTZ;
+
+CREATE INDEX issued_tokens_session_idx ON issued_tokens (session_id);
+CREATE INDEX issued_tokens_subject_idx ON issued_tokens (subject);
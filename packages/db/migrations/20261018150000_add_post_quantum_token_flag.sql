@@ -0,0 +1,3 @@
+-- Logins that asked for ML-DSA signed access tokens; refreshes keep the format
+ALTER TABLE oauth_states ADD COLUMN post_quantum BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE refresh_tokens ADD COLUMN post_quantum BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,48 @@
+-- Role-based access control: roles grant permissions, users hold roles.
+-- Built-in roles are seeded here and can't be edited through the API.
+CREATE TABLE roles (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    builtin BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE role_permissions (
+    role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission TEXT NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE user_roles (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    granted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role)
+);
+
+CREATE INDEX user_roles_role_idx ON user_roles (role);
+
+INSERT INTO roles (name, description, builtin) VALUES
+    ('admin', 'Full access, including role management', TRUE),
+    ('operator', 'Runs missions and manages inventory and packages', TRUE),
+    ('vendor', 'Manages inventory and packages', TRUE),
+    ('viewer', 'Read-only access', TRUE);
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('admin', 'users:read'), ('admin', 'users:write'),
+    ('admin', 'inventory:read'), ('admin', 'inventory:write'),
+    ('admin', 'packages:read'), ('admin', 'packages:write'),
+    ('admin', 'missions:read'), ('admin', 'missions:write'),
+    ('admin', 'sessions:revoke'), ('admin', 'roles:manage'),
+    ('operator', 'users:read'),
+    ('operator', 'inventory:read'), ('operator', 'inventory:write'),
+    ('operator', 'packages:read'), ('operator', 'packages:write'),
+    ('operator', 'missions:read'), ('operator', 'missions:write'),
+    ('vendor', 'inventory:read'), ('vendor', 'inventory:write'),
+    ('vendor', 'packages:read'), ('vendor', 'packages:write'),
+    ('viewer', 'users:read'), ('viewer', 'inventory:read'),
+    ('viewer', 'packages:read'), ('viewer', 'missions:read');
+
+-- existing accounts keep read access
+INSERT INTO user_roles (user_id, role) SELECT id, 'viewer' FROM users;
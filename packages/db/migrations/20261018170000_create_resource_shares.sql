@@ -0,0 +1,24 @@
+-- Sharing grants for inventory items and packages. The owner (owner_id) always
+-- has full access; a share gives another user read or read/write access.
+CREATE TABLE inventory_shares (
+    inventory_id UUID NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    can_write BOOLEAN NOT NULL DEFAULT FALSE,
+    granted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (inventory_id, user_id)
+);
+
+CREATE TABLE package_shares (
+    package_id UUID NOT NULL REFERENCES packages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    can_write BOOLEAN NOT NULL DEFAULT FALSE,
+    granted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (package_id, user_id)
+);
+
+CREATE INDEX inventory_shares_user_idx ON inventory_shares (user_id);
+CREATE INDEX package_shares_user_idx ON package_shares (user_id);
+CREATE INDEX inventory_owner_idx ON inventory (owner_id);
+CREATE INDEX packages_owner_idx ON packages (owner_id);
@@ -0,0 +1,28 @@
+-- Service accounts for daemons and scanners that can't do browser OAuth.
+-- Each account is backed by a users row (same id) so ownership and shares
+-- apply; it authenticates with API keys whose scopes are permissions.
+CREATE TABLE service_accounts (
+    id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    disabled_at TIMESTAMPTZ
+);
+
+-- Only a SHA-256 of each key is stored; `prefix` identifies it in listings and logs
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY,
+    service_account_id UUID NOT NULL REFERENCES service_accounts(id) ON DELETE CASCADE,
+    prefix TEXT NOT NULL UNIQUE,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX api_keys_account_idx ON api_keys (service_account_id);
+
+INSERT INTO role_permissions (role, permission) VALUES ('admin', 'service_accounts:manage');
@@ -0,0 +1,15 @@
+-- Sign-In-With-Solana: one-time messages a wallet must sign to log in or link
+CREATE TABLE wallet_challenges (
+    nonce TEXT PRIMARY KEY,
+    address TEXT NOT NULL, -- base58 ed25519 public key
+    message TEXT NOT NULL, -- exact text the wallet signs
+    link_user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX wallet_challenges_expires_idx ON wallet_challenges (expires_at);
+
+-- Wallets are now verified identities (provider 'solana'); the free-form
+-- token id was never checked against anything
+ALTER TABLE users DROP COLUMN nft_token_id;
@@ -0,0 +1,48 @@
+-- Multi-tenancy: every inventory item, package and mission belongs to one
+-- organization. Users stay global (one person can work in several programs)
+-- and see an organization's data through their membership in it.
+CREATE TABLE organizations (
+    id UUID PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE org_memberships (
+    org_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member')),
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (org_id, user_id)
+);
+
+CREATE INDEX org_memberships_user_idx ON org_memberships (user_id);
+
+-- Everything that exists today moves into the default organization
+INSERT INTO organizations (id, slug, name)
+VALUES ('00000000-0000-0000-0000-000000000001', 'default', 'Default');
+
+INSERT INTO org_memberships (org_id, user_id, role)
+SELECT '00000000-0000-0000-0000-000000000001', id, 'member' FROM users;
+
+ALTER TABLE inventory ADD COLUMN org_id UUID REFERENCES organizations(id) ON DELETE CASCADE;
+ALTER TABLE packages ADD COLUMN org_id UUID REFERENCES organizations(id) ON DELETE CASCADE;
+ALTER TABLE missions ADD COLUMN org_id UUID REFERENCES organizations(id) ON DELETE CASCADE;
+
+UPDATE inventory SET org_id = '00000000-0000-0000-0000-000000000001';
+UPDATE packages SET org_id = '00000000-0000-0000-0000-000000000001';
+UPDATE missions SET org_id = '00000000-0000-0000-0000-000000000001';
+
+-- no default: new rows must name their organization
+ALTER TABLE inventory ALTER COLUMN org_id SET NOT NULL;
+ALTER TABLE packages ALTER COLUMN org_id SET NOT NULL;
+ALTER TABLE missions ALTER COLUMN org_id SET NOT NULL;
+
+CREATE INDEX inventory_org_idx ON inventory (org_id);
+CREATE INDEX packages_org_idx ON packages (org_id);
+CREATE INDEX missions_org_idx ON missions (org_id);
+
+-- The organization a session is working in, carried across refreshes
+ALTER TABLE refresh_tokens ADD COLUMN org_id UUID REFERENCES organizations(id) ON DELETE SET NULL;
+
+INSERT INTO role_permissions (role, permission) VALUES ('admin', 'orgs:manage');
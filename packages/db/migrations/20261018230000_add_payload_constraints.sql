@@ -0,0 +1,93 @@
+-- The rules the API validates payloads against, enforced again by the
+-- database for writes that don't go through it.
+
+-- Existing rows predate the rules: bring them into line first, so the
+-- constraints below can be checked against every row. Each row a fix
+-- changes is copied to payload_constraint_originals as it was, so nothing is
+-- lost; review (and eventually drop) it with
+--   SELECT * FROM payload_constraint_originals ORDER BY id;
+CREATE TABLE payload_constraint_originals (
+    id BIGSERIAL PRIMARY KEY,
+    table_name TEXT NOT NULL,
+    row_id UUID NOT NULL,
+    original JSONB NOT NULL, -- the whole row before this fix
+    fixed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE FUNCTION keep_payload_original() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO payload_constraint_originals (table_name, row_id, original)
+    VALUES (TG_TABLE_NAME, OLD.id, to_jsonb(OLD));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER keep_payload_original BEFORE UPDATE ON users
+    FOR EACH ROW WHEN (OLD.* IS DISTINCT FROM NEW.*) EXECUTE FUNCTION keep_payload_original();
+CREATE TRIGGER keep_payload_original BEFORE UPDATE ON inventory
+    FOR EACH ROW WHEN (OLD.* IS DISTINCT FROM NEW.*) EXECUTE FUNCTION keep_payload_original();
+CREATE TRIGGER keep_payload_original BEFORE UPDATE ON packages
+    FOR EACH ROW WHEN (OLD.* IS DISTINCT FROM NEW.*) EXECUTE FUNCTION keep_payload_original();
+
+UPDATE users SET username = btrim(username), email = btrim(email)
+WHERE username <> btrim(username) OR email <> btrim(email);
+
+UPDATE users SET username = 'user-' || left(id::text, 8)
+WHERE btrim(username) = '';
+
+-- unusable addresses and later case-variant duplicates get a placeholder,
+-- the way sign-ins with an address that's already taken do
+UPDATE users SET email = id::text || '@users.noreply.tidasone'
+WHERE email !~ '^[^@[:space:]]+@[^@[:space:]]+$'
+   OR id IN (
+       SELECT id FROM (
+           SELECT id, row_number() OVER (PARTITION BY lower(email) ORDER BY created_at, id) AS n
+           FROM users
+       ) ranked
+       WHERE n > 1
+   );
+
+-- the oldest account keeps a shared username; the rest get their id appended
+UPDATE users SET username = username || '-' || left(id::text, 8)
+WHERE id IN (
+    SELECT id FROM (
+        SELECT id, row_number() OVER (PARTITION BY lower(username) ORDER BY created_at, id) AS n
+        FROM users
+    ) ranked
+    WHERE n > 1
+);
+
+UPDATE inventory SET name = 'Unnamed item' WHERE btrim(name) = '';
+UPDATE inventory SET quantity = 0 WHERE quantity < 0;
+
+UPDATE packages SET destination = 'Unknown' WHERE btrim(destination) = '';
+
+-- statuses used to be free-form: match known ones whatever their spelling
+-- ("in_transit", "DELIVERED"), and send anything else back to Pending
+UPDATE packages SET status = initcap(regexp_replace(btrim(status), '[-_[:space:]]+', ' ', 'g'))
+WHERE initcap(regexp_replace(btrim(status), '[-_[:space:]]+', ' ', 'g'))
+      IN ('Pending', 'Shipped', 'In Transit', 'Delivered', 'Returned', 'Cancelled');
+UPDATE packages SET status = 'Pending'
+WHERE status NOT IN ('Pending', 'Shipped', 'In Transit', 'Delivered', 'Returned', 'Cancelled');
+
+DROP TRIGGER keep_payload_original ON users;
+DROP TRIGGER keep_payload_original ON inventory;
+DROP TRIGGER keep_payload_original ON packages;
+DROP FUNCTION keep_payload_original();
+
+ALTER TABLE users
+    ADD CONSTRAINT users_username_not_blank CHECK (btrim(username) <> ''),
+    ADD CONSTRAINT users_email_format CHECK (email ~ '^[^@[:space:]]+@[^@[:space:]]+$');
+
+ALTER TABLE inventory
+    ADD CONSTRAINT inventory_name_not_blank CHECK (btrim(name) <> ''),
+    ADD CONSTRAINT inventory_quantity_non_negative CHECK (quantity >= 0);
+
+ALTER TABLE packages
+    ADD CONSTRAINT packages_destination_not_blank CHECK (btrim(destination) <> ''),
+    ADD CONSTRAINT packages_status_known
+        CHECK (status IN ('Pending', 'Shipped', 'In Transit', 'Delivered', 'Returned', 'Cancelled'));
+
+-- one account per address and per username, however they're capitalized
+CREATE UNIQUE INDEX users_email_key ON users (lower(email));
+CREATE UNIQUE INDEX users_username_key ON users (lower(username));
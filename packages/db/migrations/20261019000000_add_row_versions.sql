@@ -0,0 +1,33 @@
+-- Optimistic concurrency: every update bumps the row's version, which the
+-- API hands out as its ETag and checks against If-Match.
+ALTER TABLE users
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+ALTER TABLE inventory
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+ALTER TABLE packages
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+UPDATE users SET updated_at = created_at;
+UPDATE inventory SET updated_at = created_at;
+UPDATE packages SET updated_at = created_at;
+
+-- in the database, so writes that bypass the API can't leave a stale ETag
+CREATE FUNCTION bump_row_version() RETURNS trigger AS $$
+BEGIN
+    NEW.version := OLD.version + 1;
+    NEW.updated_at := now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_bump_version BEFORE UPDATE ON users
+    FOR EACH ROW EXECUTE FUNCTION bump_row_version();
+CREATE TRIGGER inventory_bump_version BEFORE UPDATE ON inventory
+    FOR EACH ROW EXECUTE FUNCTION bump_row_version();
+CREATE TRIGGER packages_bump_version BEFORE UPDATE ON packages
+    FOR EACH ROW EXECUTE FUNCTION bump_row_version();
@@ -0,0 +1,14 @@
+-- Mission statuses, enforced by the database as they are for packages.
+
+-- statuses used to be free-form: match known ones whatever their spelling
+-- ("on_hold", "ACTIVE"), and send anything else back to Planned
+UPDATE missions SET status = initcap(regexp_replace(btrim(status), '[-_[:space:]]+', ' ', 'g'))
+WHERE initcap(regexp_replace(btrim(status), '[-_[:space:]]+', ' ', 'g'))
+      IN ('Planned', 'Active', 'On Hold', 'Completed', 'Aborted');
+UPDATE missions SET status = 'Planned'
+WHERE status NOT IN ('Planned', 'Active', 'On Hold', 'Completed', 'Aborted');
+
+ALTER TABLE missions
+    ADD CONSTRAINT missions_status_known
+        CHECK (status IN ('Planned', 'Active', 'On Hold', 'Completed', 'Aborted')) NOT VALID;
+ALTER TABLE missions VALIDATE CONSTRAINT missions_status_known;
@@ -0,0 +1,2 @@
+-- The retention report covers the whole instance; only admins read it
+INSERT INTO role_permissions (role, permission) VALUES ('admin', 'retention:read');
@@ -1,9 +1,11 @@
 use dotenvy::dotenv;
 use std::env;
 use sqlx::PgPool;
+use uuid::Uuid;
 
+use db::models::{InventoryFields, PackageFields};
 use db::queries::{
-    create_user, get_users,
+    create_user, get_users, set_org_member,
     create_inventory, get_inventory,
     create_package, get_packages,
 };
@@ -18,21 +20,33 @@ async fn main() -> Result<(), sqlx::Error> {
     let user = create_user(&pool, "alice", "alice@tidasone.com").await?;
     println!("👤 Inserted user: {:?}", user);
 
+    // the organization the migrations seed
+    let org_id = Uuid::from_u128(1);
+    set_org_member(&pool, org_id, user.id, "member").await?;
+
     // 2. Insert inventory
-    let inventory = create_inventory(
-        &pool, user.id, "Quantum Drive", Some("Prototype FTL engine"),
-        1, Some("Hangar 42"), None
-    ).await?;
+    let inventory = create_inventory(&pool, org_id, user.id, InventoryFields {
+        name: "Quantum Drive",
+        description: Some("Prototype FTL engine"),
+        quantity: 1,
+        location: Some("Hangar 42"),
+        token_id: None,
+    }).await?;
     println!("📦 Inserted inventory: {:?}", inventory);
 
     // 3. Insert package
-    let package = create_package(&pool, user.id, Some(inventory.id), "Mars Base Alpha").await?;
+    let package = create_package(&pool, org_id, user.id, PackageFields {
+        inventory_item_id: Some(inventory.id),
+        status: "Pending",
+        destination: "Mars Base Alpha",
+        nft_token: None,
+    }).await?;
     println!("🚀 Inserted package: {:?}", package);
 
     // 4. Fetch back everything
     println!("👥 Users: {:?}", get_users(&pool).await?);
-    println!("📦 Inventory: {:?}", get_inventory(&pool).await?);
-    println!("📦 Packages: {:?}", get_packages(&pool).await?);
+    println!("📦 Inventory: {:?}", get_inventory(&pool, org_id, user.id).await?);
+    println!("📦 Packages: {:?}", get_packages(&pool, org_id, user.id).await?);
 
     Ok(())
 }
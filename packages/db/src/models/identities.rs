@@ -0,0 +1,61 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A provider account (Google sub, GitHub id, ...) linked to a user
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub provider_subject: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: DateTime<Utc>,
+}
+
+/// An authorization redirect we issued and haven't seen come back yet
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OAuthState {
+    pub state: String,
+    pub provider: String,
+    pub pkce_verifier: String,
+    pub nonce: Option<String>, // OIDC providers only
+    pub link_user_id: Option<Uuid>,
+    pub post_quantum: bool, // issue an ML-DSA signed access token
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A login attempt to remember until its callback arrives
+#[derive(Debug)]
+pub struct NewOAuthState<'a> {
+    pub state: &'a str,
+    pub provider: &'a str,
+    pub pkce_verifier: &'a str,
+    pub nonce: Option<&'a str>,
+    pub link_user_id: Option<Uuid>,
+    pub post_quantum: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A Sign-In-With-Solana message waiting for the wallet's signature
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WalletChallenge {
+    pub nonce: String,
+    pub address: String,
+    pub message: String,
+    pub link_user_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct NewWalletChallenge<'a> {
+    pub nonce: &'a str,
+    pub address: &'a str,
+    pub message: &'a str,
+    pub link_user_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
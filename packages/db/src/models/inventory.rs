@@ -6,6 +6,7 @@ use chrono::{DateTime, Utc};
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Inventory {
     pub id: Uuid,
+    pub org_id: Uuid,
     pub owner_id: Uuid,
     pub name: String,
     pub description: Option<String>,
@@ -13,5 +14,17 @@ pub struct Inventory {
     pub location: Option<String>,
     pub token_id: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub version: i32,
+    pub updated_at: DateTime<Utc>,
 }
 
+
+/// Item fields as written by create and update
+#[derive(Debug)]
+pub struct InventoryFields<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub quantity: i32,
+    pub location: Option<&'a str>,
+    pub token_id: Option<&'a str>,
+}
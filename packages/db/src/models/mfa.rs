@@ -0,0 +1,13 @@
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A user's TOTP enrollment; never serialized, the secret stays server-side
+#[derive(Debug, FromRow)]
+pub struct UserTotp {
+    pub user_id: Uuid,
+    pub secret: String,
+    pub confirmed_at: Option<DateTime<Utc>>, // None while enrollment is pending
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,47 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Mission {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub status: String,
+    pub kem_public_key: Option<String>,
+    pub checkin_interval_secs: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MissionAssignment {
+    pub mission_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+    pub assigned_at: DateTime<Utc>,
+}
+
+/// A field report sealed by the operator; the server only stores ciphertext
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TaskUpdate {
+    pub id: Uuid,
+    pub mission_id: Uuid,
+    pub author_id: Uuid,
+    pub kem_ciphertext: Option<String>,
+    pub nonce: String,
+    pub ciphertext: String,
+    pub associated_data: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Beacon {
+    pub mission_id: Uuid,
+    pub user_id: Uuid,
+    pub last_seen_at: DateTime<Utc>,
+    pub location: Option<String>,
+    pub checkins: i64,
+    pub missed_alerted_at: Option<DateTime<Utc>>,
+}
@@ -1,8 +1,26 @@
 pub mod users;
 pub mod inventory;
 pub mod packages;
+pub mod missions;
+pub mod tokens;
+pub mod identities;
+pub mod roles;
+pub mod shares;
+pub mod service_accounts;
+pub mod mfa;
+pub mod orgs;
+pub mod audit;
 
-pub use users::User;
-pub use inventory::Inventory;
-pub use packages::Package;
+pub use users::{User, UserFields};
+pub use inventory::{Inventory, InventoryFields};
+pub use packages::{Package, PackageFields};
+pub use missions::{Mission, MissionAssignment, TaskUpdate, Beacon};
 
+pub use tokens::{IssuedToken, RefreshToken, NewRefreshToken};
+pub use identities::{UserIdentity, OAuthState, NewOAuthState, WalletChallenge, NewWalletChallenge};
+pub use roles::{Role, UserRole};
+pub use shares::Share;
+pub use service_accounts::{ServiceAccount, ApiKey};
+pub use mfa::UserTotp;
+pub use orgs::{Organization, OrgMembership, UserOrg};
+pub use audit::{AuditEvent, NewAuditEvent, AuditFilter, GENESIS_HASH};
@@ -0,0 +1,31 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A tenant: one customer program and everything it owns
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Organization {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OrgMembership {
+    pub org_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String, // "owner", "admin" or "member"
+    pub joined_at: DateTime<Utc>,
+}
+
+/// An organization as seen by one of its members
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserOrg {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
@@ -6,11 +6,23 @@ use chrono::{DateTime, Utc};
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Package {
     pub id: Uuid,
+    pub org_id: Uuid,
     pub owner_id: Uuid,
     pub inventory_item_id: Option<Uuid>,
     pub status: String,
     pub destination: String,
     pub nft_token: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub version: i32,
+    pub updated_at: DateTime<Utc>,
 }
 
+
+/// Package fields as written by create and update
+#[derive(Debug)]
+pub struct PackageFields<'a> {
+    pub inventory_item_id: Option<Uuid>,
+    pub status: &'a str,
+    pub destination: &'a str,
+    pub nft_token: Option<&'a str>,
+}
@@ -0,0 +1,22 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A named set of permissions (`missions:write`, `roles:manage`, ...)
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Role {
+    pub name: String,
+    pub description: String,
+    pub builtin: bool, // seeded by migrations, read-only through the API
+    pub permissions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserRole {
+    pub user_id: Uuid,
+    pub role: String,
+    pub granted_by: Option<Uuid>, // None for migrations and bootstrap grants
+    pub granted_at: DateTime<Utc>,
+}
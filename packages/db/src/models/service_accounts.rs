@@ -0,0 +1,29 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A non-human caller; `id` is also its `users.id`
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ServiceAccount {
+    pub id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>, // disabled accounts' keys stop working
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub service_account_id: Uuid,
+    pub prefix: String, // first part of the key, safe to show
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub scopes: Vec<String>, // permissions the key carries
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,14 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// Access to an inventory item or package granted by its owner
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Share {
+    pub resource_id: Uuid, // inventory.id or packages.id
+    pub user_id: Uuid,
+    pub can_write: bool, // false: read-only
+    pub granted_by: Option<Uuid>,
+    pub granted_at: DateTime<Utc>,
+}
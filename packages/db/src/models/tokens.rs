@@ -0,0 +1,46 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct IssuedToken {
+    pub jti: Uuid,
+    pub subject: String,
+    pub provider: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub session_id: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Opaque refresh token; only its SHA-256 hash is stored
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>, // set once rotated; a second use is token theft
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub post_quantum: bool, // access tokens of this session are ML-DSA signed
+    pub mfa_at: Option<DateTime<Utc>>, // last MFA verification of the session
+    pub org_id: Option<Uuid>, // organization the session is working in
+}
+
+/// A refresh token to store; only its hash is kept
+#[derive(Debug)]
+pub struct NewRefreshToken<'a> {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub provider: &'a str,
+    pub token_hash: &'a str,
+    pub post_quantum: bool,
+    pub mfa_at: Option<DateTime<Utc>>,
+    pub org_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -8,8 +8,17 @@ pub struct User {
     pub id: Uuid,
     pub username: String,
     pub email: String,
-    pub nft_token_id: Option<String>,
     pub identity_hash: Option<String>,
     pub created_at: DateTime<Utc>, // ✅ Make this optional
+    pub version: i32, // bumped on every update (see bump_row_version)
+    pub updated_at: DateTime<Utc>,
 }
 
+
+/// User fields as written by create and update
+#[derive(Debug)]
+pub struct UserFields<'a> {
+    pub username: &'a str,
+    pub email: &'a str,
+    pub identity_hash: Option<&'a str>,
+}
@@ -1,7 +1,13 @@
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::{User, Inventory, Package};
+use chrono::{DateTime, SubsecRound, Utc};
+
+use crate::models::{
+    User, UserFields, Inventory, InventoryFields, Package, PackageFields, Mission, MissionAssignment, TaskUpdate, Beacon, IssuedToken,
+    UserIdentity, OAuthState, NewOAuthState, WalletChallenge, NewWalletChallenge, RefreshToken, NewRefreshToken, Role, UserRole, Share, ServiceAccount, ApiKey, UserTotp,
+    Organization, OrgMembership, UserOrg, AuditEvent, NewAuditEvent, AuditFilter, GENESIS_HASH,
+};
 
 //
 // ─── USERS ────────────────────────────────────────────────────────────────
@@ -14,7 +20,7 @@ pub async fn create_user(pool: &PgPool, username: &str, email: &str) -> sqlx::Re
         r#"
         INSERT INTO users (id, username, email)
         VALUES ($1, $2, $3)
-        RETURNING id, username, email, nft_token_id, identity_hash, created_at
+        RETURNING id, username, email, identity_hash, created_at, version, updated_at
         "#,
         Uuid::new_v4(),
         username,
@@ -33,6 +39,138 @@ pub async fn get_users(pool: &PgPool) -> sqlx::Result<Vec<User>> {
     Ok(users)
 }
 
+pub async fn get_user(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Option<User>> {
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(user)
+}
+
+/// Create a user as a member of `org_id`
+pub async fn create_org_user(pool: &PgPool, org_id: Uuid, fields: UserFields<'_>) -> sqlx::Result<User> {
+    let mut tx = pool.begin().await?;
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        INSERT INTO users (id, username, email, identity_hash)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, username, email, identity_hash, created_at, version, updated_at
+        "#,
+        Uuid::new_v4(),
+        fields.username,
+        fields.email,
+        fields.identity_hash
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO org_memberships (org_id, user_id, role) VALUES ($1, $2, 'member')",
+        org_id,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(user)
+}
+
+/// Members of `org_id`, newest first
+pub async fn get_org_users(pool: &PgPool, org_id: Uuid) -> sqlx::Result<Vec<User>> {
+    let users = sqlx::query_as!(
+        User,
+        r#"
+        SELECT u.id, u.username, u.email, u.identity_hash, u.created_at, u.version, u.updated_at
+        FROM users u
+        JOIN org_memberships m ON m.user_id = u.id
+        WHERE m.org_id = $1
+        ORDER BY u.created_at DESC
+        "#,
+        org_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(users)
+}
+
+/// `user_id`, if they're a member of `org_id`
+pub async fn get_org_user(pool: &PgPool, org_id: Uuid, user_id: Uuid) -> sqlx::Result<Option<User>> {
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        SELECT u.id, u.username, u.email, u.identity_hash, u.created_at, u.version, u.updated_at
+        FROM users u
+        JOIN org_memberships m ON m.user_id = u.id
+        WHERE u.id = $1 AND m.org_id = $2
+        "#,
+        user_id,
+        org_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(user)
+}
+
+/// Overwrite a member of `org_id` if they're still at `version`; `None` once
+/// the row has moved on (or gone)
+pub async fn update_org_user(
+    pool: &PgPool,
+    org_id: Uuid,
+    user_id: Uuid,
+    version: i32,
+    fields: UserFields<'_>,
+) -> sqlx::Result<Option<User>> {
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET username = $4, email = $5, identity_hash = $6
+        WHERE id = $1 AND version = $3
+          AND id IN (SELECT user_id FROM org_memberships WHERE org_id = $2)
+        RETURNING id, username, email, identity_hash, created_at, version, updated_at
+        "#,
+        user_id,
+        org_id,
+        version,
+        fields.username,
+        fields.email,
+        fields.identity_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(user)
+}
+
+/// Delete a member of `org_id`, returning the row as it was
+pub async fn delete_org_user(pool: &PgPool, org_id: Uuid, user_id: Uuid) -> sqlx::Result<Option<User>> {
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        DELETE FROM users
+        WHERE id = $1 AND id IN (SELECT user_id FROM org_memberships WHERE org_id = $2)
+        RETURNING id, username, email, identity_hash, created_at, version, updated_at
+        "#,
+        user_id,
+        org_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(user)
+}
+
+pub async fn get_user_by_email(pool: &PgPool, email: &str) -> sqlx::Result<Option<User>> {
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE lower(email) = lower($1)", email)
+        .fetch_optional(pool)
+        .await?;
+    Ok(user)
+}
+
+pub async fn get_user_by_username(pool: &PgPool, username: &str) -> sqlx::Result<Option<User>> {
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE lower(username) = lower($1)", username)
+        .fetch_optional(pool)
+        .await?;
+    Ok(user)
+}
+
 // Update
 pub async fn update_user_email(pool: &PgPool, user_id: Uuid, new_email: &str) -> sqlx::Result<User> {
     let user = sqlx::query_as!(
@@ -41,7 +179,7 @@ pub async fn update_user_email(pool: &PgPool, user_id: Uuid, new_email: &str) ->
         UPDATE users
         SET email = $2
         WHERE id = $1
-        RETURNING id, username, email, nft_token_id, identity_hash, created_at
+        RETURNING id, username, email, identity_hash, created_at, version, updated_at
         "#,
         user_id,
         new_email
@@ -60,69 +198,306 @@ pub async fn delete_user(pool: &PgPool, user_id: Uuid) -> sqlx::Result<u64> {
     Ok(rows_affected)
 }
 
+//
+// ─── USER IDENTITIES ────────────────────────────────────────────────────────────────
+//
+
+pub async fn find_identity(
+    pool: &PgPool,
+    provider: &str,
+    provider_subject: &str,
+) -> sqlx::Result<Option<UserIdentity>> {
+    let identity = sqlx::query_as!(
+        UserIdentity,
+        "SELECT * FROM user_identities WHERE provider = $1 AND provider_subject = $2",
+        provider,
+        provider_subject
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(identity)
+}
+
+pub async fn link_identity(
+    pool: &PgPool,
+    user_id: Uuid,
+    provider: &str,
+    provider_subject: &str,
+    email: Option<&str>,
+) -> sqlx::Result<UserIdentity> {
+    let identity = sqlx::query_as!(
+        UserIdentity,
+        r#"
+        INSERT INTO user_identities (id, user_id, provider, provider_subject, email)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, user_id, provider, provider_subject, email, created_at, last_login_at
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        provider,
+        provider_subject,
+        email
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(identity)
+}
+
+pub async fn touch_identity(pool: &PgPool, identity_id: Uuid, email: Option<&str>) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        r#"
+        UPDATE user_identities
+        SET last_login_at = NOW(), email = COALESCE($2, email)
+        WHERE id = $1
+        "#,
+        identity_id,
+        email
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn get_user_identities(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Vec<UserIdentity>> {
+    let identities = sqlx::query_as!(
+        UserIdentity,
+        "SELECT * FROM user_identities WHERE user_id = $1 ORDER BY created_at",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(identities)
+}
+
+pub async fn delete_identity(pool: &PgPool, user_id: Uuid, identity_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM user_identities WHERE id = $1 AND user_id = $2",
+        identity_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn create_oauth_state(pool: &PgPool, new: NewOAuthState<'_>) -> sqlx::Result<OAuthState> {
+    let row = sqlx::query_as!(
+        OAuthState,
+        r#"
+        INSERT INTO oauth_states (state, provider, pkce_verifier, nonce, link_user_id, post_quantum, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING state, provider, pkce_verifier, nonce, link_user_id, post_quantum, created_at, expires_at
+        "#,
+        new.state,
+        new.provider,
+        new.pkce_verifier,
+        new.nonce,
+        new.link_user_id,
+        new.post_quantum,
+        new.expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Consume a pending state; each state can be redeemed at most once
+pub async fn take_oauth_state(
+    pool: &PgPool,
+    state: &str,
+    provider: &str,
+) -> sqlx::Result<Option<OAuthState>> {
+    let row = sqlx::query_as!(
+        OAuthState,
+        r#"
+        DELETE FROM oauth_states
+        WHERE state = $1 AND provider = $2
+        RETURNING state, provider, pkce_verifier, nonce, link_user_id, post_quantum, created_at, expires_at
+        "#,
+        state,
+        provider
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+pub async fn delete_expired_oauth_states(pool: &PgPool) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!("DELETE FROM oauth_states WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn create_wallet_challenge(pool: &PgPool, new: NewWalletChallenge<'_>) -> sqlx::Result<WalletChallenge> {
+    let row = sqlx::query_as!(
+        WalletChallenge,
+        r#"
+        INSERT INTO wallet_challenges (nonce, address, message, link_user_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING nonce, address, message, link_user_id, created_at, expires_at
+        "#,
+        new.nonce,
+        new.address,
+        new.message,
+        new.link_user_id,
+        new.expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Consume a wallet challenge; like OAuth states, each nonce is good for one attempt
+pub async fn take_wallet_challenge(pool: &PgPool, nonce: &str) -> sqlx::Result<Option<WalletChallenge>> {
+    let row = sqlx::query_as!(
+        WalletChallenge,
+        r#"
+        DELETE FROM wallet_challenges
+        WHERE nonce = $1
+        RETURNING nonce, address, message, link_user_id, created_at, expires_at
+        "#,
+        nonce
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+pub async fn delete_expired_wallet_challenges(pool: &PgPool) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!("DELETE FROM wallet_challenges WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(rows_affected)
+}
+
 //
 // ─── INVENTORY ────────────────────────────────────────────────────────────────
 //
+// Every query takes the caller's organization: rows of other tenants are
+// invisible, whatever id is asked for.
 
 pub async fn create_inventory(
     pool: &PgPool,
+    org_id: Uuid,
     owner_id: Uuid,
-    name: &str,
-    description: Option<&str>,
-    quantity: i32,
-    location: Option<&str>,
-    token_id: Option<&str>,
+    fields: InventoryFields<'_>,
 ) -> sqlx::Result<Inventory> {
     let inventory = sqlx::query_as!(
         Inventory,
         r#"
-        INSERT INTO inventory (id, owner_id, name, description, quantity, location, token_id)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
-        RETURNING id, owner_id, name, description, quantity, location, token_id, created_at
+        INSERT INTO inventory (id, org_id, owner_id, name, description, quantity, location, token_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, org_id, owner_id, name, description, quantity, location, token_id, created_at, version, updated_at
         "#,
         Uuid::new_v4(),
+        org_id,
         owner_id,
-        name,
-        description,
-        quantity,
-        location,
-        token_id
+        fields.name,
+        fields.description,
+        fields.quantity,
+        fields.location,
+        fields.token_id
     )
     .fetch_one(pool)
     .await?;
     Ok(inventory)
 }
 
-pub async fn get_inventory(pool: &PgPool) -> sqlx::Result<Vec<Inventory>> {
-    let items = sqlx::query_as!(Inventory, "SELECT * FROM inventory ORDER BY created_at DESC")
-        .fetch_all(pool)
-        .await?;
+/// Items in the organization that `user_id` owns or has been shared
+pub async fn get_inventory(pool: &PgPool, org_id: Uuid, user_id: Uuid) -> sqlx::Result<Vec<Inventory>> {
+    let items = sqlx::query_as!(
+        Inventory,
+        r#"
+        SELECT id, org_id, owner_id, name, description, quantity, location, token_id, created_at, version, updated_at
+        FROM inventory
+        WHERE org_id = $1
+          AND (owner_id = $2 OR id IN (SELECT inventory_id FROM inventory_shares WHERE user_id = $2))
+        ORDER BY created_at
+        "#,
+        org_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
     Ok(items)
 }
 
+pub async fn get_inventory_item(pool: &PgPool, org_id: Uuid, inventory_id: Uuid) -> sqlx::Result<Option<Inventory>> {
+    let item = sqlx::query_as!(
+        Inventory,
+        r#"
+        SELECT id, org_id, owner_id, name, description, quantity, location, token_id, created_at, version, updated_at
+        FROM inventory
+        WHERE id = $1 AND org_id = $2
+        "#,
+        inventory_id,
+        org_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(item)
+}
+
+/// Overwrite the item if it's still at `version`; `None` once it has moved on (or gone)
+pub async fn update_inventory(
+    pool: &PgPool,
+    org_id: Uuid,
+    inventory_id: Uuid,
+    version: i32,
+    fields: InventoryFields<'_>,
+) -> sqlx::Result<Option<Inventory>> {
+    let item = sqlx::query_as!(
+        Inventory,
+        r#"
+        UPDATE inventory
+        SET name = $4, description = $5, quantity = $6, location = $7, token_id = $8
+        WHERE id = $1 AND org_id = $2 AND version = $3
+        RETURNING id, org_id, owner_id, name, description, quantity, location, token_id, created_at, version, updated_at
+        "#,
+        inventory_id,
+        org_id,
+        version,
+        fields.name,
+        fields.description,
+        fields.quantity,
+        fields.location,
+        fields.token_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(item)
+}
+
 pub async fn update_inventory_quantity(
     pool: &PgPool,
+    org_id: Uuid,
     inventory_id: Uuid,
     new_quantity: i32,
-) -> sqlx::Result<Inventory> {
+) -> sqlx::Result<Option<Inventory>> {
     let item = sqlx::query_as!(
         Inventory,
         r#"
         UPDATE inventory
-        SET quantity = $2
-        WHERE id = $1
-        RETURNING id, owner_id, name, description, quantity, location, token_id, created_at
+        SET quantity = $3
+        WHERE id = $1 AND org_id = $2
+        RETURNING id, org_id, owner_id, name, description, quantity, location, token_id, created_at, version, updated_at
         "#,
         inventory_id,
+        org_id,
         new_quantity
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await?;
     Ok(item)
 }
 
-pub async fn delete_inventory(pool: &PgPool, inventory_id: Uuid) -> sqlx::Result<u64> {
-    let rows_affected = sqlx::query!("DELETE FROM inventory WHERE id = $1", inventory_id)
+pub async fn delete_inventory(pool: &PgPool, org_id: Uuid, inventory_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!("DELETE FROM inventory WHERE id = $1 AND org_id = $2", inventory_id, org_id)
         .execute(pool)
         .await?
         .rows_affected();
@@ -135,60 +510,1558 @@ pub async fn delete_inventory(pool: &PgPool, inventory_id: Uuid) -> sqlx::Result
 
 pub async fn create_package(
     pool: &PgPool,
+    org_id: Uuid,
     owner_id: Uuid,
-    inventory_item_id: Option<Uuid>,
-    destination: &str,
+    fields: PackageFields<'_>,
 ) -> sqlx::Result<Package> {
     let package = sqlx::query_as!(
         Package,
         r#"
-        INSERT INTO packages (id, owner_id, inventory_item_id, destination)
-        VALUES ($1, $2, $3, $4)
-        RETURNING id, owner_id, inventory_item_id, status, destination, nft_token, created_at
+        INSERT INTO packages (id, org_id, owner_id, inventory_item_id, status, destination, nft_token)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, org_id, owner_id, inventory_item_id, status, destination, nft_token, created_at, version, updated_at
         "#,
         Uuid::new_v4(),
+        org_id,
         owner_id,
-        inventory_item_id,
-        destination
+        fields.inventory_item_id,
+        fields.status,
+        fields.destination,
+        fields.nft_token
     )
     .fetch_one(pool)
     .await?;
     Ok(package)
 }
 
-pub async fn get_packages(pool: &PgPool) -> sqlx::Result<Vec<Package>> {
-    let packages = sqlx::query_as!(Package, "SELECT * FROM packages ORDER BY created_at DESC")
-        .fetch_all(pool)
-        .await?;
+/// Packages in the organization that `user_id` owns or has been shared
+pub async fn get_packages(pool: &PgPool, org_id: Uuid, user_id: Uuid) -> sqlx::Result<Vec<Package>> {
+    let packages = sqlx::query_as!(
+        Package,
+        r#"
+        SELECT id, org_id, owner_id, inventory_item_id, status, destination, nft_token, created_at, version, updated_at
+        FROM packages
+        WHERE org_id = $1
+          AND (owner_id = $2 OR id IN (SELECT package_id FROM package_shares WHERE user_id = $2))
+        ORDER BY created_at
+        "#,
+        org_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
     Ok(packages)
 }
 
+pub async fn get_package(pool: &PgPool, org_id: Uuid, package_id: Uuid) -> sqlx::Result<Option<Package>> {
+    let package = sqlx::query_as!(
+        Package,
+        r#"
+        SELECT id, org_id, owner_id, inventory_item_id, status, destination, nft_token, created_at, version, updated_at
+        FROM packages
+        WHERE id = $1 AND org_id = $2
+        "#,
+        package_id,
+        org_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(package)
+}
+
+/// Overwrite the package if it's still at `version`; `None` once it has moved on (or gone)
+pub async fn update_package(
+    pool: &PgPool,
+    org_id: Uuid,
+    package_id: Uuid,
+    version: i32,
+    fields: PackageFields<'_>,
+) -> sqlx::Result<Option<Package>> {
+    let package = sqlx::query_as!(
+        Package,
+        r#"
+        UPDATE packages
+        SET inventory_item_id = $4, status = $5, destination = $6, nft_token = $7
+        WHERE id = $1 AND org_id = $2 AND version = $3
+        RETURNING id, org_id, owner_id, inventory_item_id, status, destination, nft_token, created_at, version, updated_at
+        "#,
+        package_id,
+        org_id,
+        version,
+        fields.inventory_item_id,
+        fields.status,
+        fields.destination,
+        fields.nft_token
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(package)
+}
+
 pub async fn update_package_status(
     pool: &PgPool,
+    org_id: Uuid,
     package_id: Uuid,
     new_status: &str,
-) -> sqlx::Result<Package> {
+) -> sqlx::Result<Option<Package>> {
     let package = sqlx::query_as!(
         Package,
         r#"
         UPDATE packages
-        SET status = $2
-        WHERE id = $1
-        RETURNING id, owner_id, inventory_item_id, status, destination, nft_token, created_at
+        SET status = $3
+        WHERE id = $1 AND org_id = $2
+        RETURNING id, org_id, owner_id, inventory_item_id, status, destination, nft_token, created_at, version, updated_at
         "#,
         package_id,
+        org_id,
         new_status
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await?;
     Ok(package)
 }
 
-pub async fn delete_package(pool: &PgPool, package_id: Uuid) -> sqlx::Result<u64> {
-    let rows_affected = sqlx::query!("DELETE FROM packages WHERE id = $1", package_id)
+pub async fn delete_package(pool: &PgPool, org_id: Uuid, package_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!("DELETE FROM packages WHERE id = $1 AND org_id = $2", package_id, org_id)
         .execute(pool)
         .await?
         .rows_affected();
     Ok(rows_affected)
 }
 
+//
+// ─── SHARES ────────────────────────────────────────────────────────────────
+//
+
+pub async fn get_inventory_shares(pool: &PgPool, inventory_id: Uuid) -> sqlx::Result<Vec<Share>> {
+    let shares = sqlx::query_as!(
+        Share,
+        r#"
+        SELECT inventory_id AS resource_id, user_id, can_write, granted_by, granted_at
+        FROM inventory_shares
+        WHERE inventory_id = $1
+        ORDER BY granted_at
+        "#,
+        inventory_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(shares)
+}
+
+pub async fn get_inventory_share(pool: &PgPool, inventory_id: Uuid, user_id: Uuid) -> sqlx::Result<Option<Share>> {
+    let share = sqlx::query_as!(
+        Share,
+        r#"
+        SELECT inventory_id AS resource_id, user_id, can_write, granted_by, granted_at
+        FROM inventory_shares
+        WHERE inventory_id = $1 AND user_id = $2
+        "#,
+        inventory_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(share)
+}
+
+/// Grant or change a user's access to an inventory item
+pub async fn share_inventory(
+    pool: &PgPool,
+    inventory_id: Uuid,
+    user_id: Uuid,
+    can_write: bool,
+    granted_by: Uuid,
+) -> sqlx::Result<Share> {
+    let share = sqlx::query_as!(
+        Share,
+        r#"
+        INSERT INTO inventory_shares (inventory_id, user_id, can_write, granted_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (inventory_id, user_id)
+        DO UPDATE SET can_write = EXCLUDED.can_write, granted_by = EXCLUDED.granted_by, granted_at = NOW()
+        RETURNING inventory_id AS resource_id, user_id, can_write, granted_by, granted_at
+        "#,
+        inventory_id,
+        user_id,
+        can_write,
+        granted_by
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(share)
+}
+
+pub async fn unshare_inventory(pool: &PgPool, inventory_id: Uuid, user_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM inventory_shares WHERE inventory_id = $1 AND user_id = $2",
+        inventory_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn get_package_shares(pool: &PgPool, package_id: Uuid) -> sqlx::Result<Vec<Share>> {
+    let shares = sqlx::query_as!(
+        Share,
+        r#"
+        SELECT package_id AS resource_id, user_id, can_write, granted_by, granted_at
+        FROM package_shares
+        WHERE package_id = $1
+        ORDER BY granted_at
+        "#,
+        package_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(shares)
+}
+
+pub async fn get_package_share(pool: &PgPool, package_id: Uuid, user_id: Uuid) -> sqlx::Result<Option<Share>> {
+    let share = sqlx::query_as!(
+        Share,
+        r#"
+        SELECT package_id AS resource_id, user_id, can_write, granted_by, granted_at
+        FROM package_shares
+        WHERE package_id = $1 AND user_id = $2
+        "#,
+        package_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(share)
+}
+
+/// Grant or change a user's access to a package
+pub async fn share_package(
+    pool: &PgPool,
+    package_id: Uuid,
+    user_id: Uuid,
+    can_write: bool,
+    granted_by: Uuid,
+) -> sqlx::Result<Share> {
+    let share = sqlx::query_as!(
+        Share,
+        r#"
+        INSERT INTO package_shares (package_id, user_id, can_write, granted_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (package_id, user_id)
+        DO UPDATE SET can_write = EXCLUDED.can_write, granted_by = EXCLUDED.granted_by, granted_at = NOW()
+        RETURNING package_id AS resource_id, user_id, can_write, granted_by, granted_at
+        "#,
+        package_id,
+        user_id,
+        can_write,
+        granted_by
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(share)
+}
+
+pub async fn unshare_package(pool: &PgPool, package_id: Uuid, user_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM package_shares WHERE package_id = $1 AND user_id = $2",
+        package_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+//
+// ─── MISSIONS ────────────────────────────────────────────────────────────────
+//
+// Missions are scoped to an organization like inventory; assignments, task
+// updates and beacons hang off a mission the caller already loaded.
+
+pub async fn create_mission(
+    pool: &PgPool,
+    org_id: Uuid,
+    name: &str,
+    description: Option<&str>,
+    kem_public_key: Option<&str>,
+    checkin_interval_secs: i32,
+) -> sqlx::Result<Mission> {
+    let mission = sqlx::query_as!(
+        Mission,
+        r#"
+        INSERT INTO missions (id, org_id, name, description, kem_public_key, checkin_interval_secs)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, org_id, name, description, status, kem_public_key, checkin_interval_secs, created_at
+        "#,
+        Uuid::new_v4(),
+        org_id,
+        name,
+        description,
+        kem_public_key,
+        checkin_interval_secs
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(mission)
+}
+
+pub async fn get_missions(pool: &PgPool, org_id: Uuid) -> sqlx::Result<Vec<Mission>> {
+    let missions = sqlx::query_as!(
+        Mission,
+        r#"
+        SELECT id, org_id, name, description, status, kem_public_key, checkin_interval_secs, created_at
+        FROM missions
+        WHERE org_id = $1
+        ORDER BY created_at DESC
+        "#,
+        org_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(missions)
+}
+
+pub async fn get_mission(pool: &PgPool, org_id: Uuid, mission_id: Uuid) -> sqlx::Result<Option<Mission>> {
+    let mission = sqlx::query_as!(
+        Mission,
+        r#"
+        SELECT id, org_id, name, description, status, kem_public_key, checkin_interval_secs, created_at
+        FROM missions
+        WHERE id = $1 AND org_id = $2
+        "#,
+        mission_id,
+        org_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(mission)
+}
+
+pub async fn update_mission_status(
+    pool: &PgPool,
+    org_id: Uuid,
+    mission_id: Uuid,
+    new_status: &str,
+) -> sqlx::Result<Option<Mission>> {
+    let mission = sqlx::query_as!(
+        Mission,
+        r#"
+        UPDATE missions
+        SET status = $3
+        WHERE id = $1 AND org_id = $2
+        RETURNING id, org_id, name, description, status, kem_public_key, checkin_interval_secs, created_at
+        "#,
+        mission_id,
+        org_id,
+        new_status
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(mission)
+}
+
+pub async fn delete_mission(pool: &PgPool, org_id: Uuid, mission_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!("DELETE FROM missions WHERE id = $1 AND org_id = $2", mission_id, org_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(rows_affected)
+}
+
+// Assignments
+pub async fn assign_user_to_mission(
+    pool: &PgPool,
+    mission_id: Uuid,
+    user_id: Uuid,
+    role: &str,
+) -> sqlx::Result<MissionAssignment> {
+    let assignment = sqlx::query_as!(
+        MissionAssignment,
+        r#"
+        INSERT INTO mission_assignments (mission_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (mission_id, user_id) DO UPDATE SET role = EXCLUDED.role
+        RETURNING mission_id, user_id, role, assigned_at
+        "#,
+        mission_id,
+        user_id,
+        role
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(assignment)
+}
+
+pub async fn unassign_user_from_mission(
+    pool: &PgPool,
+    mission_id: Uuid,
+    user_id: Uuid,
+) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM mission_assignments WHERE mission_id = $1 AND user_id = $2",
+        mission_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn get_mission_assignments(
+    pool: &PgPool,
+    mission_id: Uuid,
+) -> sqlx::Result<Vec<MissionAssignment>> {
+    let assignments = sqlx::query_as!(
+        MissionAssignment,
+        "SELECT * FROM mission_assignments WHERE mission_id = $1 ORDER BY assigned_at",
+        mission_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(assignments)
+}
+
+pub async fn is_assigned_to_mission(
+    pool: &PgPool,
+    mission_id: Uuid,
+    user_id: Uuid,
+) -> sqlx::Result<bool> {
+    let assigned = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM mission_assignments WHERE mission_id = $1 AND user_id = $2
+        ) AS "assigned!"
+        "#,
+        mission_id,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(assigned)
+}
+
+// Task updates
+pub async fn create_task_update(
+    pool: &PgPool,
+    mission_id: Uuid,
+    author_id: Uuid,
+    kem_ciphertext: Option<&str>,
+    nonce: &str,
+    ciphertext: &str,
+    associated_data: Option<&str>,
+) -> sqlx::Result<TaskUpdate> {
+    let update = sqlx::query_as!(
+        TaskUpdate,
+        r#"
+        INSERT INTO task_updates (id, mission_id, author_id, kem_ciphertext, nonce, ciphertext, associated_data)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, mission_id, author_id, kem_ciphertext, nonce, ciphertext, associated_data, created_at
+        "#,
+        Uuid::new_v4(),
+        mission_id,
+        author_id,
+        kem_ciphertext,
+        nonce,
+        ciphertext,
+        associated_data
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(update)
+}
+
+pub async fn get_task_updates(pool: &PgPool, mission_id: Uuid) -> sqlx::Result<Vec<TaskUpdate>> {
+    let updates = sqlx::query_as!(
+        TaskUpdate,
+        "SELECT * FROM task_updates WHERE mission_id = $1 ORDER BY created_at",
+        mission_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(updates)
+}
+
+// Beacons
+pub async fn record_beacon_checkin(
+    pool: &PgPool,
+    mission_id: Uuid,
+    user_id: Uuid,
+    location: Option<&str>,
+) -> sqlx::Result<Beacon> {
+    let beacon = sqlx::query_as!(
+        Beacon,
+        r#"
+        INSERT INTO beacons (mission_id, user_id, location)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (mission_id, user_id) DO UPDATE
+        SET last_seen_at = NOW(),
+            location = EXCLUDED.location,
+            checkins = beacons.checkins + 1,
+            missed_alerted_at = NULL
+        RETURNING mission_id, user_id, last_seen_at, location, checkins, missed_alerted_at
+        "#,
+        mission_id,
+        user_id,
+        location
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(beacon)
+}
+
+pub async fn get_beacons(pool: &PgPool, mission_id: Uuid) -> sqlx::Result<Vec<Beacon>> {
+    let beacons = sqlx::query_as!(
+        Beacon,
+        "SELECT * FROM beacons WHERE mission_id = $1 ORDER BY last_seen_at DESC",
+        mission_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(beacons)
+}
+
+/// Beacons past their mission's check-in interval that haven't been alerted yet
+pub async fn get_missed_beacons(pool: &PgPool) -> sqlx::Result<Vec<Beacon>> {
+    let beacons = sqlx::query_as!(
+        Beacon,
+        r#"
+        SELECT b.mission_id, b.user_id, b.last_seen_at, b.location, b.checkins, b.missed_alerted_at
+        FROM beacons b
+        JOIN missions m ON m.id = b.mission_id
+        WHERE m.status = 'Active'
+          AND b.missed_alerted_at IS NULL
+          AND b.last_seen_at + make_interval(secs => m.checkin_interval_secs) < NOW()
+        ORDER BY b.last_seen_at
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(beacons)
+}
+
+pub async fn mark_beacon_alerted(pool: &PgPool, mission_id: Uuid, user_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "UPDATE beacons SET missed_alerted_at = NOW() WHERE mission_id = $1 AND user_id = $2",
+        mission_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+//
+// ─── ISSUED TOKENS ────────────────────────────────────────────────────────────────
+//
+
+pub async fn record_issued_token(
+    pool: &PgPool,
+    jti: Uuid,
+    subject: &str,
+    provider: &str,
+    session_id: Option<Uuid>,
+    expires_at: DateTime<Utc>,
+) -> sqlx::Result<IssuedToken> {
+    let token = sqlx::query_as!(
+        IssuedToken,
+        r#"
+        INSERT INTO issued_tokens (jti, subject, provider, session_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING jti, subject, provider, issued_at, expires_at, session_id, revoked_at
+        "#,
+        jti,
+        subject,
+        provider,
+        session_id,
+        expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(token)
+}
+
+/// Denylist check for access tokens
+pub async fn is_token_revoked(pool: &PgPool, jti: Uuid) -> sqlx::Result<bool> {
+    let revoked = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM issued_tokens WHERE jti = $1 AND revoked_at IS NOT NULL) AS "revoked!""#,
+        jti
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(revoked)
+}
+
+pub async fn revoke_issued_token(pool: &PgPool, jti: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "UPDATE issued_tokens SET revoked_at = NOW() WHERE jti = $1 AND revoked_at IS NULL",
+        jti
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+//
+// ─── REFRESH TOKENS ───────────────────────────────────────────────────────────────
+//
+
+pub async fn create_refresh_token(pool: &PgPool, new: NewRefreshToken<'_>) -> sqlx::Result<RefreshToken> {
+    let token = sqlx::query_as!(
+        RefreshToken,
+        r#"
+        INSERT INTO refresh_tokens (id, session_id, user_id, provider, token_hash, post_quantum, mfa_at, org_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, session_id, user_id, provider, token_hash, issued_at, expires_at, used_at, revoked_at, post_quantum, mfa_at, org_id
+        "#,
+        Uuid::new_v4(),
+        new.session_id,
+        new.user_id,
+        new.provider,
+        new.token_hash,
+        new.post_quantum,
+        new.mfa_at,
+        new.org_id,
+        new.expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(token)
+}
+
+pub async fn get_refresh_token_by_hash(pool: &PgPool, token_hash: &str) -> sqlx::Result<Option<RefreshToken>> {
+    let token = sqlx::query_as!(
+        RefreshToken,
+        r#"
+        SELECT id, session_id, user_id, provider, token_hash, issued_at, expires_at, used_at, revoked_at, post_quantum, mfa_at, org_id
+        FROM refresh_tokens
+        WHERE token_hash = $1
+        "#,
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(token)
+}
+
+/// Mark a refresh token as rotated; false if it was already used or revoked
+pub async fn mark_refresh_token_used(pool: &PgPool, id: Uuid) -> sqlx::Result<bool> {
+    let rows_affected = sqlx::query!(
+        "UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1 AND used_at IS NULL AND revoked_at IS NULL",
+        id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+/// Revoke every refresh and access token minted for one login session
+pub async fn revoke_session(pool: &PgPool, session_id: Uuid) -> sqlx::Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let refresh = sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE session_id = $1 AND revoked_at IS NULL",
+        session_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let access = sqlx::query!(
+        "UPDATE issued_tokens SET revoked_at = NOW() WHERE session_id = $1 AND revoked_at IS NULL",
+        session_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+    Ok(refresh + access)
+}
+
+/// Revoke all of a user's sessions; returns (refresh tokens, access tokens) revoked
+pub async fn revoke_user_sessions(pool: &PgPool, user_id: Uuid) -> sqlx::Result<(u64, u64)> {
+    let mut tx = pool.begin().await?;
+
+    let refresh = sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let access = sqlx::query!(
+        "UPDATE issued_tokens SET revoked_at = NOW() WHERE subject = $1 AND revoked_at IS NULL",
+        user_id.to_string()
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+    Ok((refresh, access))
+}
+
+pub async fn delete_expired_refresh_tokens(pool: &PgPool) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!("DELETE FROM refresh_tokens WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(rows_affected)
+}
+
+//
+// ─── ROLES ────────────────────────────────────────────────────────────────
+//
+
+pub async fn get_roles(pool: &PgPool) -> sqlx::Result<Vec<Role>> {
+    let roles = sqlx::query_as!(
+        Role,
+        r#"
+        SELECT r.name, r.description, r.builtin, r.created_at,
+               COALESCE(array_agg(p.permission ORDER BY p.permission) FILTER (WHERE p.permission IS NOT NULL), '{}') AS "permissions!"
+        FROM roles r
+        LEFT JOIN role_permissions p ON p.role = r.name
+        GROUP BY r.name
+        ORDER BY r.builtin DESC, r.name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(roles)
+}
+
+pub async fn get_role(pool: &PgPool, name: &str) -> sqlx::Result<Option<Role>> {
+    let role = sqlx::query_as!(
+        Role,
+        r#"
+        SELECT r.name, r.description, r.builtin, r.created_at,
+               COALESCE(array_agg(p.permission ORDER BY p.permission) FILTER (WHERE p.permission IS NOT NULL), '{}') AS "permissions!"
+        FROM roles r
+        LEFT JOIN role_permissions p ON p.role = r.name
+        WHERE r.name = $1
+        GROUP BY r.name
+        "#,
+        name
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(role)
+}
+
+pub async fn create_role(pool: &PgPool, name: &str, description: &str, permissions: &[String]) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("INSERT INTO roles (name, description) VALUES ($1, $2)", name, description)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO role_permissions (role, permission) SELECT $1, UNNEST($2::text[])",
+        name,
+        permissions
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Replace a custom role's permissions; false if there is no such custom role
+pub async fn set_role_permissions(pool: &PgPool, name: &str, permissions: &[String]) -> sqlx::Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let exists = sqlx::query_scalar!(
+        "SELECT name FROM roles WHERE name = $1 AND builtin = FALSE FOR UPDATE",
+        name
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .is_some();
+    if !exists {
+        return Ok(false);
+    }
+
+    sqlx::query!("DELETE FROM role_permissions WHERE role = $1", name)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO role_permissions (role, permission) SELECT $1, UNNEST($2::text[])",
+        name,
+        permissions
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Delete a custom role (and its assignments); built-in roles are kept
+pub async fn delete_role(pool: &PgPool, name: &str) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!("DELETE FROM roles WHERE name = $1 AND builtin = FALSE", name)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn get_user_roles(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Vec<UserRole>> {
+    let roles = sqlx::query_as!(
+        UserRole,
+        "SELECT user_id, role, granted_by, granted_at FROM user_roles WHERE user_id = $1 ORDER BY role",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(roles)
+}
+
+/// Grant `role`; false if the user already had it
+pub async fn assign_role(pool: &PgPool, user_id: Uuid, role: &str, granted_by: Option<Uuid>) -> sqlx::Result<bool> {
+    let rows_affected = sqlx::query!(
+        r#"
+        INSERT INTO user_roles (user_id, role, granted_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, role) DO NOTHING
+        "#,
+        user_id,
+        role,
+        granted_by
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+pub async fn unassign_role(pool: &PgPool, user_id: Uuid, role: &str) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM user_roles WHERE user_id = $1 AND role = $2",
+        user_id,
+        role
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn count_role_members(pool: &PgPool, role: &str) -> sqlx::Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM user_roles WHERE role = $1"#,
+        role
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(count)
+}
+
+/// Every permission granted to a user through any of their roles
+pub async fn get_user_permissions(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Vec<String>> {
+    let permissions = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT p.permission
+        FROM user_roles ur
+        JOIN role_permissions p ON p.role = ur.role
+        WHERE ur.user_id = $1
+        ORDER BY p.permission
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(permissions)
+}
+
+//
+// ─── ORGANIZATIONS ────────────────────────────────────────────────────────────────
+//
+
+/// Create an organization with `owner_id` as its first owner
+pub async fn create_organization(pool: &PgPool, slug: &str, name: &str, owner_id: Uuid) -> sqlx::Result<Organization> {
+    let mut tx = pool.begin().await?;
+
+    let org = sqlx::query_as!(
+        Organization,
+        r#"
+        INSERT INTO organizations (id, slug, name)
+        VALUES ($1, $2, $3)
+        RETURNING id, slug, name, created_at
+        "#,
+        Uuid::new_v4(),
+        slug,
+        name
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO org_memberships (org_id, user_id, role) VALUES ($1, $2, 'owner')",
+        org.id,
+        owner_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(org)
+}
+
+pub async fn get_organization(pool: &PgPool, org_id: Uuid) -> sqlx::Result<Option<Organization>> {
+    let org = sqlx::query_as!(
+        Organization,
+        "SELECT id, slug, name, created_at FROM organizations WHERE id = $1",
+        org_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(org)
+}
+
+/// Organizations the user belongs to, oldest membership first
+pub async fn get_user_orgs(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Vec<UserOrg>> {
+    let orgs = sqlx::query_as!(
+        UserOrg,
+        r#"
+        SELECT o.id, o.slug, o.name, m.role, m.joined_at
+        FROM org_memberships m
+        JOIN organizations o ON o.id = m.org_id
+        WHERE m.user_id = $1
+        ORDER BY m.joined_at, o.slug
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(orgs)
+}
+
+pub async fn get_membership(pool: &PgPool, org_id: Uuid, user_id: Uuid) -> sqlx::Result<Option<OrgMembership>> {
+    let membership = sqlx::query_as!(
+        OrgMembership,
+        "SELECT org_id, user_id, role, joined_at FROM org_memberships WHERE org_id = $1 AND user_id = $2",
+        org_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(membership)
+}
+
+/// Where a session starts when it hasn't picked an organization: the oldest membership
+pub async fn get_default_membership(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Option<OrgMembership>> {
+    let membership = sqlx::query_as!(
+        OrgMembership,
+        r#"
+        SELECT org_id, user_id, role, joined_at
+        FROM org_memberships
+        WHERE user_id = $1
+        ORDER BY joined_at, org_id
+        LIMIT 1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(membership)
+}
+
+pub async fn get_org_members(pool: &PgPool, org_id: Uuid) -> sqlx::Result<Vec<OrgMembership>> {
+    let members = sqlx::query_as!(
+        OrgMembership,
+        "SELECT org_id, user_id, role, joined_at FROM org_memberships WHERE org_id = $1 ORDER BY joined_at",
+        org_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(members)
+}
+
+/// Add a member, or change the role of an existing one
+pub async fn set_org_member(pool: &PgPool, org_id: Uuid, user_id: Uuid, role: &str) -> sqlx::Result<OrgMembership> {
+    let membership = sqlx::query_as!(
+        OrgMembership,
+        r#"
+        INSERT INTO org_memberships (org_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (org_id, user_id) DO UPDATE SET role = EXCLUDED.role
+        RETURNING org_id, user_id, role, joined_at
+        "#,
+        org_id,
+        user_id,
+        role
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(membership)
+}
+
+pub async fn remove_org_member(pool: &PgPool, org_id: Uuid, user_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM org_memberships WHERE org_id = $1 AND user_id = $2",
+        org_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn count_org_owners(pool: &PgPool, org_id: Uuid) -> sqlx::Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM org_memberships WHERE org_id = $1 AND role = 'owner'"#,
+        org_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(count)
+}
+
+//
+// ─── SERVICE ACCOUNTS ────────────────────────────────────────────────────────────────
+//
+
+/// Create the account, the users row backing it and its membership in `org_id`
+pub async fn create_service_account(
+    pool: &PgPool,
+    org_id: Uuid,
+    name: &str,
+    description: &str,
+    created_by: Option<Uuid>,
+) -> sqlx::Result<ServiceAccount> {
+    let mut tx = pool.begin().await?;
+    let id = Uuid::new_v4();
+
+    sqlx::query!(
+        "INSERT INTO users (id, username, email, created_at) VALUES ($1, $2, $3, $4)",
+        id,
+        name,
+        format!("{}@service-accounts.invalid", name),
+        Utc::now()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let account = sqlx::query_as!(
+        ServiceAccount,
+        r#"
+        INSERT INTO service_accounts (id, name, description, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, name, description, created_by, created_at, disabled_at
+        "#,
+        id,
+        name,
+        description,
+        created_by
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO org_memberships (org_id, user_id, role) VALUES ($1, $2, 'member')",
+        org_id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(account)
+}
+
+/// Service accounts working in `org_id`
+pub async fn get_service_accounts(pool: &PgPool, org_id: Uuid) -> sqlx::Result<Vec<ServiceAccount>> {
+    let accounts = sqlx::query_as!(
+        ServiceAccount,
+        r#"
+        SELECT id, name, description, created_by, created_at, disabled_at
+        FROM service_accounts
+        WHERE id IN (SELECT user_id FROM org_memberships WHERE org_id = $1)
+        ORDER BY name
+        "#,
+        org_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(accounts)
+}
+
+pub async fn get_service_account(pool: &PgPool, org_id: Uuid, id: Uuid) -> sqlx::Result<Option<ServiceAccount>> {
+    let account = sqlx::query_as!(
+        ServiceAccount,
+        r#"
+        SELECT id, name, description, created_by, created_at, disabled_at
+        FROM service_accounts
+        WHERE id = $1 AND id IN (SELECT user_id FROM org_memberships WHERE org_id = $2)
+        "#,
+        id,
+        org_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(account)
+}
+
+pub async fn get_service_account_by_name(pool: &PgPool, name: &str) -> sqlx::Result<Option<ServiceAccount>> {
+    let account = sqlx::query_as!(
+        ServiceAccount,
+        "SELECT id, name, description, created_by, created_at, disabled_at FROM service_accounts WHERE name = $1",
+        name
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(account)
+}
+
+/// Disable the account and revoke its keys; false if it was already disabled
+pub async fn disable_service_account(pool: &PgPool, id: Uuid) -> sqlx::Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let disabled = sqlx::query!(
+        "UPDATE service_accounts SET disabled_at = NOW() WHERE id = $1 AND disabled_at IS NULL",
+        id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    sqlx::query!(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE service_account_id = $1 AND revoked_at IS NULL",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(disabled == 1)
+}
+
+pub async fn create_api_key(
+    pool: &PgPool,
+    service_account_id: Uuid,
+    prefix: &str,
+    key_hash: &str,
+    scopes: &[String],
+    expires_at: Option<DateTime<Utc>>,
+) -> sqlx::Result<ApiKey> {
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (id, service_account_id, prefix, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, service_account_id, prefix, key_hash, scopes, created_at, expires_at, last_used_at, revoked_at
+        "#,
+        Uuid::new_v4(),
+        service_account_id,
+        prefix,
+        key_hash,
+        scopes,
+        expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(key)
+}
+
+pub async fn get_api_keys(pool: &PgPool, service_account_id: Uuid) -> sqlx::Result<Vec<ApiKey>> {
+    let keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, service_account_id, prefix, key_hash, scopes, created_at, expires_at, last_used_at, revoked_at
+        FROM api_keys
+        WHERE service_account_id = $1
+        ORDER BY created_at
+        "#,
+        service_account_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(keys)
+}
+
+pub async fn get_api_key_by_prefix(pool: &PgPool, prefix: &str) -> sqlx::Result<Option<ApiKey>> {
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, service_account_id, prefix, key_hash, scopes, created_at, expires_at, last_used_at, revoked_at
+        FROM api_keys
+        WHERE prefix = $1
+        "#,
+        prefix
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(key)
+}
+
+/// Look up a presented key; keys of disabled accounts are not found
+pub async fn get_active_api_key_by_hash(pool: &PgPool, key_hash: &str) -> sqlx::Result<Option<ApiKey>> {
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT k.id, k.service_account_id, k.prefix, k.key_hash, k.scopes,
+               k.created_at, k.expires_at, k.last_used_at, k.revoked_at
+        FROM api_keys k
+        JOIN service_accounts a ON a.id = k.service_account_id
+        WHERE k.key_hash = $1 AND a.disabled_at IS NULL
+        "#,
+        key_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(key)
+}
+
+/// Record a use, at most once a minute per key
+pub async fn touch_api_key(pool: &PgPool, id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        r#"
+        UPDATE api_keys SET last_used_at = NOW()
+        WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+        "#,
+        id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn revoke_api_key(pool: &PgPool, service_account_id: Uuid, key_id: Uuid) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        r#"
+        UPDATE api_keys SET revoked_at = NOW()
+        WHERE id = $1 AND service_account_id = $2 AND revoked_at IS NULL
+        "#,
+        key_id,
+        service_account_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+//
+// ─── MFA ────────────────────────────────────────────────────────────────
+//
+
+pub async fn get_totp(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Option<UserTotp>> {
+    let totp = sqlx::query_as!(
+        UserTotp,
+        "SELECT user_id, secret, confirmed_at, last_used_step, created_at FROM user_totp WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(totp)
+}
+
+/// Start (or restart) enrollment; false if TOTP is already confirmed
+pub async fn set_pending_totp(pool: &PgPool, user_id: Uuid, secret: &str) -> sqlx::Result<bool> {
+    let rows_affected = sqlx::query!(
+        r#"
+        INSERT INTO user_totp (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+        WHERE user_totp.confirmed_at IS NULL
+        "#,
+        user_id,
+        secret
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+/// Accept a code's time step, once: false if it (or a later one) was used already.
+/// Confirms a pending enrollment.
+pub async fn use_totp_step(pool: &PgPool, user_id: Uuid, step: i64) -> sqlx::Result<bool> {
+    let rows_affected = sqlx::query!(
+        r#"
+        UPDATE user_totp
+        SET last_used_step = $2, confirmed_at = COALESCE(confirmed_at, NOW())
+        WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        user_id,
+        step
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+/// Remove TOTP and recovery codes
+pub async fn delete_mfa(pool: &PgPool, user_id: Uuid) -> sqlx::Result<u64> {
+    let mut tx = pool.begin().await?;
+    let rows_affected = sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(rows_affected)
+}
+
+/// Swap the user's recovery codes for a new set
+pub async fn replace_recovery_codes(pool: &PgPool, user_id: Uuid, code_hashes: &[String]) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO mfa_recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::text[])",
+        user_id,
+        code_hashes
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Spend a recovery code; false if unknown or already used
+pub async fn use_recovery_code(pool: &PgPool, user_id: Uuid, code_hash: &str) -> sqlx::Result<bool> {
+    let rows_affected = sqlx::query!(
+        r#"
+        UPDATE mfa_recovery_codes SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        code_hash
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+pub async fn count_recovery_codes_left(pool: &PgPool, user_id: Uuid) -> sqlx::Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(count)
+}
+
+//
+// ─── AUDIT ────────────────────────────────────────────────────────────────
+//
+
+/// Advisory lock key held while appending to the audit chain
+const AUDIT_CHAIN_LOCK: i64 = 0x6175_6469_745f_6368; // "audit_ch"
+
+/// Append an event to the audit trail, chained onto the latest one
+pub async fn append_audit_event(pool: &PgPool, event: NewAuditEvent<'_>) -> sqlx::Result<AuditEvent> {
+    let mut tx = pool.begin().await?;
+
+    // one writer at a time, so ids and the hash chain agree on the order;
+    // readers of audit_events aren't held up, and the lock goes with the transaction
+    sqlx::query!("SELECT pg_advisory_xact_lock($1)", AUDIT_CHAIN_LOCK)
+        .execute(&mut *tx)
+        .await?;
+
+    let prev_hash = sqlx::query_scalar!("SELECT hash FROM audit_events ORDER BY id DESC LIMIT 1")
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or_else(|| GENESIS_HASH.to_vec());
+
+    let occurred_at = Utc::now().trunc_subsecs(6);
+    let hash = event.chain_hash(&prev_hash, occurred_at);
+
+    let stored = sqlx::query_as!(
+        AuditEvent,
+        r#"
+        INSERT INTO audit_events
+            (occurred_at, actor, org_id, action, target_type, target_id, changes, ip, request_id, prev_hash, hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id, occurred_at, actor, org_id, action, target_type, target_id, changes, ip, request_id, prev_hash, hash
+        "#,
+        occurred_at,
+        event.actor,
+        event.org_id,
+        event.action,
+        event.target_type,
+        event.target_id,
+        event.changes,
+        event.ip,
+        event.request_id,
+        prev_hash,
+        hash
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(stored)
+}
+
+/// Events matching `filter`, newest first
+pub async fn get_audit_events(pool: &PgPool, filter: &AuditFilter) -> sqlx::Result<Vec<AuditEvent>> {
+    let events = sqlx::query_as!(
+        AuditEvent,
+        r#"
+        SELECT id, occurred_at, actor, org_id, action, target_type, target_id, changes, ip, request_id, prev_hash, hash
+        FROM audit_events
+        WHERE ($1::TEXT IS NULL OR actor = $1)
+          AND ($2::UUID IS NULL OR org_id = $2)
+          AND ($3::TEXT IS NULL OR action = $3)
+          AND ($4::TEXT IS NULL OR target_type = $4)
+          AND ($5::TEXT IS NULL OR target_id = $5)
+          AND ($6::TIMESTAMPTZ IS NULL OR occurred_at >= $6)
+          AND ($7::TIMESTAMPTZ IS NULL OR occurred_at < $7)
+          AND ($8::BIGINT IS NULL OR id < $8)
+        ORDER BY id DESC
+        LIMIT $9
+        "#,
+        filter.actor,
+        filter.org_id,
+        filter.action,
+        filter.target_type,
+        filter.target_id,
+        filter.since,
+        filter.until,
+        filter.before_id,
+        filter.limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+/// The chain in order, `limit` events after `after_id`, for verification
+pub async fn get_audit_chain(pool: &PgPool, after_id: i64, limit: i64) -> sqlx::Result<Vec<AuditEvent>> {
+    let events = sqlx::query_as!(
+        AuditEvent,
+        r#"
+        SELECT id, occurred_at, actor, org_id, action, target_type, target_id, changes, ip, request_id, prev_hash, hash
+        FROM audit_events
+        WHERE id > $1
+        ORDER BY id
+        LIMIT $2
+        "#,
+        after_id,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+// ─── RETENTION ────────────────────────────────────────────────────────────────
+//
+
+/// Overwrite, then delete, task updates created before `cutoff`.
+/// The overwrite runs in the same transaction so the sealed payload is gone
+/// from the live row before it is removed; old tuple versions still linger
+/// until Postgres vacuums them.
+pub async fn erase_task_updates_before(pool: &PgPool, cutoff: DateTime<Utc>) -> sqlx::Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE task_updates
+        SET nonce = repeat('0', length(nonce)),
+            ciphertext = repeat('0', length(ciphertext)),
+            kem_ciphertext = CASE WHEN kem_ciphertext IS NULL THEN NULL
+                                  ELSE repeat('0', length(kem_ciphertext)) END,
+            associated_data = NULL
+        WHERE created_at < $1
+        "#,
+        cutoff
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let rows_affected = sqlx::query!("DELETE FROM task_updates WHERE created_at < $1", cutoff)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+    Ok(rows_affected)
+}
+
+/// Drop token records that are past their own expiry or older than `cutoff`
+pub async fn delete_expired_tokens(pool: &PgPool, cutoff: DateTime<Utc>) -> sqlx::Result<u64> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM issued_tokens WHERE expires_at < NOW() OR issued_at < $1",
+        cutoff
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected)
+}
+
+pub async fn count_task_updates(pool: &PgPool) -> sqlx::Result<i64> {
+    let count = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM task_updates"#)
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+pub async fn count_issued_tokens(pool: &PgPool) -> sqlx::Result<i64> {
+    let count = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM issued_tokens"#)
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+/// When the oldest task updates expire under a `ttl_secs` policy
+pub async fn next_task_update_expiries(
+    pool: &PgPool,
+    ttl_secs: i64,
+    limit: i64,
+) -> sqlx::Result<Vec<DateTime<Utc>>> {
+    let expiries = sqlx::query_scalar!(
+        r#"
+        SELECT created_at + make_interval(secs => $1::BIGINT::DOUBLE PRECISION) AS "expires_at!"
+        FROM task_updates
+        ORDER BY created_at
+        LIMIT $2
+        "#,
+        ttl_secs,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(expiries)
+}
+
+/// When the soonest-expiring token records go, honouring both `exp` and the `ttl_secs` policy
+pub async fn next_token_expiries(
+    pool: &PgPool,
+    ttl_secs: i64,
+    limit: i64,
+) -> sqlx::Result<Vec<DateTime<Utc>>> {
+    let expiries = sqlx::query_scalar!(
+        r#"
+        SELECT LEAST(expires_at, issued_at + make_interval(secs => $1::BIGINT::DOUBLE PRECISION)) AS "expires_at!"
+        FROM issued_tokens
+        ORDER BY 1
+        LIMIT $2
+        "#,
+        ttl_secs,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(expiries)
+}